@@ -0,0 +1,82 @@
+//! Compares `IsarCollection::put_all` with a loop of `IsarCollection::put` calls.
+//!
+//! Run with `cargo bench --bench put_all`.
+
+use isar_core::instance::IsarInstance;
+use isar_core::object::data_type::DataType;
+use isar_core::object::isar_object::IsarObject;
+use isar_core::schema::collection_schema::{
+    CollectionSchema, IndexPropertySchema, IndexSchema, IndexType, PropertySchema,
+};
+use isar_core::schema::Schema;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+const OBJECTS: i64 = 100_000;
+
+fn schema() -> Schema {
+    let properties = vec![
+        PropertySchema::new("id", DataType::Long),
+        PropertySchema::new("value", DataType::Int),
+        PropertySchema::new("name", DataType::String),
+    ];
+    let indexes = vec![
+        IndexSchema::new(
+            vec![IndexPropertySchema::new("value", IndexType::Value, None)],
+            false,
+            false,
+        ),
+        IndexSchema::new(
+            vec![IndexPropertySchema::new(
+                "name",
+                IndexType::Hash,
+                Some(true),
+            )],
+            false,
+            false,
+        ),
+    ];
+    let collection = CollectionSchema::new("bench", "id", properties, indexes, vec![]);
+    Schema::new(vec![collection]).unwrap()
+}
+
+fn run(batch: bool) -> Duration {
+    let dir = tempdir().unwrap();
+    let path = dir.path().to_str().unwrap();
+    let isar = IsarInstance::open(path, dir.path().into(), 1 << 30, schema(), None).unwrap();
+    let col = isar.get_collection(0).unwrap();
+    let objects: Vec<Vec<u8>> = (0..OBJECTS)
+        .map(|i| {
+            let mut builder = col.new_object_builder(None);
+            // shuffled ids and values so the writes are not already in key order
+            let oid = (i * 7919) % OBJECTS + 1;
+            builder.write_long(oid);
+            builder.write_int((oid * 31 % 1000) as i32);
+            builder.write_string(Some(&format!("name {}", oid % 5000)));
+            builder.finish().as_bytes().to_vec()
+        })
+        .collect();
+    let objects: Vec<IsarObject> = objects.iter().map(|b| IsarObject::from_bytes(b)).collect();
+
+    let start = Instant::now();
+    let mut txn = isar.begin_txn(true, true).unwrap();
+    if batch {
+        col.put_all(&mut txn, &objects).unwrap();
+    } else {
+        for object in &objects {
+            col.put(&mut txn, *object).unwrap();
+        }
+    }
+    txn.commit().unwrap();
+    let elapsed = start.elapsed();
+    isar.close();
+    elapsed
+}
+
+fn main() {
+    for (name, batch) in &[("put loop", false), ("put_all", true)] {
+        let elapsed = run(*batch);
+        let per_second = OBJECTS as f64 / elapsed.as_secs_f64();
+        println!("{:>8}: {:?} ({:.0} objects/s)", name, elapsed, per_second);
+    }
+}
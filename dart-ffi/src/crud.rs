@@ -1,13 +1,17 @@
 use crate::raw_object_set::{RawObject, RawObjectSend, RawObjectSet, RawObjectSetSend};
 use crate::txn::IsarDartTxn;
-use crate::{BoolSend, UintSend};
+use crate::{BoolSend, DoubleSend, LongSend, UintSend};
 use byteorder::{ByteOrder, LittleEndian};
-use isar_core::collection::IsarCollection;
-use isar_core::error::Result;
+use isar_core::collection::{IsarCollection, PutResult};
+use isar_core::error::{illegal_arg, IsarError, Result};
 use isar_core::index::index_key::IndexKey;
-use isar_core::object::isar_object::IsarObject;
+use isar_core::instance::IsarInstance;
+use isar_core::object::data_type::DataType;
+use isar_core::object::isar_object::{IsarObject, IsarValue};
 use isar_core::txn::IsarTxn;
 use serde_json::Value;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[no_mangle]
 pub unsafe extern "C" fn isar_get(
@@ -56,9 +60,10 @@ pub unsafe extern "C" fn isar_get_all(
                 object.set_object(result);
             }
         } else {
-            for object in objects.0.get_objects() {
-                let oid = object.get_oid();
-                let result = collection.get(txn, oid)?;
+            let objects = objects.0.get_objects();
+            let oids: Vec<i64> = objects.iter_mut().map(|o| o.get_oid()).collect();
+            let results = collection.get_all(txn, &oids)?;
+            for (object, result) in objects.iter_mut().zip(results) {
                 object.set_object(result);
             }
         };
@@ -66,6 +71,25 @@ pub unsafe extern "C" fn isar_get_all(
     })
 }
 
+static VALIDATE_OBJECTS: AtomicBool = AtomicBool::new(false);
+
+/// Validates all objects before they are written. This is useful to detect invalid objects
+/// during development.
+#[no_mangle]
+pub extern "C" fn isar_set_validate_objects(validate: bool) {
+    VALIDATE_OBJECTS.store(validate, Ordering::Relaxed);
+}
+
+fn validate_object(collection: &IsarCollection, bytes: &[u8]) -> Result<()> {
+    if VALIDATE_OBJECTS.load(Ordering::Relaxed) {
+        if bytes.len() < 2 {
+            return Err(IsarError::InvalidObject {});
+        }
+        collection.validate(IsarObject::from_bytes(bytes))?;
+    }
+    Ok(())
+}
+
 fn update_auto_increment(
     collection: &IsarCollection,
     txn: &mut IsarTxn,
@@ -87,12 +111,71 @@ pub unsafe extern "C" fn isar_put(
     collection: &'static mut IsarCollection,
     txn: &mut IsarDartTxn,
     object: &'static mut RawObject,
+    inserted: &'static mut bool,
+) -> i32 {
+    let object = RawObjectSend(object);
+    let inserted = BoolSend(inserted);
+    isar_try_txn!(txn, move |txn| {
+        let bytes = object.0.get_bytes();
+        validate_object(collection, bytes)?;
+        let auto_increment = update_auto_increment(collection, txn, bytes)?;
+        let result = collection.put(txn, IsarObject::from_bytes(bytes))?;
+        *inserted.0 = result == PutResult::Inserted;
+        object.0.set_oid(auto_increment);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_put_if_absent(
+    collection: &'static mut IsarCollection,
+    txn: &mut IsarDartTxn,
+    object: &'static mut RawObject,
+    inserted: &'static mut bool,
 ) -> i32 {
     let object = RawObjectSend(object);
+    let inserted = BoolSend(inserted);
     isar_try_txn!(txn, move |txn| {
         let bytes = object.0.get_bytes();
+        validate_object(collection, bytes)?;
         let auto_increment = update_auto_increment(collection, txn, bytes)?;
-        collection.put(txn, IsarObject::from_bytes(bytes))?;
+        *inserted.0 = collection.put_if_absent(txn, IsarObject::from_bytes(bytes))?;
+        object.0.set_oid(auto_increment);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_replace(
+    collection: &'static mut IsarCollection,
+    txn: &mut IsarDartTxn,
+    object: &'static mut RawObject,
+    replaced: &'static mut bool,
+) -> i32 {
+    let object = RawObjectSend(object);
+    let replaced = BoolSend(replaced);
+    isar_try_txn!(txn, move |txn| {
+        let bytes = object.0.get_bytes();
+        validate_object(collection, bytes)?;
+        *replaced.0 = collection.replace(txn, IsarObject::from_bytes(bytes))?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_put_skip_unchanged(
+    collection: &'static mut IsarCollection,
+    txn: &mut IsarDartTxn,
+    object: &'static mut RawObject,
+    written: &'static mut bool,
+) -> i32 {
+    let object = RawObjectSend(object);
+    let written = BoolSend(written);
+    isar_try_txn!(txn, move |txn| {
+        let bytes = object.0.get_bytes();
+        validate_object(collection, bytes)?;
+        let auto_increment = update_auto_increment(collection, txn, bytes)?;
+        *written.0 = collection.put_skip_unchanged(txn, IsarObject::from_bytes(bytes))?;
         object.0.set_oid(auto_increment);
         Ok(())
     })
@@ -106,13 +189,26 @@ pub unsafe extern "C" fn isar_put_all(
 ) -> i32 {
     let objects = RawObjectSetSend(objects);
     isar_try_txn!(txn, move |txn| {
+        let oid_property = collection.get_oid_property();
         for raw_obj in objects.0.get_objects() {
             let bytes = raw_obj.get_bytes();
-            let auto_increment = update_auto_increment(collection, txn, bytes)?;
-            collection.put(txn, IsarObject::from_bytes(bytes))?;
+            validate_object(collection, bytes)?;
+            let object = IsarObject::from_bytes(bytes);
+            if !object.is_null(oid_property) {
+                collection.register_oid(txn, object.read_long(oid_property))?;
+            }
+        }
+        for raw_obj in objects.0.get_objects() {
+            let auto_increment = update_auto_increment(collection, txn, raw_obj.get_bytes())?;
             raw_obj.set_oid(auto_increment)
         }
-        Ok(())
+        let isar_objects: Vec<IsarObject> = objects
+            .0
+            .get_objects()
+            .iter()
+            .map(|raw_obj| IsarObject::from_bytes(raw_obj.get_bytes()))
+            .collect();
+        collection.put_all(txn, &isar_objects)
     })
 }
 
@@ -176,6 +272,70 @@ pub unsafe extern "C" fn isar_delete_all(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_update_long_property(
+    collection: &'static IsarCollection,
+    txn: &mut IsarDartTxn,
+    oid: i64,
+    property_index: u32,
+    delta: i64,
+    value: &'static mut i64,
+) -> i32 {
+    let value = LongSend(value);
+    isar_try_txn!(txn, move |txn| {
+        let property = collection.get_properties().get(property_index as usize);
+        if let Some((_, property)) = property {
+            let delta = if property.data_type == DataType::Int {
+                if let Ok(delta) = i32::try_from(delta) {
+                    IsarValue::Int(delta)
+                } else {
+                    return illegal_arg("The delta is out of range.");
+                }
+            } else {
+                IsarValue::Long(delta)
+            };
+            *value.0 = match collection.update_property(txn, oid, *property, delta)? {
+                IsarValue::Int(new_value) => new_value as i64,
+                IsarValue::Long(new_value) => new_value,
+                _ => unreachable!(),
+            };
+            Ok(())
+        } else {
+            illegal_arg("Property does not exist.")
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_update_double_property(
+    collection: &'static IsarCollection,
+    txn: &mut IsarDartTxn,
+    oid: i64,
+    property_index: u32,
+    delta: f64,
+    value: &'static mut f64,
+) -> i32 {
+    let value = DoubleSend(value);
+    isar_try_txn!(txn, move |txn| {
+        let property = collection.get_properties().get(property_index as usize);
+        if let Some((_, property)) = property {
+            let delta = if property.data_type == DataType::Float {
+                IsarValue::Float(delta as f32)
+            } else {
+                IsarValue::Double(delta)
+            };
+            *value.0 = match collection.update_property(txn, oid, *property, delta)? {
+                IsarValue::Float(new_value) => new_value as f64,
+                IsarValue::Double(new_value) => new_value,
+                _ => unreachable!(),
+            };
+            Ok(())
+        } else {
+            illegal_arg("Property does not exist.")
+        }
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_clear(
     collection: &'static IsarCollection,
@@ -189,6 +349,19 @@ pub unsafe extern "C" fn isar_clear(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_clear_all(
+    isar: &'static IsarInstance,
+    txn: &mut IsarDartTxn,
+    count: &'static mut u32,
+) -> i32 {
+    let count = UintSend(count);
+    isar_try_txn!(txn, move |txn| {
+        *count.0 = isar.clear_all(txn)? as u32;
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_json_import(
     collection: &'static IsarCollection,
@@ -1,5 +1,6 @@
 use isar_core::error::IsarError;
 use once_cell::sync::Lazy;
+use std::cell::RefCell;
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::sync::Mutex;
@@ -7,19 +8,26 @@ use std::sync::Mutex;
 type ErrCounter = (Vec<(i32, String)>, i32);
 static ERRORS: Lazy<Mutex<ErrCounter>> = Lazy::new(|| Mutex::new((vec![], 1)));
 
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = RefCell::new(None);
+}
+
 pub trait DartErrCode {
     fn into_dart_err_code(self) -> i32;
 }
 
 impl DartErrCode for IsarError {
     fn into_dart_err_code(self) -> i32 {
+        let message = self.to_string();
+        LAST_ERROR.with(|last| *last.borrow_mut() = Some(message.clone()));
+
         let mut lock = ERRORS.lock().unwrap();
         let (errors, counter) = &mut (*lock);
         if errors.len() > 10 {
             errors.remove(0);
         }
         let err_code = *counter;
-        errors.push((err_code, self.to_string()));
+        errors.push((err_code, message));
         *counter = counter.wrapping_add(1);
         if *counter == 0 {
             *counter = 1
@@ -72,3 +80,25 @@ pub unsafe extern "C" fn isar_get_error(err_code: i32) -> *mut c_char {
 pub unsafe extern "C" fn isar_free_error(error: *mut c_char) {
     CString::from_raw(error);
 }
+
+/// Copies the message of the last error of the calling thread into `buffer` and returns the full
+/// length of the message or 0 if there was no error. Messages longer than `len` are cut off at a
+/// character boundary. Errors of async operations are reported on a background thread and are
+/// only available using `isar_get_error()`.
+#[no_mangle]
+pub unsafe extern "C" fn isar_get_last_error(buffer: *mut u8, len: u32) -> u32 {
+    LAST_ERROR.with(|last| {
+        if let Some(message) = last.borrow().as_ref() {
+            if !buffer.is_null() {
+                let mut count = message.len().min(len as usize);
+                while !message.is_char_boundary(count) {
+                    count -= 1;
+                }
+                std::ptr::copy_nonoverlapping(message.as_ptr(), buffer, count);
+            }
+            message.len() as u32
+        } else {
+            0
+        }
+    })
+}
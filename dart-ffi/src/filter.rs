@@ -54,6 +54,40 @@ pub unsafe extern "C" fn isar_filter_link(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_reference(
+    collection: &IsarCollection,
+    target_collection: &IsarCollection,
+    filter: *mut *const Filter,
+    condition: *mut Filter,
+    property_index: u32,
+) -> i32 {
+    let property = collection.get_properties().get(property_index as usize);
+    isar_try! {
+        let condition = *Box::from_raw(condition);
+        if let Some((_, property)) = property {
+            let query_filter = ReferenceCond::filter(*property, target_collection, condition)?;
+            let ptr = Box::into_raw(Box::new(query_filter));
+            filter.write(ptr);
+        } else {
+            illegal_arg("Property does not exist.")?;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_id_between(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    lower: i64,
+    upper: i64,
+) -> i32 {
+    let query_filter = IdBetweenCond::filter(collection, lower, upper);
+    let ptr = Box::into_raw(Box::new(query_filter));
+    filter.write(ptr);
+    0
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_filter_null_between(
     collection: &IsarCollection,
@@ -65,7 +99,7 @@ pub unsafe extern "C" fn isar_filter_null_between(
     isar_try! {
         if let Some((_, property)) = property {
             let query_filter = match property.data_type {
-                DataType::Byte => {
+                DataType::Byte | DataType::Bool => {
                     let upper = if upper_unbounded {
                         u8::MAX
                     } else {
@@ -89,7 +123,7 @@ pub unsafe extern "C" fn isar_filter_null_between(
                     };
                     FloatBetweenCond::filter(*property, IsarObject::NULL_FLOAT, upper)?
                 },
-                DataType::Long => {
+                DataType::Long | DataType::DateTime => {
                     let upper = if upper_unbounded {
                         i64::MAX
                     } else {
@@ -105,7 +139,13 @@ pub unsafe extern "C" fn isar_filter_null_between(
                     };
                     DoubleBetweenCond::filter(*property, IsarObject::NULL_DOUBLE, upper)?
                 },
-                DataType::String => StringBetweenCond::filter(*property, None, None, false)?,
+                DataType::String => {
+                    if upper_unbounded {
+                        StaticCond::filter(true)
+                    } else {
+                        StringBetweenCond::filter(*property, None, true, None, true, false)?
+                    }
+                },
                 _ => unreachable!(),
             };
             let ptr = Box::into_raw(Box::new(query_filter));
@@ -186,6 +226,75 @@ pub unsafe extern "C" fn isar_filter_double_between(
     }
 }
 
+#[macro_export]
+macro_rules! filter_in_ffi {
+    ($filter_name:ident, $function_name:ident, $type:ty) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn $function_name(
+            collection: &IsarCollection,
+            filter: *mut *const Filter,
+            values: *const $type,
+            length: u32,
+            property_index: u32,
+        ) -> i32 {
+            let property = collection.get_properties().get(property_index as usize);
+            isar_try! {
+                if let Some((_, property)) = property {
+                    let values = if length > 0 {
+                        slice::from_raw_parts(values, length as usize)
+                    } else {
+                        &[]
+                    };
+                    let query_filter = isar_core::query::filter::$filter_name::filter(*property, values)?;
+                    let ptr = Box::into_raw(Box::new(query_filter));
+                    filter.write(ptr);
+                } else {
+                    illegal_arg("Property does not exist.")?;
+                }
+            }
+        }
+    }
+}
+
+filter_in_ffi!(IntInCond, isar_filter_int_in, i32);
+filter_in_ffi!(LongInCond, isar_filter_long_in, i64);
+
+/// Null pointers in `values` match null strings.
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_string_in(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    values: *const *const c_char,
+    length: u32,
+    case_sensitive: bool,
+    property_index: u32,
+) -> i32 {
+    let property = collection.get_properties().get(property_index as usize);
+    isar_try! {
+        if let Some((_, property)) = property {
+            let values = if length > 0 {
+                slice::from_raw_parts(values, length as usize)
+            } else {
+                &[]
+            };
+            let mut strings = vec![];
+            for value in values {
+                let str = if !value.is_null() {
+                    Some(from_c_str(*value)?)
+                } else {
+                    None
+                };
+                strings.push(str);
+            }
+            let query_filter = StringInCond::filter(*property, &strings, case_sensitive)?;
+            let ptr = Box::into_raw(Box::new(query_filter));
+            filter.write(ptr);
+        } else {
+            illegal_arg("Property does not exist.")?;
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! filter_single_value_ffi {
     ($filter_name:ident, $function_name:ident, $type:ty) => {
@@ -258,7 +367,9 @@ pub unsafe extern "C" fn isar_filter_string_between(
     collection: &IsarCollection,
     filter: *mut *const Filter,
     lower: *const c_char,
+    include_lower: bool,
     upper: *const c_char,
+    include_upper: bool,
     case_sensitive: bool,
     property_index: u32,
 ) -> i32 {
@@ -275,7 +386,82 @@ pub unsafe extern "C" fn isar_filter_string_between(
             } else {
                 None
             };
-            let query_filter = isar_core::query::filter::StringBetweenCond::filter(*property, lower, upper, case_sensitive)?;
+            let query_filter = isar_core::query::filter::StringBetweenCond::filter(*property, lower, include_lower, upper, include_upper, case_sensitive)?;
+            let ptr = Box::into_raw(Box::new(query_filter));
+            filter.write(ptr);
+        } else {
+            illegal_arg("Property does not exist.")?;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_list_length(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    lower: u32,
+    upper: u32,
+    null_as_empty: bool,
+    property_index: u32,
+) -> i32 {
+    let property = collection.get_properties().get(property_index as usize);
+    isar_try! {
+        if let Some((_, property)) = property {
+            let query_filter = isar_core::query::filter::ListLengthCond::filter(*property, lower as usize, upper as usize, null_as_empty)?;
+            let ptr = Box::into_raw(Box::new(query_filter));
+            filter.write(ptr);
+        } else {
+            illegal_arg("Property does not exist.")?;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_string_regex(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    pattern: *const c_char,
+    case_sensitive: bool,
+    property_index: u32,
+) -> i32 {
+    let property = collection.get_properties().get(property_index as usize);
+    isar_try! {
+        if let Some((_, property)) = property {
+            if pattern.is_null() {
+                illegal_arg("Pattern must not be null.")?;
+            }
+            let pattern = from_c_str(pattern)?;
+            let query_filter = isar_core::query::filter::StringRegexCond::filter(*property, pattern, case_sensitive)?;
+            let ptr = Box::into_raw(Box::new(query_filter));
+            filter.write(ptr);
+        } else {
+            illegal_arg("Property does not exist.")?;
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_string_similar(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    value: *const c_char,
+    max_distance: u32,
+    case_sensitive: bool,
+    property_index: u32,
+) -> i32 {
+    let property = collection.get_properties().get(property_index as usize);
+    isar_try! {
+        if let Some((_, property)) = property {
+            if value.is_null() {
+                illegal_arg("Value must not be null.")?;
+            }
+            let value = from_c_str(value)?;
+            let query_filter = isar_core::query::filter::StringSimilarCond::filter(
+                *property,
+                value,
+                max_distance,
+                case_sensitive,
+            )?;
             let ptr = Box::into_raw(Box::new(query_filter));
             filter.write(ptr);
         } else {
@@ -316,5 +502,40 @@ macro_rules! filter_string_ffi {
 
 filter_string_ffi!(StringStartsWithCond, isar_filter_string_starts_with);
 filter_string_ffi!(StringEndsWithCond, isar_filter_string_ends_with);
-filter_string_ffi!(StringMatchesCond, isar_filter_string_matches);
+filter_string_ffi!(StringWildcardCond, isar_filter_string_wildcard);
+filter_string_ffi!(StringWildcardCond, isar_filter_string_matches);
 filter_string_ffi!(StringListContainsCond, isar_filter_string_list_contains);
+filter_string_ffi!(StringWordCond, isar_filter_string_word);
+
+#[macro_export]
+macro_rules! filter_bytes_ffi {
+    ($filter_name:ident, $function_name:ident) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn $function_name(
+            collection: &IsarCollection,
+            filter: *mut *const Filter,
+            value: *const u8,
+            length: u32,
+            property_index: u32,
+        ) -> i32 {
+            let property = collection.get_properties().get(property_index as usize);
+            isar_try! {
+                if let Some((_, property)) = property {
+                    let bytes = if !value.is_null() {
+                        Some(slice::from_raw_parts(value, length as usize))
+                    } else {
+                        None
+                    };
+                    let query_filter = isar_core::query::filter::$filter_name::filter(*property, bytes)?;
+                    let ptr = Box::into_raw(Box::new(query_filter));
+                    filter.write(ptr);
+                } else {
+                    illegal_arg("Property does not exist.")?;
+                }
+            }
+        }
+    }
+}
+
+filter_bytes_ffi!(BytesEqualCond, isar_filter_bytes_equal);
+filter_bytes_ffi!(BytesStartsWithCond, isar_filter_bytes_starts_with);
@@ -16,6 +16,7 @@ pub mod instance;
 pub mod link;
 pub mod query;
 pub mod query_aggregation;
+pub mod query_cursor;
 pub mod raw_object_set;
 pub mod txn;
 pub mod watchers;
@@ -34,3 +35,11 @@ unsafe impl Send for UintSend {}
 pub struct BoolSend(&'static mut bool);
 
 unsafe impl Send for BoolSend {}
+
+pub struct LongSend(&'static mut i64);
+
+unsafe impl Send for LongSend {}
+
+pub struct DoubleSend(&'static mut f64);
+
+unsafe impl Send for DoubleSend {}
@@ -6,7 +6,7 @@ use isar_core::error::illegal_arg;
 use isar_core::index::index_key::IndexKey;
 use isar_core::query::filter::Filter;
 use isar_core::query::query_builder::QueryBuilder;
-use isar_core::query::{Query, Sort};
+use isar_core::query::{Case, Query, Sort};
 
 #[no_mangle]
 pub extern "C" fn isar_qb_create(collection: &IsarCollection) -> *mut QueryBuilder {
@@ -86,7 +86,7 @@ pub unsafe extern "C" fn isar_qb_add_sort_by(
     };
     isar_try! {
         if let Some((_,property)) = property {
-            builder.add_sort(*property, sort);
+            builder.add_sort(*property, sort, Case::Sensitive);
         } else {
             illegal_arg("Property does not exist.")?;
         }
@@ -99,17 +99,23 @@ pub unsafe extern "C" fn isar_qb_add_distinct_by(
     builder: &mut QueryBuilder,
     property_index: u32,
     case_sensitive: bool,
+    treat_nulls_distinct: bool,
 ) -> i32 {
     let property = collection.get_properties().get(property_index as usize);
     isar_try! {
         if let Some((_,property)) = property {
-            builder.add_distinct(*property, case_sensitive);
+            builder.add_distinct(*property, case_sensitive, treat_nulls_distinct);
         } else {
             illegal_arg("Property does not exist.")?;
         }
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_qb_skip_default_sort(builder: &mut QueryBuilder) {
+    builder.skip_default_sort();
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_qb_set_offset_limit(
     builder: &mut QueryBuilder,
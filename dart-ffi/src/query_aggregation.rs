@@ -4,9 +4,9 @@ use isar_core::collection::IsarCollection;
 use isar_core::error::Result;
 use isar_core::object::data_type::DataType;
 use isar_core::object::isar_object::{IsarObject, Property};
+use isar_core::query::aggregation::{AggregateValue, AggregationOp};
 use isar_core::query::Query;
 use isar_core::txn::IsarTxn;
-use std::cmp::Ordering;
 
 #[derive(Debug)]
 pub enum AggregationResult {
@@ -15,116 +15,21 @@ pub enum AggregationResult {
     Null,
 }
 
-#[derive(Ordinalize, PartialEq)]
-#[repr(u8)]
-pub enum AggregationOp {
-    Min,
-    Max,
-    Sum,
-    Average,
-    Count,
-}
-
 fn aggregate(
     query: &Query,
     txn: &mut IsarTxn,
     op: AggregationOp,
-    property: Option<Property>,
+    property: Property,
 ) -> Result<AggregationResult> {
-    let mut count = 0usize;
-
-    let (mut long_value, mut double_value) = if op == AggregationOp::Min {
-        (i64::MAX, f64::INFINITY)
-    } else if op == AggregationOp::Max {
-        (i64::MIN, f64::NEG_INFINITY)
-    } else {
-        (0, 0.0)
-    };
-
-    let min_max_cmp = if op == AggregationOp::Max {
-        Ordering::Greater
-    } else {
-        Ordering::Less
-    };
-
-    query.find_while(txn, |obj| {
-        if op == AggregationOp::Count {
-            count += 1;
-            return true;
-        }
-
-        let property = property.unwrap();
-        if obj.is_null(property) {
-            return true;
-        }
-
-        count += 1;
-        match op {
-            AggregationOp::Min | AggregationOp::Max => match property.data_type {
-                DataType::Int | DataType::Long => {
-                    let value = if property.data_type == DataType::Int {
-                        obj.read_int(property) as i64
-                    } else {
-                        obj.read_long(property)
-                    };
-                    if value.cmp(&long_value) == min_max_cmp {
-                        long_value = value;
-                    }
-                }
-                DataType::Float | DataType::Double => {
-                    let value = if property.data_type == DataType::Float {
-                        obj.read_float(property) as f64
-                    } else {
-                        obj.read_double(property)
-                    };
-                    if value > double_value && min_max_cmp == Ordering::Greater {
-                        double_value = value;
-                    } else if value < double_value && min_max_cmp == Ordering::Less {
-                        double_value = value;
-                    }
-                }
-                _ => unreachable!(),
-            },
-            AggregationOp::Sum | AggregationOp::Average => match property.data_type {
-                DataType::Int => {
-                    long_value = long_value.saturating_add(obj.read_int(property) as i64)
-                }
-                DataType::Long => long_value = long_value.saturating_add(obj.read_long(property)),
-                DataType::Float => double_value += obj.read_float(property) as f64,
-                DataType::Double => double_value += obj.read_double(property),
-                _ => unreachable!(),
-            },
-            _ => unreachable!(),
-        }
-        true
-    })?;
-
-    match op {
-        AggregationOp::Min | AggregationOp::Max | AggregationOp::Average => {
-            if count == 0 {
-                return Ok(AggregationResult::Null);
-            }
-        }
-        _ => {}
-    }
-
-    let result = match op {
-        AggregationOp::Average => {
-            let result = match property.unwrap().data_type {
-                DataType::Int | DataType::Long => (long_value as f64) / (count as f64),
-                DataType::Float | DataType::Double => double_value / (count as f64),
-                _ => unreachable!(),
-            };
-            AggregationResult::Double(result)
-        }
-        AggregationOp::Count => AggregationResult::Long(count as i64),
-        _ => match property.unwrap().data_type {
-            DataType::Int | DataType::Long => AggregationResult::Long(long_value),
-            DataType::Float | DataType::Double => AggregationResult::Double(double_value),
-            _ => unreachable!(),
+    let result = match query.aggregate(txn, property, op)? {
+        Some(AggregateValue::Long(value)) => AggregationResult::Long(value),
+        Some(AggregateValue::Double(value)) => AggregationResult::Double(value),
+        None if op == AggregationOp::Sum => match property.data_type {
+            DataType::Float | DataType::Double => AggregationResult::Double(0.0),
+            _ => AggregationResult::Long(0),
         },
+        None => AggregationResult::Null,
     };
-
     Ok(result)
 }
 
@@ -147,9 +52,9 @@ pub unsafe extern "C" fn isar_q_aggregate(
             .get_properties()
             .get(property_index as usize)
             .unwrap();
-        Some(*p)
+        *p
     } else {
-        None
+        collection.get_oid_property()
     };
 
     let result = AggregationResultSend(result);
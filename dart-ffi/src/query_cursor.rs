@@ -0,0 +1,140 @@
+use crate::raw_object_set::{RawObject, RawObjectSet};
+use isar_core::error::{illegal_arg, IsarError, Result};
+use isar_core::instance::IsarInstance;
+use isar_core::query::Query;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+struct RawObjectBatch(Vec<RawObject>);
+
+unsafe impl Send for RawObjectBatch {}
+
+/// Streams the results of a query in batches. The query runs on a separate thread in its own
+/// read transaction and pauses after each batch. The transaction stays open until the cursor is
+/// closed so the objects of all batches stay valid.
+pub struct QueryCursor {
+    requests: Option<SyncSender<usize>>,
+    batches: Receiver<Result<RawObjectBatch>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl QueryCursor {
+    fn open(isar: &'static IsarInstance, query: &'static Query) -> QueryCursor {
+        let (requests_tx, requests_rx) = mpsc::sync_channel(0);
+        let (batches_tx, batches_rx) = mpsc::sync_channel(0);
+        let handle = thread::spawn(move || Self::run(isar, query, requests_rx, batches_tx));
+        QueryCursor {
+            requests: Some(requests_tx),
+            batches: batches_rx,
+            handle: Some(handle),
+        }
+    }
+
+    fn run(
+        isar: &'static IsarInstance,
+        query: &'static Query,
+        requests: Receiver<usize>,
+        batches: SyncSender<Result<RawObjectBatch>>,
+    ) {
+        let mut txn = match isar.begin_txn(false, false) {
+            Ok(txn) => txn,
+            Err(e) => {
+                if requests.recv().is_ok() {
+                    let _ = batches.send(Err(e));
+                }
+                return;
+            }
+        };
+        let mut batch_size = if let Ok(batch_size) = requests.recv() {
+            batch_size
+        } else {
+            txn.abort();
+            return;
+        };
+
+        let mut batch = vec![];
+        let result = query.find_while(&mut txn, |object| {
+            let mut raw_obj = RawObject::new();
+            raw_obj.set_object(Some(object));
+            batch.push(raw_obj);
+            if batch.len() < batch_size {
+                return true;
+            }
+            let full_batch = RawObjectBatch(std::mem::take(&mut batch));
+            if batches.send(Ok(full_batch)).is_err() {
+                return false;
+            }
+            if let Ok(next_batch_size) = requests.recv() {
+                batch_size = next_batch_size;
+                true
+            } else {
+                false
+            }
+        });
+
+        if batches.send(result.map(|_| RawObjectBatch(batch))).is_ok() {
+            // all results have been returned
+            while requests.recv().is_ok() {
+                if batches.send(Ok(RawObjectBatch(vec![]))).is_err() {
+                    break;
+                }
+            }
+        }
+        txn.abort();
+    }
+
+    /// Returns the next `batch_size` results. An empty batch means that there are no more
+    /// results.
+    fn next(&mut self, batch_size: usize) -> Result<Vec<RawObject>> {
+        if batch_size == 0 {
+            return illegal_arg("The batch size needs to be at least one.");
+        }
+        let requests = self.requests.as_ref().unwrap();
+        if requests.send(batch_size).is_err() {
+            return Err(IsarError::TransactionClosed {});
+        }
+        match self.batches.recv() {
+            Ok(Ok(batch)) => Ok(batch.0),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(IsarError::TransactionClosed {}),
+        }
+    }
+
+    fn close(mut self) {
+        self.requests.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_query_cursor_open(
+    isar: &'static IsarInstance,
+    query: &'static Query,
+    cursor: *mut *mut QueryCursor,
+) -> i32 {
+    isar_try! {
+        let new_cursor = QueryCursor::open(isar, query);
+        cursor.write(Box::into_raw(Box::new(new_cursor)));
+    }
+}
+
+/// The objects of `batch` stay valid until the cursor is closed. The batch has to be freed
+/// using `isar_free_raw_obj_list()`.
+#[no_mangle]
+pub unsafe extern "C" fn isar_query_cursor_next(
+    cursor: &mut QueryCursor,
+    batch: &mut RawObjectSet,
+    batch_size: u32,
+) -> i32 {
+    isar_try! {
+        let objects = cursor.next(batch_size as usize)?;
+        batch.fill_from_vec(objects);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_query_cursor_close(cursor: *mut QueryCursor) {
+    Box::from_raw(cursor).close();
+}
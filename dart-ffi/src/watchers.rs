@@ -42,10 +42,12 @@ pub extern "C" fn isar_watch_query(
     collection: &IsarCollection,
     query: &Query,
     port: DartPort,
+    fire_immediately: bool,
 ) -> *mut WatchHandle {
     let handle = isar.watch_query(
         collection,
         query.clone(),
+        fire_immediately,
         Box::new(move || {
             dart_post_int(port, 1);
         }),
@@ -0,0 +1,80 @@
+use crate::error::{IsarError, Result};
+use crate::instance::IsarInstance;
+use crate::txn::IsarTxn;
+
+/// Writes large amounts of data in a series of write transactions to keep the number of dirty
+/// pages small. LMDB keeps all changes of a write transaction in memory until it is committed
+/// and cannot flush parts of an open transaction, so a checkpoint commits the current
+/// transaction and begins a new one.
+///
+/// Only the writes between two checkpoints are atomic. Writes committed by previous checkpoints
+/// are kept if a later write fails or the writer is dropped; `committed()` tells how many writes
+/// can be skipped when the import is resumed.
+pub struct BatchWriter<'a> {
+    isar: &'a IsarInstance,
+    silent: bool,
+    txn: Option<IsarTxn<'a>>,
+    chunk_size: usize,
+    pending: usize,
+    committed: usize,
+}
+
+impl<'a> BatchWriter<'a> {
+    pub(crate) fn new(isar: &'a IsarInstance, silent: bool, chunk_size: usize) -> Result<Self> {
+        let txn = isar.begin_txn(true, silent)?;
+        Ok(BatchWriter {
+            isar,
+            silent,
+            txn: Some(txn),
+            chunk_size,
+            pending: 0,
+            committed: 0,
+        })
+    }
+
+    /// Runs `job` in the current transaction and creates a checkpoint after every `chunk_size`
+    /// writes. If `job` fails, the pending writes are discarded and the writer is closed.
+    pub fn write<T>(&mut self, job: impl FnOnce(&mut IsarTxn<'a>) -> Result<T>) -> Result<T> {
+        let txn = self.txn.as_mut().ok_or(IsarError::TransactionClosed {})?;
+        match job(txn) {
+            Ok(result) => {
+                self.pending += 1;
+                if self.pending >= self.chunk_size {
+                    self.checkpoint()?;
+                }
+                Ok(result)
+            }
+            Err(e) => {
+                self.txn.take();
+                Err(e)
+            }
+        }
+    }
+
+    /// Commits the pending writes and begins a new transaction.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        let txn = self.txn.take().ok_or(IsarError::TransactionClosed {})?;
+        txn.commit()?;
+        self.committed += self.pending;
+        self.pending = 0;
+        self.txn = Some(self.isar.begin_txn(true, self.silent)?);
+        Ok(())
+    }
+
+    /// The number of writes that have been committed.
+    pub fn committed(&self) -> usize {
+        self.committed
+    }
+
+    /// The number of writes that will be committed by the next checkpoint.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Commits the pending writes and returns the number of committed writes.
+    pub fn finish(mut self) -> Result<usize> {
+        let txn = self.txn.take().ok_or(IsarError::TransactionClosed {})?;
+        txn.commit()?;
+        Ok(self.committed + self.pending)
+    }
+}
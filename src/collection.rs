@@ -1,17 +1,25 @@
 use crate::error::{illegal_arg, IsarError, Result};
-use crate::index::index_key::IndexKey;
+use crate::index::index_key::{IndexKey, IndexKeyBuilder};
+use crate::index::{Index, IndexOptimizeStats};
 use crate::link::Link;
-use crate::lmdb::{verify_id, IntKey, MAX_ID, MIN_ID};
-use crate::object::isar_object::{IsarObject, Property};
+use crate::lmdb::ByteKey;
+use crate::lmdb::{verify_id, IntKey, Key, MAX_ID, MIN_ID};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::{IsarObject, IsarValue, Property};
 use crate::object::json_encode_decode::JsonEncodeDecode;
 use crate::object::object_builder::ObjectBuilder;
 use crate::object::object_info::ObjectInfo;
 use crate::query::id_where_clause::IdWhereClause;
+use crate::query::index_where_clause::IndexWhereClause;
 use crate::query::query_builder::QueryBuilder;
-use crate::query::Sort;
+use crate::query::query_plan::WhereClausePlan;
+use crate::query::{Query, Sort};
+use crate::raw_cursor::{RawCursor, RawCursorScope};
+use crate::schema::collection_schema::IndexType;
 use crate::txn::{Cursors, IsarTxn};
 use crate::watch::change_set::ChangeSet;
-use crate::{index::Index, lmdb::ByteKey};
+use crate::watch::watcher::ChangeKind;
+use byteorder::{ByteOrder, LittleEndian};
 use serde_json::Value;
 use std::cell::Cell;
 use std::ops::Add;
@@ -19,6 +27,14 @@ use std::ops::Add;
 #[cfg(test)]
 use {crate::utils::debug::dump_db_oid, hashbrown::HashMap};
 
+/// Whether `IsarCollection::put()` inserted a new object or replaced an existing object with
+/// the same id.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PutResult {
+    Inserted,
+    Updated,
+}
+
 pub struct IsarCollection {
     id: u16,
     name: String,
@@ -26,6 +42,7 @@ pub struct IsarCollection {
     indexes: Vec<Index>,
     links: Vec<(String, Link)>,
     backlinks: Vec<Link>,
+    default_sort: Vec<(Property, Sort, bool)>,
     oid_counter: Cell<i64>,
 }
 
@@ -40,6 +57,7 @@ impl IsarCollection {
         indexes: Vec<Index>,
         links: Vec<(String, Link)>,
         backlinks: Vec<Link>,
+        default_sort: Vec<(Property, Sort, bool)>,
     ) -> Self {
         IsarCollection {
             id,
@@ -48,6 +66,7 @@ impl IsarCollection {
             indexes,
             links,
             backlinks,
+            default_sort,
             oid_counter: Cell::new(0),
         }
     }
@@ -60,6 +79,10 @@ impl IsarCollection {
         &self.indexes
     }
 
+    pub(crate) fn get_default_sort(&self) -> &[(Property, Sort, bool)] {
+        &self.default_sort
+    }
+
     pub(crate) fn update_oid_counter(&self, counter: i64) {
         if counter > self.oid_counter.get() {
             self.oid_counter.set(counter);
@@ -93,10 +116,123 @@ impl IsarCollection {
         QueryBuilder::new(self)
     }
 
+    /// Returns whether all where clauses of `query` scan an index of this collection or a limited
+    /// id range instead of the whole collection. The query is not executed.
+    pub fn query_uses_index(&self, query: &Query) -> bool {
+        query.explain().where_clauses.iter().all(|wc| match wc {
+            WhereClausePlan::IdRange { lower, upper, .. } => *lower > MIN_ID || *upper < MAX_ID,
+            WhereClausePlan::IndexRange { index_id, .. } => {
+                self.indexes.iter().any(|index| index.id == *index_id)
+            }
+        })
+    }
+
     pub fn new_index_key(&self, index_index: usize) -> Option<IndexKey> {
         self.indexes.get(index_index).map(|i| IndexKey::new(i))
     }
 
+    pub fn new_compound_index_key(&self, index_index: usize) -> Result<IndexKeyBuilder> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        Ok(IndexKeyBuilder::new(index))
+    }
+
+    /// Creates the key of a single property string hash index. `value` is hashed with the case
+    /// sensitivity of the index so the key can be used for equality where clauses.
+    pub fn new_hash_index_key(&self, index_index: usize, value: Option<&str>) -> Result<IndexKey> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        if index.properties.len() != 1 {
+            return illegal_arg("Hash index keys require a single property index.");
+        }
+        let mut key = IndexKeyBuilder::new(index);
+        key.add_string_hash(value)?;
+        Ok(key.finish())
+    }
+
+    /// Creates a where clause for all objects with a string starting with `prefix`. The string
+    /// has to be the first property of the index. An empty prefix matches all non-null strings.
+    pub(crate) fn new_index_prefix_where_clause(
+        &self,
+        index_index: usize,
+        prefix: &str,
+        case_sensitive: bool,
+        sort: Sort,
+    ) -> Result<IndexWhereClause> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        let ip = index.properties[0];
+        if ip.property.data_type != DataType::String || ip.index_type != IndexType::Value {
+            return illegal_arg("Index needs to start with a string value property.");
+        }
+        if ip.descending {
+            return illegal_arg("Prefix scans are not supported for descending index properties.");
+        }
+        let mut lower = IndexKey::new(index);
+        lower.add_string_prefix(prefix, case_sensitive, false)?;
+        let mut upper = IndexKey::new(index);
+        upper.add_string_prefix(prefix, case_sensitive, true)?;
+        IndexWhereClause::new(lower, true, upper, true, false, sort)
+    }
+
+    /// Creates a where clause for all objects whose indexed property is null or, if `is_null` is
+    /// false, not null. Null values have the smallest key so both ranges are adjacent.
+    pub(crate) fn new_index_null_where_clause(
+        &self,
+        index_index: usize,
+        is_null: bool,
+        sort: Sort,
+    ) -> Result<IndexWhereClause> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        if index.properties.len() != 1 || index.properties[0].index_type != IndexType::Value {
+            return illegal_arg("Null where clauses require a single property value index.");
+        }
+        let ip = index.properties[0];
+        if ip.descending {
+            return illegal_arg("Null where clauses are not supported for descending indexes.");
+        }
+        if is_null && index.sparse {
+            return illegal_arg("Sparse indexes do not contain null values.");
+        }
+        let mut null = IndexKey::new(index);
+        let mut max = IndexKey::new(index);
+        match ip.property.data_type {
+            DataType::Int => {
+                null.add_int(IsarObject::NULL_INT);
+                max.add_int(i32::MAX);
+            }
+            DataType::Long | DataType::DateTime => {
+                null.add_long(IsarObject::NULL_LONG);
+                max.add_long(i64::MAX);
+            }
+            DataType::Float => {
+                null.add_float(IsarObject::NULL_FLOAT);
+                max.add_float(f32::INFINITY);
+            }
+            DataType::Double => {
+                null.add_double(IsarObject::NULL_DOUBLE);
+                max.add_double(f64::INFINITY);
+            }
+            DataType::String => {
+                let case_sensitive = ip.case_sensitive.unwrap_or(true);
+                null.add_string_value(None, case_sensitive);
+                max.add_string_prefix("", case_sensitive, true)?;
+            }
+            // null bytes can't be distinguished from 0 and list indexes contain the elements
+            _ => return illegal_arg("The indexed property does not have null values."),
+        }
+        if is_null {
+            IndexWhereClause::new(null.clone(), true, null, true, false, sort)
+        } else {
+            IndexWhereClause::new(null, false, max, true, false, sort)
+        }
+    }
+
     pub(crate) fn verify_index_key(&self, key: &IndexKey) -> Result<()> {
         if key.index.get_col_id() != self.id {
             return illegal_arg("Invalid IndexKey for this collection");
@@ -129,6 +265,47 @@ impl IsarCollection {
         })
     }
 
+    pub fn existing_ids(&self, txn: &mut IsarTxn, oids: &[i64]) -> Result<Vec<bool>> {
+        for oid in oids {
+            verify_id(*oid)?;
+        }
+        let mut sorted: Vec<usize> = (0..oids.len()).collect();
+        sorted.sort_unstable_by_key(|i| oids[*i]);
+        txn.read(|cursors| {
+            let mut result = vec![false; oids.len()];
+            for i in sorted {
+                result[i] = cursors
+                    .data
+                    .move_to(IntKey::new(self.id, oids[i]))?
+                    .is_some();
+            }
+            Ok(result)
+        })
+    }
+
+    /// Returns the objects in the order of `oids` and `None` for objects that do not exist.
+    pub fn get_all<'txn>(
+        &self,
+        txn: &'txn mut IsarTxn,
+        oids: &[i64],
+    ) -> Result<Vec<Option<IsarObject<'txn>>>> {
+        for oid in oids {
+            verify_id(*oid)?;
+        }
+        let mut sorted: Vec<usize> = (0..oids.len()).collect();
+        sorted.sort_unstable_by_key(|i| oids[*i]);
+        txn.read(|cursors| {
+            let mut result = vec![None; oids.len()];
+            for i in sorted {
+                result[i] = cursors
+                    .data
+                    .move_to(IntKey::new(self.id, oids[i]))?
+                    .map(|(_, v)| IsarObject::from_bytes(v));
+            }
+            Ok(result)
+        })
+    }
+
     pub fn get_by_index<'txn>(
         &self,
         txn: &'txn mut IsarTxn,
@@ -149,37 +326,339 @@ impl IsarCollection {
         })
     }
 
-    pub fn put(&self, txn: &mut IsarTxn, object: IsarObject) -> Result<()> {
+    pub fn count_by_index(
+        &self,
+        txn: &mut IsarTxn,
+        index_index: usize,
+    ) -> Result<Vec<(Vec<u8>, u64)>> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        txn.read(|cursors| index.count_keys(cursors))
+    }
+
+    pub(crate) fn count_index_entries(&self, txn: &mut IsarTxn) -> Result<u64> {
+        txn.read(|cursors| {
+            let mut entries = 0;
+            for index in &self.indexes {
+                entries += index
+                    .count_keys(cursors)?
+                    .iter()
+                    .map(|(_, count)| count)
+                    .sum::<u64>();
+            }
+            Ok(entries)
+        })
+    }
+
+    /// Returns the number of bytes used by the objects and index entries of this collection.
+    /// The LMDB page overhead is not included.
+    pub fn storage_size(&self, txn: &mut IsarTxn) -> Result<u64> {
+        txn.read(|cursors| {
+            let mut size = 0;
+            IdWhereClause::new(self, MIN_ID, MAX_ID, Sort::Ascending).iter(
+                &mut cursors.data,
+                None,
+                |_, id, object| {
+                    size += (id.as_bytes().len() + object.byte_len()) as u64;
+                    Ok(true)
+                },
+            )?;
+            for index in &self.indexes {
+                size += index.byte_size(cursors)?;
+            }
+            Ok(size)
+        })
+    }
+
+    pub fn optimize_index(
+        &self,
+        txn: &mut IsarTxn,
+        index_index: usize,
+    ) -> Result<IndexOptimizeStats> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        if index.unique {
+            return illegal_arg("Only non-unique indexes can be optimized.");
+        }
+        txn.write(|cursors, _| index.optimize(cursors))
+    }
+
+    pub fn raw_cursor<'a, 'txn>(&self, txn: &'a mut IsarTxn<'txn>) -> Result<RawCursor<'a, 'txn>> {
+        let cursors = txn.read_only_cursors()?;
+        Ok(RawCursor::new(
+            &mut cursors.data,
+            RawCursorScope::Data(self.id),
+        ))
+    }
+
+    pub fn raw_index_cursor<'a, 'txn>(
+        &self,
+        txn: &'a mut IsarTxn<'txn>,
+        index_index: usize,
+    ) -> Result<RawCursor<'a, 'txn>> {
+        let index = self.indexes.get(index_index).ok_or(IsarError::IllegalArg {
+            message: "Index does not exist".to_string(),
+        })?;
+        let cursors = txn.read_only_cursors()?;
+        Ok(RawCursor::new(
+            &mut cursors.index,
+            RawCursorScope::Index(index.get_prefix()),
+        ))
+    }
+
+    /// Checks that the object matches the schema of this collection, that all dynamic data is
+    /// within the bounds of the object and that all strings are valid UTF-8. Objects are validated
+    /// automatically by `put()` in debug builds.
+    pub fn validate(&self, object: IsarObject) -> Result<()> {
+        if self.object_info.verify_object(object) {
+            Ok(())
+        } else {
+            Err(IsarError::InvalidObject {})
+        }
+    }
+
+    pub fn put(&self, txn: &mut IsarTxn, object: IsarObject) -> Result<PutResult> {
         txn.write(|cursors, change_set| self.put_internal(cursors, change_set, object))
     }
 
+    /// Inserts the object only if there is no object with the same id. Returns whether the object
+    /// was inserted.
+    pub fn put_if_absent(&self, txn: &mut IsarTxn, object: IsarObject) -> Result<bool> {
+        self.put_if_exists(txn, object, false)
+    }
+
+    /// Replaces an existing object with the same id. Returns whether the object was replaced.
+    pub fn replace(&self, txn: &mut IsarTxn, object: IsarObject) -> Result<bool> {
+        self.put_if_exists(txn, object, true)
+    }
+
+    fn put_if_exists(&self, txn: &mut IsarTxn, object: IsarObject, exists: bool) -> Result<bool> {
+        txn.write(|cursors, change_set| {
+            let oid = object.read_long(self.get_oid_property());
+            verify_id(oid)?;
+            let existing = cursors.data.move_to(IntKey::new(self.id, oid))?;
+            if existing.is_some() == exists {
+                self.put_internal(cursors, change_set, object)?;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        })
+    }
+
+    /// Puts the object unless the stored object with the same id is byte-identical. Unchanged
+    /// objects don't touch the database or notify watchers. Returns whether the object was written.
+    pub fn put_skip_unchanged(&self, txn: &mut IsarTxn, object: IsarObject) -> Result<bool> {
+        txn.write(|cursors, change_set| {
+            let oid = object.read_long(self.get_oid_property());
+            verify_id(oid)?;
+            let existing = cursors.data.move_to(IntKey::new(self.id, oid))?;
+            if let Some((_, existing)) = existing {
+                if existing == object.as_bytes() {
+                    return Ok(false);
+                }
+            }
+            self.put_internal(cursors, change_set, object)?;
+            Ok(true)
+        })
+    }
+
+    /// Puts all objects in a single write using the same cursors. The objects are written in
+    /// their original order so later objects replace earlier ones. The non-unique index keys of
+    /// new objects are collected and inserted in key order once no later write depends on them.
+    pub fn put_all(&self, txn: &mut IsarTxn, objects: &[IsarObject]) -> Result<()> {
+        // replaced objects may be deleted from any index, so all keys have to be written at once
+        let batch_keys = !self
+            .indexes
+            .iter()
+            .any(|index| index.unique && index.replace);
+        txn.write(|cursors, mut change_set| {
+            let mut keys = vec![];
+            for object in objects {
+                let keys = if batch_keys { Some(&mut keys) } else { None };
+                self.put_object(cursors, change_set.as_deref_mut(), *object, keys)?;
+            }
+            Index::put_batch_keys(cursors, &mut keys)
+        })
+    }
+
+    /// Makes sure auto increment ids assigned later don't collide with `oid`. `put` does this
+    /// itself, but a batch has to register its explicit ids before assigning auto increment ids.
+    pub fn register_oid(&self, _txn: &mut IsarTxn, oid: i64) -> Result<()> {
+        verify_id(oid)?;
+        self.update_oid_counter(oid);
+        Ok(())
+    }
+
     fn put_internal(
+        &self,
+        cursors: &mut Cursors,
+        change_set: Option<&mut ChangeSet>,
+        object: IsarObject,
+    ) -> Result<PutResult> {
+        self.put_object(cursors, change_set, object, None)
+    }
+
+    /// If `batch_keys` is provided, the non-unique index keys of new objects are added to it
+    /// instead of being written. Pending keys are written before an existing object is updated
+    /// since its old keys may still be pending.
+    fn put_object(
         &self,
         cursors: &mut Cursors,
         mut change_set: Option<&mut ChangeSet>,
         object: IsarObject,
-    ) -> Result<()> {
+        batch_keys: Option<&mut Vec<(Vec<u8>, IntKey)>>,
+    ) -> Result<PutResult> {
         let oid = object.read_long(self.get_oid_property());
         verify_id(oid)?;
-        self.delete_internal(cursors, false, change_set.as_deref_mut(), oid)?;
         self.update_oid_counter(oid);
 
-        if !self.object_info.verify_object(object) {
-            return Err(IsarError::InvalidObject {});
+        if cfg!(debug_assertions) {
+            self.validate(object)?;
         }
 
-        for index in &self.indexes {
-            index.create_for_object(cursors, oid, object, |cursors, id| {
-                self.delete_internal(cursors, true, change_set.as_deref_mut(), id)?;
-                Ok(())
-            })?;
-        }
+        // copied because replacing objects of unique indexes may modify the data db
+        let existing = cursors
+            .data
+            .move_to(IntKey::new(self.id, oid))?
+            .map(|(_, bytes)| bytes.to_vec());
+        let result = if let Some(existing) = &existing {
+            if let Some(batch_keys) = batch_keys {
+                Index::put_batch_keys(cursors, batch_keys)?;
+            }
+            let existing = IsarObject::from_bytes(existing);
+            self.register_object_change(
+                change_set.as_deref_mut(),
+                oid,
+                ChangeKind::Delete,
+                existing,
+            );
+            for index in &self.indexes {
+                index.update_for_object(cursors, oid, existing, object, |cursors, id| {
+                    self.delete_internal(cursors, true, change_set.as_deref_mut(), id)?;
+                    Ok(())
+                })?;
+            }
+            PutResult::Updated
+        } else {
+            let mut batch_keys = batch_keys;
+            for index in &self.indexes {
+                match batch_keys.as_deref_mut() {
+                    Some(batch_keys) if !index.unique => {
+                        index.create_batch_keys(oid, object, batch_keys)?
+                    }
+                    _ => index.create_for_object(cursors, oid, object, |cursors, id| {
+                        self.delete_internal(cursors, true, change_set.as_deref_mut(), id)?;
+                        Ok(())
+                    })?,
+                }
+            }
+            PutResult::Inserted
+        };
 
         cursors
             .data
             .put(IntKey::new(self.id, oid), object.as_bytes())?;
-        self.register_object_change(change_set, oid, object);
-        Ok(())
+        self.register_object_change(change_set, oid, ChangeKind::Put, object);
+        Ok(result)
+    }
+
+    /// Adds `delta` to a numeric property of an existing object and returns the new value. Null
+    /// values are treated as zero. `delta` needs to have the type of the property.
+    ///
+    /// The object is patched in place and only the indexes containing the property are updated.
+    pub fn update_property(
+        &self,
+        txn: &mut IsarTxn,
+        oid: i64,
+        property: Property,
+        delta: IsarValue,
+    ) -> Result<IsarValue<'static>> {
+        if property == self.get_oid_property() {
+            return illegal_arg("The ObjectId cannot be updated.");
+        }
+        txn.write(|cursors, change_set| {
+            let existing =
+                if let Some((_, bytes)) = cursors.data.move_to(IntKey::new(self.id, oid))? {
+                    bytes.to_vec()
+                } else {
+                    return illegal_arg("Object does not exist.");
+                };
+            let mut bytes = existing.clone();
+            let object = IsarObject::from_bytes(&existing);
+            if !object.contains_property(property) {
+                return illegal_arg("The object needs to be put again before it can be updated.");
+            }
+            let out_of_range = || IsarError::IllegalArg {
+                message: "The new value is out of range.".to_string(),
+            };
+            let offset = property.offset;
+            let new_value = match (property.data_type, delta) {
+                (DataType::Int, IsarValue::Int(delta)) => {
+                    let value = object.read_int(property);
+                    let value = if value == IsarObject::NULL_INT {
+                        0
+                    } else {
+                        value
+                    };
+                    let new_value = value
+                        .checked_add(delta)
+                        .filter(|v| *v != IsarObject::NULL_INT)
+                        .ok_or_else(out_of_range)?;
+                    LittleEndian::write_i32(&mut bytes[offset..], new_value);
+                    IsarValue::Int(new_value)
+                }
+                (DataType::Long, IsarValue::Long(delta)) => {
+                    let value = object.read_long(property);
+                    let value = if value == IsarObject::NULL_LONG {
+                        0
+                    } else {
+                        value
+                    };
+                    let new_value = value
+                        .checked_add(delta)
+                        .filter(|v| *v != IsarObject::NULL_LONG)
+                        .ok_or_else(out_of_range)?;
+                    LittleEndian::write_i64(&mut bytes[offset..], new_value);
+                    IsarValue::Long(new_value)
+                }
+                (DataType::Float, IsarValue::Float(delta)) => {
+                    let value = object.read_float(property);
+                    let value = if value.is_nan() { 0.0 } else { value };
+                    LittleEndian::write_f32(&mut bytes[offset..], value + delta);
+                    IsarValue::Float(value + delta)
+                }
+                (DataType::Double, IsarValue::Double(delta)) => {
+                    let value = object.read_double(property);
+                    let value = if value.is_nan() { 0.0 } else { value };
+                    LittleEndian::write_f64(&mut bytes[offset..], value + delta);
+                    IsarValue::Double(value + delta)
+                }
+                _ => return illegal_arg("The property cannot be updated with this value."),
+            };
+            let existing = IsarObject::from_bytes(&existing);
+            let object = IsarObject::from_bytes(&bytes);
+            let mut change_set = change_set;
+            for index in &self.indexes {
+                if !index.properties.iter().any(|ip| ip.property == property) {
+                    continue;
+                }
+                index.update_for_object(cursors, oid, existing, object, |cursors, id| {
+                    self.delete_internal(cursors, true, change_set.as_deref_mut(), id)?;
+                    Ok(())
+                })?;
+            }
+            cursors
+                .data
+                .put(IntKey::new(self.id, oid), object.as_bytes())?;
+            if let Some(change_set) = change_set {
+                change_set.register_change(self.id, Some(oid), Some(existing));
+                change_set.register_change(self.id, Some(oid), Some(object));
+            }
+            Ok(new_value)
+        })
     }
 
     pub fn delete(&self, txn: &mut IsarTxn, oid: i64) -> Result<bool> {
@@ -216,7 +695,7 @@ impl IsarCollection {
                     link.delete_all_for_object(&mut cursors.links, oid)?;
                 }
             }
-            self.register_object_change(change_set, oid, object);
+            self.register_object_change(change_set, oid, ChangeKind::Delete, object);
             cursors.data.delete_current()?;
             Ok(true)
         } else {
@@ -309,7 +788,12 @@ impl IsarCollection {
                 &mut cursors.data,
                 None,
                 |cursor, id, object| {
-                    self.register_object_change(change_set.as_deref_mut(), id.get_id(), object);
+                    self.register_object_change(
+                        change_set.as_deref_mut(),
+                        id.get_id(),
+                        ChangeKind::Delete,
+                        object,
+                    );
                     cursor.delete_current()?;
                     counter += 1;
                     Ok(true)
@@ -337,10 +821,11 @@ impl IsarCollection {
         &self,
         change_set: Option<&mut ChangeSet>,
         oid: i64,
+        kind: ChangeKind,
         object: IsarObject,
     ) {
         if let Some(change_set) = change_set {
-            change_set.register_change(self.id, Some(oid), Some(object));
+            change_set.register_object_change(self.id, oid, kind, object);
         }
     }
 
@@ -371,10 +856,17 @@ impl IsarCollection {
 
 #[cfg(test)]
 mod tests {
-    use crate::lmdb::{IntKey, Key};
+    use super::PutResult;
+    use crate::error::IsarError;
+    use crate::lmdb::{IntKey, Key, MAX_ID, MIN_ID};
     use crate::object::data_type::DataType;
-    use crate::query::filter::LongBetweenCond;
+    use crate::object::isar_object::{IsarObject, IsarValue};
+    use crate::query::filter::{IntBetweenCond, LongBetweenCond};
+    use crate::query::Sort;
+    use crate::schema::collection_schema::{IndexPropertySchema, IndexType};
+    use crate::txn::IsarTxn;
     use crate::{col, ind, isar, map, set};
+    use byteorder::{ByteOrder, LittleEndian};
     use crossbeam_channel::unbounded;
 
     #[test]
@@ -395,6 +887,86 @@ mod tests {
         isar.close();
     }
 
+    #[test]
+    fn test_existing_ids() {
+        isar!(isar, col => col!(oid => DataType::Long));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+
+        for oid in &[3, 1, 7] {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(*oid);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        assert_eq!(
+            col.existing_ids(&mut txn, &[7, 2, 1, 7, 100, 3]).unwrap(),
+            vec![true, false, true, true, false, true]
+        );
+        assert!(col.existing_ids(&mut txn, &[]).unwrap().is_empty());
+        assert!(col.existing_ids(&mut txn, &[1, i64::MAX]).is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_get_all() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+
+        for oid in &[3, 1, 7] {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(*oid);
+            builder.write_int(*oid as i32 * 10);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let field = col.get_properties().get(1).unwrap().1;
+        let objects = col.get_all(&mut txn, &[7, 2, 1, 7, 100, 3]).unwrap();
+        let values: Vec<_> = objects
+            .iter()
+            .map(|o| o.map(|o| o.read_int(field)))
+            .collect();
+        assert_eq!(
+            values,
+            vec![Some(70), None, Some(10), Some(70), None, Some(30)]
+        );
+        assert!(col.get_all(&mut txn, &[]).unwrap().is_empty());
+        assert!(col.get_all(&mut txn, &[1, i64::MAX]).is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_query_uses_index() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int, other => DataType::Int; ind!(field)));
+        let field = col.get_properties().get(1).unwrap().1;
+        let other = col.get_properties().get(2).unwrap().1;
+
+        assert!(!col.query_uses_index(&col.new_query_builder().build()));
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(IntBetweenCond::filter(field, 1, 2).unwrap());
+        assert!(!col.query_uses_index(&qb.build()));
+
+        let mut qb = col.new_query_builder();
+        qb.add_index_null_where_clause(0, false, Sort::Ascending)
+            .unwrap();
+        qb.set_filter(IntBetweenCond::filter(other, 1, 2).unwrap());
+        assert!(col.query_uses_index(&qb.build()));
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(5, MAX_ID, Sort::Ascending).unwrap();
+        assert!(col.query_uses_index(&qb.build()));
+
+        let mut qb = col.new_query_builder();
+        qb.add_index_null_where_clause(0, false, Sort::Ascending)
+            .unwrap();
+        qb.add_id_where_clause(MIN_ID, MAX_ID, Sort::Ascending)
+            .unwrap();
+        assert!(!col.query_uses_index(&qb.build()));
+        isar.close();
+    }
+
     #[test]
     fn test_put_new() {
         isar!(isar, col => col!(field1 => DataType::Long));
@@ -405,13 +977,13 @@ mod tests {
         let mut builder = col.new_object_builder(None);
         builder.write_long(123);
         let object1 = builder.finish();
-        col.put(&mut txn, object1).unwrap();
+        assert_eq!(col.put(&mut txn, object1).unwrap(), PutResult::Inserted);
         assert_eq!(col.oid_counter.get(), 123);
 
         let mut builder = col.new_object_builder(None);
         builder.write_long(100);
         let object2 = builder.finish();
-        col.put(&mut txn, object2).unwrap();
+        assert_eq!(col.put(&mut txn, object2).unwrap(), PutResult::Inserted);
         assert_eq!(col.oid_counter.get(), 123);
 
         assert_eq!(
@@ -435,14 +1007,14 @@ mod tests {
         builder.write_long(123);
         builder.write_int(1);
         let object1 = builder.finish();
-        col.put(&mut txn, object1).unwrap();
+        assert_eq!(col.put(&mut txn, object1).unwrap(), PutResult::Inserted);
         assert_eq!(col.oid_counter.get(), 123);
 
         let mut builder = col.new_object_builder(None);
         builder.write_long(123);
         builder.write_int(2);
         let object2 = builder.finish();
-        col.put(&mut txn, object2).unwrap();
+        assert_eq!(col.put(&mut txn, object2).unwrap(), PutResult::Updated);
         assert_eq!(col.oid_counter.get(), 123);
 
         let mut builder = col.new_object_builder(None);
@@ -463,6 +1035,336 @@ mod tests {
         isar.close();
     }
 
+    #[test]
+    fn test_put_if_absent_and_replace() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2)));
+        let object = |oid: i64, value: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int(value);
+            builder.finish().as_bytes().to_vec()
+        };
+        let object1 = object(1, 1);
+        let object2 = object(1, 2);
+        let object3 = object(2, 3);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert!(!col
+            .replace(&mut txn, IsarObject::from_bytes(&object1))
+            .unwrap());
+        assert!(col.debug_dump(&mut txn).is_empty());
+
+        assert!(col
+            .put_if_absent(&mut txn, IsarObject::from_bytes(&object1))
+            .unwrap());
+        assert!(!col
+            .put_if_absent(&mut txn, IsarObject::from_bytes(&object2))
+            .unwrap());
+        assert_eq!(col.debug_dump(&mut txn), map![1 => object1.clone()]);
+
+        assert!(col
+            .replace(&mut txn, IsarObject::from_bytes(&object2))
+            .unwrap());
+        assert!(col
+            .put_if_absent(&mut txn, IsarObject::from_bytes(&object3))
+            .unwrap());
+        assert_eq!(
+            col.debug_dump(&mut txn),
+            map![1 => object2.clone(), 2 => object3.clone()]
+        );
+
+        let index = &col.indexes[0];
+        let entry = |bytes: &[u8], oid: i64| {
+            let key = index.debug_create_keys(IsarObject::from_bytes(bytes))[0].clone();
+            (key, IntKey::new(col.id, oid).as_bytes().to_vec())
+        };
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            set![entry(&object2, 1), entry(&object3, 2)]
+        );
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_update_property() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int, field3 => DataType::Double; ind!(field2)));
+        let int_property = col.get_properties().get(1).unwrap().1;
+        let double_property = col.get_properties().get(2).unwrap().1;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut builder = col.new_object_builder(None);
+        builder.write_long(1);
+        builder.write_null();
+        builder.write_double(1.5);
+        col.put(&mut txn, builder.finish()).unwrap();
+
+        for i in 1..=5 {
+            let value = col
+                .update_property(&mut txn, 1, int_property, IsarValue::Int(2))
+                .unwrap();
+            assert_eq!(value, IsarValue::Int(i * 2));
+        }
+        let value = col
+            .update_property(&mut txn, 1, double_property, IsarValue::Double(-0.5))
+            .unwrap();
+        assert_eq!(value, IsarValue::Double(1.0));
+
+        let object = col.get(&mut txn, 1).unwrap().unwrap();
+        assert_eq!(object.read_int(int_property), 10);
+        assert_eq!(object.read_double(double_property), 1.0);
+        let index = &col.indexes[0];
+        let key = index.debug_create_keys(object)[0].clone();
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            set![(key, IntKey::new(col.id, 1).as_bytes().to_vec())]
+        );
+        txn.commit().unwrap();
+
+        let oid_property = col.get_oid_property();
+        let failures = [
+            (2, int_property, IsarValue::Int(1)),
+            (1, int_property, IsarValue::Long(1)),
+            (1, int_property, IsarValue::Int(i32::MAX)),
+            (1, oid_property, IsarValue::Long(1)),
+        ];
+        for (oid, property, delta) in failures.iter() {
+            let mut txn = isar.begin_txn(true, false).unwrap();
+            match col.update_property(&mut txn, *oid, *property, delta.clone()) {
+                Err(IsarError::IllegalArg { .. }) => {}
+                _ => panic!("wrong error"),
+            }
+            txn.abort();
+        }
+        isar.close();
+    }
+
+    #[test]
+    fn test_update_property_replace_and_watch() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2; true, true)));
+        let int_property = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for (oid, value) in &[(1, 2), (2, 4)] {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(*oid);
+            builder.write_int(*value);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(IntBetweenCond::filter(int_property, 2, 2).unwrap());
+        let (tx, rx) = unbounded();
+        let handle = isar.watch_query(
+            col,
+            qb.build(),
+            false,
+            Box::new(move || tx.send(true).unwrap()),
+        );
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let value = col
+            .update_property(&mut txn, 1, int_property, IsarValue::Int(2))
+            .unwrap();
+        assert_eq!(value, IsarValue::Int(4));
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 1);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert_eq!(col.get(&mut txn, 2).unwrap(), None);
+        let object = col.get(&mut txn, 1).unwrap().unwrap();
+        assert_eq!(object.read_int(int_property), 4);
+        let index = &col.indexes[0];
+        let key = index.debug_create_keys(object)[0].clone();
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            set![(key, IntKey::new(col.id, 1).as_bytes().to_vec())]
+        );
+        txn.abort();
+        handle.stop();
+        isar.close();
+    }
+
+    #[test]
+    fn test_put_all() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2; true, false)));
+        let object = |oid: i64, value: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int(value);
+            builder.finish().as_bytes().to_vec()
+        };
+        let object1 = object(3, 1);
+        let object2 = object(1, 2);
+        let object3 = object(3, 3);
+        let object4 = object(2, 4);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let objects: Vec<IsarObject> = [&object1, &object2, &object3, &object4]
+            .iter()
+            .map(|bytes| IsarObject::from_bytes(bytes))
+            .collect();
+        col.put_all(&mut txn, &objects).unwrap();
+        assert_eq!(col.oid_counter.get(), 3);
+        assert_eq!(
+            col.debug_dump(&mut txn),
+            map![
+                1 => object2.clone(),
+                2 => object4.clone(),
+                3 => object3.clone()
+            ]
+        );
+
+        let object5 = object(4, 2);
+        let result = col.put_all(&mut txn, &[IsarObject::from_bytes(&object5)]);
+        assert!(matches!(result, Err(IsarError::UniqueViolated { .. })));
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_put_all_batch_index_keys() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2)));
+        let object = |oid: i64, value: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int(value);
+            builder.finish().as_bytes().to_vec()
+        };
+        let objects = vec![object(3, 10), object(1, 20), object(3, 30), object(2, 20)];
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let isar_objects: Vec<IsarObject> =
+            objects.iter().map(|b| IsarObject::from_bytes(b)).collect();
+        col.put_all(&mut txn, &isar_objects).unwrap();
+
+        let index = &col.indexes[0];
+        let entry = |bytes: &[u8], oid: i64| {
+            let key = index.debug_create_keys(IsarObject::from_bytes(bytes))[0].clone();
+            (key, IntKey::new(col.id, oid).as_bytes().to_vec())
+        };
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            set![
+                entry(&objects[1], 1),
+                entry(&objects[3], 2),
+                entry(&objects[2], 3)
+            ]
+        );
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_put_all_keeps_order_for_replace() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2; true, true)));
+        let object = |oid: i64, value: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int(value);
+            builder.finish().as_bytes().to_vec()
+        };
+        let object1 = object(2, 5);
+        let object2 = object(1, 5);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let objects = [
+            IsarObject::from_bytes(&object1),
+            IsarObject::from_bytes(&object2),
+        ];
+        col.put_all(&mut txn, &objects).unwrap();
+        assert_eq!(col.debug_dump(&mut txn), map![1 => object2.clone()]);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_put_all_explicit_and_auto_increment_ids() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int));
+        let object = |oid: Option<i64>, value: i32| {
+            let mut builder = col.new_object_builder(None);
+            match oid {
+                Some(oid) => builder.write_long(oid),
+                None => builder.write_null(),
+            }
+            builder.write_int(value);
+            builder.finish().as_bytes().to_vec()
+        };
+        let mut objects = vec![object(Some(2), 1), object(None, 2), object(None, 3)];
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let oid_property = col.get_oid_property();
+        for bytes in &objects {
+            let object = IsarObject::from_bytes(bytes);
+            if !object.is_null(oid_property) {
+                col.register_oid(&mut txn, object.read_long(oid_property))
+                    .unwrap();
+            }
+        }
+        for bytes in &mut objects {
+            if IsarObject::from_bytes(bytes).is_null(oid_property) {
+                let oid = col.auto_increment(&mut txn).unwrap();
+                LittleEndian::write_i64(&mut bytes[oid_property.offset..], oid);
+            }
+        }
+        let isar_objects: Vec<IsarObject> =
+            objects.iter().map(|b| IsarObject::from_bytes(b)).collect();
+        col.put_all(&mut txn, &isar_objects).unwrap();
+
+        assert_eq!(
+            col.debug_dump(&mut txn),
+            map![
+                2 => objects[0].clone(),
+                3 => objects[1].clone(),
+                4 => objects[2].clone()
+            ]
+        );
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_validate() {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String, tags => DataType::StringList, ints => DataType::IntList));
+        let mut builder = col.new_object_builder(None);
+        builder.write_long(1);
+        builder.write_string(Some("abc"));
+        builder.write_string_list(Some(&[Some("x"), None]));
+        builder.write_int_list(Some(&[1, 2]));
+        let bytes = builder.finish().as_bytes().to_vec();
+        let is_invalid = |bytes: &[u8]| {
+            matches!(
+                col.validate(IsarObject::from_bytes(bytes)),
+                Err(IsarError::InvalidObject {})
+            )
+        };
+        assert!(col.validate(IsarObject::from_bytes(&bytes)).is_ok());
+
+        for len in &[2, 20, bytes.len() - 1] {
+            assert!(is_invalid(&bytes[..*len]));
+        }
+
+        let mut over_long = bytes.clone();
+        over_long.push(0);
+        assert!(is_invalid(&over_long));
+
+        let mut wrong_static_size = bytes.clone();
+        wrong_static_size[0] += 8;
+        assert!(is_invalid(&wrong_static_size));
+
+        let mut string_out_of_bounds = bytes.clone();
+        string_out_of_bounds[14] = 100;
+        assert!(is_invalid(&string_out_of_bounds));
+
+        let mut list_in_static_section = bytes.clone();
+        list_in_static_section[37] = 4;
+        assert!(is_invalid(&list_in_static_section));
+
+        let mut invalid_utf8 = bytes;
+        invalid_utf8[34] = 0xff;
+        assert!(is_invalid(&invalid_utf8));
+        isar.close();
+    }
+
     #[test]
     fn test_put_creates_index() {
         isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2)));
@@ -513,6 +1415,33 @@ mod tests {
         isar.close();
     }
 
+    #[test]
+    fn test_put_updates_changed_indexes_only() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int, field3 => DataType::Int; ind!(field2), ind!(field3)));
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let put = |txn: &mut IsarTxn, field3: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_int(5);
+            builder.write_int(field3);
+            col.put(txn, builder.finish()).unwrap();
+        };
+        put(&mut txn, 10);
+        let field2_index = col.indexes[0].debug_dump(&mut txn);
+
+        put(&mut txn, 20);
+        assert_eq!(col.indexes[0].debug_dump(&mut txn), field2_index);
+        let mut key = col.new_index_key(1).unwrap();
+        key.add_int(20);
+        assert_eq!(
+            col.indexes[1].debug_dump(&mut txn),
+            set![(key.bytes, IntKey::new(col.id, 1).as_bytes().to_vec())]
+        );
+        txn.abort();
+        isar.close();
+    }
+
     #[test]
     fn test_put_calls_notifiers() {
         isar!(isar, col => col!(oid => DataType::Long));
@@ -527,10 +1456,10 @@ mod tests {
         let q2 = qb2.build();
 
         let (tx1, rx1) = unbounded();
-        let handle1 = isar.watch_query(col, q1, Box::new(move || tx1.send(true).unwrap()));
+        let handle1 = isar.watch_query(col, q1, false, Box::new(move || tx1.send(true).unwrap()));
 
         let (tx2, rx2) = unbounded();
-        let handle2 = isar.watch_query(col, q2, Box::new(move || tx2.send(true).unwrap()));
+        let handle2 = isar.watch_query(col, q2, false, Box::new(move || tx2.send(true).unwrap()));
 
         let mut txn = isar.begin_txn(true, false).unwrap();
         let mut builder = col.new_object_builder(None);
@@ -555,6 +1484,296 @@ mod tests {
         isar.close();
     }
 
+    #[test]
+    fn test_count_by_index() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+
+        for (oid, value) in [(1, 3), (2, 1), (3, 3), (4, 2), (5, 3), (6, 1)].iter() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(*oid);
+            builder.write_int(*value);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let key = |value: i32| {
+            let mut key = col.new_index_key(0).unwrap();
+            key.add_int(value);
+            key.bytes[2..].to_vec()
+        };
+        assert_eq!(
+            col.count_by_index(&mut txn, 0).unwrap(),
+            vec![(key(1), 2), (key(2), 1), (key(3), 3)]
+        );
+        assert!(col.count_by_index(&mut txn, 1).is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_new_compound_index_key() {
+        let index = || {
+            (
+                vec![
+                    IndexPropertySchema::new("a", IndexType::Value, None),
+                    IndexPropertySchema::new("b", IndexType::Hash, Some(false)),
+                ],
+                false,
+                false,
+            )
+        };
+        isar!(isar, col => col!(oid => DataType::Long, a => DataType::Int, b => DataType::String; ind!(a), index()));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut builder = col.new_object_builder(None);
+        builder.write_long(1);
+        builder.write_int(5);
+        builder.write_string(Some("Hello"));
+        col.put(&mut txn, builder.finish()).unwrap();
+
+        let mut key = col.new_compound_index_key(1).unwrap();
+        assert!(key.add_long(5).is_err());
+        key.add_int(5).unwrap();
+        assert!(key.add_string_value(Some("hello")).is_err());
+        key.add_string_hash(Some("hello")).unwrap();
+        assert!(key.add_int(1).is_err());
+        let key = key.finish();
+        assert!(col.get_by_index(&mut txn, &key).unwrap().is_some());
+
+        assert!(col.new_compound_index_key(2).is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_new_hash_index_key() {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String; ind!(str name, IndexType::Hash, Some(false)), ind!(str name, IndexType::Value, Some(true))));
+        let name = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let long_value = "Hello World ".repeat(200);
+        let values = [Some(long_value.as_str()), Some("hello"), None, Some("Maẞ")];
+        for (i, value) in values.iter().enumerate() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(i as i64 + 1);
+            builder.write_string(*value);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let mut find = |value: Option<&str>| -> Vec<i64> {
+            let key = col.new_hash_index_key(0, value).unwrap();
+            let mut qb = col.new_query_builder();
+            qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+                .unwrap();
+            qb.build()
+                .find_all_vec(&mut txn)
+                .unwrap()
+                .iter()
+                .map(|o| o.read_long(col.get_oid_property()))
+                .collect()
+        };
+        assert_eq!(find(Some(&long_value)), vec![1]);
+        assert_eq!(find(Some(&long_value.to_uppercase())), vec![1]);
+        assert_eq!(find(Some("HELLO")), vec![2]);
+        assert_eq!(find(None), vec![3]);
+        assert!(find(Some("hello world")).is_empty());
+        // the hash covers the length of the folded string
+        assert_eq!(find(Some("MASS")), vec![4]);
+
+        let key = col.new_hash_index_key(0, Some(&long_value)).unwrap();
+        let object = col.get_by_index(&mut txn, &key).unwrap().unwrap();
+        assert_eq!(object.read_string(name), Some(long_value.as_str()));
+
+        assert!(col.new_hash_index_key(1, Some("hello")).is_err());
+        assert!(col.new_hash_index_key(2, Some("hello")).is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_index_prefix_where_clause() {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String; ind!(str name, IndexType::Value, Some(false))));
+        let name = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let names = [
+            Some("Joe"),
+            Some("john"),
+            Some("Jo"),
+            Some("Anna"),
+            Some("Jörg"),
+            Some("J"),
+            Some(""),
+            None,
+            Some("JOANNA"),
+        ];
+        for (i, value) in names.iter().enumerate() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(i as i64 + 1);
+            builder.write_string(*value);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let mut find = |prefix: &str, sort: Sort| -> Vec<String> {
+            let mut qb = col.new_query_builder();
+            qb.add_index_prefix_where_clause(0, prefix, false, sort)
+                .unwrap();
+            qb.build()
+                .find_all_vec(&mut txn)
+                .unwrap()
+                .iter()
+                .map(|o| o.read_string(name).unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            find("jo", Sort::Ascending),
+            vec!["Jo", "JOANNA", "Joe", "john"]
+        );
+        assert_eq!(
+            find("JO", Sort::Descending),
+            vec!["john", "Joe", "JOANNA", "Jo"]
+        );
+        assert_eq!(find("jö", Sort::Ascending), vec!["Jörg"]);
+        assert_eq!(find("Joh", Sort::Ascending), vec!["john"]);
+        assert_eq!(find("x", Sort::Ascending), Vec::<String>::new());
+        assert_eq!(
+            find("", Sort::Ascending),
+            vec!["", "Anna", "J", "Jo", "JOANNA", "Joe", "john", "Jörg"]
+        );
+
+        let max_string_size = col.debug_get_index(0).max_string_size;
+        let mut qb = col.new_query_builder();
+        let long_prefix = "a".repeat(max_string_size + 1);
+        assert!(qb
+            .add_index_prefix_where_clause(0, &long_prefix, false, Sort::Ascending)
+            .is_err());
+        assert!(qb
+            .add_index_prefix_where_clause(1, "a", false, Sort::Ascending)
+            .is_err());
+        assert!(qb
+            .add_index_prefix_where_clause(0, "a", true, Sort::Ascending)
+            .is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_index_null_where_clause() {
+        isar!(isar, col => col!(oid => DataType::Long, int => DataType::Int, double => DataType::Double, name => DataType::String; ind!(int), ind!(double), ind!(str name, IndexType::Value, Some(true)), ind!(oid, int)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let values = [
+            (Some(5), Some(1.5), Some("b")),
+            (None, None, None),
+            (Some(i32::MAX), Some(f64::NEG_INFINITY), Some("")),
+            (Some(i32::MIN + 1), None, Some("a")),
+            (None, Some(-0.0), None),
+            (Some(-3), Some(f64::INFINITY), Some("\u{10FFFF}")),
+        ];
+        for (i, (int, double, name)) in values.iter().enumerate() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(i as i64 + 1);
+            builder.write_int(int.unwrap_or(IsarObject::NULL_INT));
+            builder.write_double(double.unwrap_or(IsarObject::NULL_DOUBLE));
+            builder.write_string(*name);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let mut find = |index: usize, is_null: bool| -> Vec<i64> {
+            let mut qb = col.new_query_builder();
+            qb.add_index_null_where_clause(index, is_null, Sort::Ascending)
+                .unwrap();
+            let mut ids: Vec<i64> = qb
+                .build()
+                .find_all_vec(&mut txn)
+                .unwrap()
+                .iter()
+                .map(|o| o.read_long(col.get_oid_property()))
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(find(0, true), vec![2, 5]);
+        assert_eq!(find(0, false), vec![1, 3, 4, 6]);
+        assert_eq!(find(1, true), vec![2, 4]);
+        assert_eq!(find(1, false), vec![1, 3, 5, 6]);
+        assert_eq!(find(2, true), vec![2, 5]);
+        assert_eq!(find(2, false), vec![1, 3, 4, 6]);
+
+        let mut qb = col.new_query_builder();
+        assert!(qb
+            .add_index_null_where_clause(3, true, Sort::Ascending)
+            .is_err());
+        assert!(qb
+            .add_index_null_where_clause(4, true, Sort::Ascending)
+            .is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_optimize_index() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+
+        for oid in 0..2000 {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int((oid % 4) as i32);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+        for oid in (0..2000).filter(|oid| oid % 3 != 0) {
+            col.delete(&mut txn, oid).unwrap();
+        }
+        let counts_before = col.count_by_index(&mut txn, 0).unwrap();
+
+        let stats = col.optimize_index(&mut txn, 0).unwrap();
+        assert_eq!(stats.keys, 4);
+        assert_eq!(stats.entries, 667);
+        assert!(stats.pages_after <= stats.pages_before);
+        assert_eq!(col.count_by_index(&mut txn, 0).unwrap(), counts_before);
+
+        let q = col.new_query_builder().build();
+        assert_eq!(q.count(&mut txn).unwrap(), 667);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_storage_size() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert_eq!(col.storage_size(&mut txn).unwrap(), 0);
+
+        let mut object_size = 0;
+        for oid in 0..100 {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int(5);
+            let object = builder.finish();
+            object_size = object.byte_len();
+            col.put(&mut txn, object).unwrap();
+        }
+
+        // 8 byte id keys, index keys with a 2 byte prefix and 8 byte id values
+        let data_size = 100 * (8 + object_size as u64);
+        let index_size = 100 * (2 + 4 + 8);
+        let size = col.storage_size(&mut txn).unwrap();
+        assert!(size >= data_size + index_size);
+        assert!(size <= data_size + index_size + 100 * 8);
+
+        col.clear(&mut txn).unwrap();
+        assert_eq!(col.storage_size(&mut txn).unwrap(), 0);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_optimize_index_unique() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field; true, false)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert!(col.optimize_index(&mut txn, 0).is_err());
+        assert!(col.optimize_index(&mut txn, 1).is_err());
+        txn.abort();
+        isar.close();
+    }
+
     #[test]
     fn test_delete() {
         isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field)));
@@ -590,6 +1809,52 @@ mod tests {
         isar.close();
     }
 
+    #[test]
+    fn test_put_skip_unchanged() {
+        isar!(isar, col => col!(field1 => DataType::Long, field2 => DataType::Int; ind!(field2)));
+        let object = |value: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_int(value);
+            builder.finish().as_bytes().to_vec()
+        };
+        let object1 = object(1);
+        let object2 = object(2);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        col.put(&mut txn, IsarObject::from_bytes(&object1)).unwrap();
+        txn.commit().unwrap();
+
+        let (tx, rx) = unbounded();
+        let handle = isar.watch_collection(col, Box::new(move || tx.send(true).unwrap()));
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert!(!col
+            .put_skip_unchanged(&mut txn, IsarObject::from_bytes(&object1))
+            .unwrap());
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 0);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert!(col
+            .put_skip_unchanged(&mut txn, IsarObject::from_bytes(&object2))
+            .unwrap());
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 1);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert_eq!(col.debug_dump(&mut txn), map![1 => object2.clone()]);
+        let index = &col.indexes[0];
+        let key = index.debug_create_keys(IsarObject::from_bytes(&object2))[0].clone();
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            set![(key, IntKey::new(col.id, 1).as_bytes().to_vec())]
+        );
+        txn.abort();
+        handle.stop();
+        isar.close();
+    }
+
     #[test]
     fn test_delete_calls_notifiers() {
         isar!(isar, col => col!(field1 => DataType::Long));
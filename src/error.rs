@@ -14,12 +14,18 @@ pub enum IsarError {
     #[error("The database is full.")]
     DbFull {},
 
-    #[error("Unique index violated.")]
-    UniqueViolated {},
+    #[error("Unique index {index_id} violated by object {oid}.")]
+    UniqueViolated { index_id: u16, oid: i64 },
+
+    #[error("Index {index_id} does not allow string values of {size} bytes.")]
+    IndexKeyTooLarge { index_id: u16, size: usize },
 
     #[error("Write transaction required.")]
     WriteTxnRequired {},
 
+    #[error("The instance has been opened in read-only mode.")]
+    ReadOnly {},
+
     #[error("Auto increment id cannot be generated because the limit is reached.")]
     AutoIncrementOverflow {},
 
@@ -44,11 +50,17 @@ pub enum IsarError {
     #[error("SchemaError: {message:?}")]
     SchemaError { message: String },
 
+    #[error("Too many indexes.")]
+    TooManyIndexes {},
+
     #[error(
         "CryptoError: Error during encryption or decryption. Please check the encryption key."
     )]
     CryptoError {},
 
+    #[error("IoError: {message:?}")]
+    IoError { message: String },
+
     #[error("LmdbError ({code:?}): {message:?}")]
     LmdbError { code: i32, message: String },
 }
@@ -69,6 +81,30 @@ impl From<LmdbError> for IsarError {
     }
 }
 
+impl From<std::io::Error> for IsarError {
+    fn from(e: std::io::Error) -> Self {
+        IsarError::IoError {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for IsarError {
+    fn from(e: serde_json::Error) -> Self {
+        IsarError::IoError {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl serde::de::Error for IsarError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        IsarError::IllegalArg {
+            message: msg.to_string(),
+        }
+    }
+}
+
 pub fn illegal_arg<T>(msg: &str) -> Result<T> {
     Err(IsarError::IllegalArg {
         message: msg.to_string(),
@@ -80,3 +116,13 @@ pub fn schema_error<T>(msg: &str) -> Result<T> {
         message: msg.to_string(),
     })
 }
+
+/// Schema error that names the collection and optionally the property or link it refers to.
+pub fn schema_error_for<T>(collection: &str, property: Option<&str>, msg: &str) -> Result<T> {
+    let message = if let Some(property) = property {
+        format!("Collection \"{}\", \"{}\": {}", collection, property, msg)
+    } else {
+        format!("Collection \"{}\": {}", collection, msg)
+    };
+    Err(IsarError::SchemaError { message })
+}
@@ -1,5 +1,9 @@
-use crate::index::Index;
-use crate::index::MAX_STRING_INDEX_SIZE;
+use crate::error::{illegal_arg, Result};
+use crate::index::{Index, IndexProperty};
+use crate::object::data_type::DataType;
+use crate::schema::collection_schema::IndexType;
+use crate::utils::fold_case;
+use std::borrow::Cow;
 use std::hash::Hasher;
 use wyhash::{wyhash, WyHash};
 
@@ -7,6 +11,7 @@ use wyhash::{wyhash, WyHash};
 pub struct IndexKey<'a> {
     pub(crate) index: &'a Index,
     pub(crate) bytes: Vec<u8>,
+    properties: usize,
 }
 
 impl<'a> IndexKey<'a> {
@@ -14,6 +19,7 @@ impl<'a> IndexKey<'a> {
         IndexKey {
             index: &index,
             bytes: index.get_prefix(),
+            properties: 0,
         }
     }
 
@@ -23,27 +29,46 @@ impl<'a> IndexKey<'a> {
         IndexKey {
             index: &index,
             bytes: buffer,
+            properties: 0,
         }
     }
 
+    fn add_property_bytes(&mut self, bytes: &[u8]) {
+        let descending = self
+            .index
+            .properties
+            .get(self.properties)
+            .map_or(false, |p| p.descending);
+        if descending {
+            self.bytes.extend(bytes.iter().map(|b| !b));
+        } else {
+            self.bytes.extend_from_slice(bytes);
+        }
+        self.properties += 1;
+    }
+
     pub fn add_byte(&mut self, value: u8) {
-        self.bytes.push(value);
+        self.add_property_bytes(&[value]);
     }
 
     pub fn add_int(&mut self, value: i32) {
         let unsigned = unsafe { std::mem::transmute::<i32, u32>(value) };
         let bytes: [u8; 4] = (unsigned ^ 1 << 31).to_be_bytes();
-        self.bytes.extend_from_slice(&bytes);
+        self.add_property_bytes(&bytes);
     }
 
     pub fn add_long(&mut self, value: i64) {
         let unsigned = unsafe { std::mem::transmute::<i64, u64>(value) };
         let bytes: [u8; 8] = (unsigned ^ 1 << 63).to_be_bytes();
-        self.bytes.extend_from_slice(&bytes);
+        self.add_property_bytes(&bytes);
     }
 
+    /// Floats are ordered NaN (null) first, then by value with -0.0 equal to 0.0. This is the
+    /// same order used by filters and sorting.
     pub fn add_float(&mut self, value: f32) {
         let bytes: [u8; 4] = if !value.is_nan() {
+            // -0.0 == 0.0 so both need the same key
+            let value = if value == 0.0 { 0.0 } else { value };
             let bits = if value.is_sign_positive() {
                 value.to_bits() + 2u32.pow(31)
             } else {
@@ -53,11 +78,13 @@ impl<'a> IndexKey<'a> {
         } else {
             [0; 4]
         };
-        self.bytes.extend_from_slice(&bytes);
+        self.add_property_bytes(&bytes);
     }
 
+    /// Same order as `add_float()`.
     pub fn add_double(&mut self, value: f64) {
         let bytes: [u8; 8] = if !value.is_nan() {
+            let value = if value == 0.0 { 0.0 } else { value };
             let bits = if value.is_sign_positive() {
                 value.to_bits() + 2u64.pow(63)
             } else {
@@ -67,57 +94,178 @@ impl<'a> IndexKey<'a> {
         } else {
             [0; 8]
         };
-        self.bytes.extend_from_slice(&bytes);
+        self.add_property_bytes(&bytes);
     }
 
     pub fn add_string_hash(&mut self, value: Option<&str>, case_sensitive: bool) {
         let hash = if let Some(value) = value {
+            let value = if case_sensitive {
+                Cow::Borrowed(value)
+            } else {
+                Cow::Owned(fold_case(value))
+            };
             let mut hasher = WyHash::default();
             hasher.write_usize(value.len());
-            if case_sensitive {
-                hasher.write(value.as_bytes());
-            } else {
-                let lower_case = value.to_lowercase();
-                hasher.write(lower_case.as_bytes());
-            }
+            hasher.write(value.as_bytes());
             hasher.finish()
         } else {
             0
         };
         let bytes: [u8; 8] = hash.to_be_bytes();
-        self.bytes.extend_from_slice(&bytes);
+        self.add_property_bytes(&bytes);
     }
 
     pub fn add_string_value(&mut self, value: Option<&str>, case_sensitive: bool) {
+        let mut key = vec![];
         if let Some(value) = value {
             let value = if case_sensitive {
                 value.to_string()
             } else {
-                value.to_lowercase()
+                fold_case(value)
             };
             let bytes = value.as_bytes();
-            self.bytes.push(1);
-            if bytes.len() >= MAX_STRING_INDEX_SIZE {
-                self.bytes
-                    .extend_from_slice(&bytes[0..MAX_STRING_INDEX_SIZE]);
-                self.bytes.push(0);
+            key.push(1);
+            let max_string_size = self.index.max_string_size;
+            if bytes.len() >= max_string_size {
+                key.extend_from_slice(&bytes[0..max_string_size]);
+                key.push(0);
                 let hash = wyhash(bytes, 0);
-                self.bytes.extend_from_slice(&u64::to_le_bytes(hash));
+                key.extend_from_slice(&u64::to_le_bytes(hash));
             } else {
-                self.bytes.extend_from_slice(bytes);
-                self.bytes.push(0);
+                key.extend_from_slice(bytes);
+                key.push(0);
             }
         } else {
-            self.bytes.push(0);
+            key.push(0);
+        }
+        self.add_property_bytes(&key);
+    }
+
+    /// Adds a bound for all string values starting with `prefix`. The upper bound is followed by
+    /// `0xFF` which never occurs in UTF-8 so it is larger than every value with the prefix.
+    pub(crate) fn add_string_prefix(
+        &mut self,
+        prefix: &str,
+        case_sensitive: bool,
+        upper: bool,
+    ) -> Result<()> {
+        let ip = self.index.properties.get(self.properties);
+        if ip.and_then(|ip| ip.case_sensitive) != Some(case_sensitive) {
+            return illegal_arg("The case sensitivity does not match the index.");
+        }
+        let prefix = if case_sensitive {
+            prefix.to_string()
+        } else {
+            fold_case(prefix)
+        };
+        // longer values are cut off in the index so a longer prefix cannot be matched exactly
+        if prefix.len() > self.index.max_string_size {
+            return illegal_arg("The prefix is longer than the maximum indexed string size.");
         }
+        let mut key = vec![1];
+        key.extend_from_slice(prefix.as_bytes());
+        if upper {
+            key.push(u8::MAX);
+        }
+        self.add_property_bytes(&key);
+        Ok(())
     }
 
     pub fn add_string_word(&mut self, value: &str, case_sensitive: bool) {
+        let max_string_size = self.index.max_string_size;
         if case_sensitive {
-            self.bytes.extend_from_slice(value.as_bytes());
+            let key = Index::create_word_key(value.as_bytes(), max_string_size);
+            self.add_property_bytes(&key);
         } else {
-            let lower_case = value.to_lowercase();
-            self.bytes.extend_from_slice(lower_case.as_bytes());
+            let lower_case = fold_case(value);
+            let key = Index::create_word_key(lower_case.as_bytes(), max_string_size);
+            self.add_property_bytes(&key);
+        }
+    }
+}
+
+/// Builds an `IndexKey` and verifies that every value matches the type of the next index property.
+pub struct IndexKeyBuilder<'a> {
+    key: IndexKey<'a>,
+}
+
+impl<'a> IndexKeyBuilder<'a> {
+    pub(crate) fn new(index: &'a Index) -> Self {
+        IndexKeyBuilder {
+            key: IndexKey::new(index),
         }
     }
+
+    fn next_property(&self, data_type: DataType, index_type: IndexType) -> Result<IndexProperty> {
+        let ip = if let Some(ip) = self.key.index.properties.get(self.key.properties) {
+            *ip
+        } else {
+            return illegal_arg("Too many index key components.");
+        };
+        let element_type = match ip.property.data_type {
+            DataType::ByteList => DataType::Byte,
+            DataType::IntList => DataType::Int,
+            DataType::FloatList => DataType::Float,
+            DataType::LongList => DataType::Long,
+            DataType::DoubleList => DataType::Double,
+            DataType::StringList => DataType::String,
+            data_type => data_type.storage_type(),
+        };
+        if element_type != data_type || ip.index_type != index_type {
+            return illegal_arg("Index key component does not match the index property.");
+        }
+        Ok(ip)
+    }
+
+    pub fn add_byte(&mut self, value: u8) -> Result<()> {
+        self.next_property(DataType::Byte, IndexType::Value)?;
+        self.key.add_byte(value);
+        Ok(())
+    }
+
+    pub fn add_int(&mut self, value: i32) -> Result<()> {
+        self.next_property(DataType::Int, IndexType::Value)?;
+        self.key.add_int(value);
+        Ok(())
+    }
+
+    pub fn add_long(&mut self, value: i64) -> Result<()> {
+        self.next_property(DataType::Long, IndexType::Value)?;
+        self.key.add_long(value);
+        Ok(())
+    }
+
+    pub fn add_float(&mut self, value: f32) -> Result<()> {
+        self.next_property(DataType::Float, IndexType::Value)?;
+        self.key.add_float(value);
+        Ok(())
+    }
+
+    pub fn add_double(&mut self, value: f64) -> Result<()> {
+        self.next_property(DataType::Double, IndexType::Value)?;
+        self.key.add_double(value);
+        Ok(())
+    }
+
+    pub fn add_string_value(&mut self, value: Option<&str>) -> Result<()> {
+        let ip = self.next_property(DataType::String, IndexType::Value)?;
+        self.key.add_string_value(value, ip.case_sensitive.unwrap());
+        Ok(())
+    }
+
+    pub fn add_string_hash(&mut self, value: Option<&str>) -> Result<()> {
+        let ip = self.next_property(DataType::String, IndexType::Hash)?;
+        self.key.add_string_hash(value, ip.case_sensitive.unwrap());
+        Ok(())
+    }
+
+    pub fn add_string_word(&mut self, value: &str) -> Result<()> {
+        let ip = self.next_property(DataType::String, IndexType::Words)?;
+        self.key.add_string_word(value, ip.case_sensitive.unwrap());
+        Ok(())
+    }
+
+    pub fn finish(self) -> IndexKey<'a> {
+        self.key
+    }
 }
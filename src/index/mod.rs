@@ -3,24 +3,28 @@ use crate::index::index_key::IndexKey;
 use crate::lmdb::{ByteKey, IntKey, Key};
 use crate::object::data_type::DataType;
 use crate::object::isar_object::{IsarObject, Property};
-use crate::query::index_where_clause::IndexWhereClause;
-use crate::query::Sort;
-use crate::schema::collection_schema::IndexType;
+use crate::schema::collection_schema::{IndexType, WordTokenizer};
 use crate::txn::Cursors;
+use crate::utils::fold_case;
+use hashbrown::HashSet;
 use itertools::Itertools;
-use unicode_segmentation::UnicodeSegmentation;
+use std::borrow::Cow;
+use wyhash::wyhash;
 
 #[cfg(test)]
-use {crate::txn::IsarTxn, crate::utils::debug::dump_db, hashbrown::HashSet};
+use {crate::txn::IsarTxn, crate::utils::debug::dump_db};
 
 pub mod index_key;
 
-pub const MAX_STRING_INDEX_SIZE: usize = 1024;
+/// The default `MDB_MAXKEYSIZE` of LMDB. Longer index keys cannot be stored.
+pub const MAX_KEY_SIZE: usize = 511;
 
 /*
 
 Null values are always considered the "smallest" element.
 
+Descending index properties store the bit-inverted key bytes so the natural byte order is reversed.
+
  */
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -28,6 +32,7 @@ pub struct IndexProperty {
     pub property: Property,
     pub index_type: IndexType,
     pub case_sensitive: Option<bool>,
+    pub descending: bool,
 }
 
 impl IndexProperty {
@@ -35,11 +40,13 @@ impl IndexProperty {
         property: Property,
         index_type: IndexType,
         case_sensitive: Option<bool>,
+        descending: bool,
     ) -> Self {
         IndexProperty {
             property,
             index_type,
             case_sensitive,
+            descending,
         }
     }
 
@@ -48,12 +55,20 @@ impl IndexProperty {
             if self.case_sensitive.unwrap() {
                 str.to_string()
             } else {
-                str.to_lowercase()
+                fold_case(str)
             }
         })
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct IndexOptimizeStats {
+    pub keys: usize,
+    pub entries: usize,
+    pub pages_before: u64,
+    pub pages_after: u64,
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub(crate) struct Index {
     pub id: u16,
@@ -61,15 +76,24 @@ pub(crate) struct Index {
     pub properties: Vec<IndexProperty>,
     pub unique: bool,
     pub replace: bool,
+    pub max_string_size: usize,
+    pub sparse: bool,
+    pub word_tokenizer: WordTokenizer,
+    pub reject_long_strings: bool,
 }
 
 impl Index {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: u16,
         col_id: u16,
         properties: Vec<IndexProperty>,
         unique: bool,
         replace: bool,
+        max_string_size: usize,
+        sparse: bool,
+        word_tokenizer: WordTokenizer,
+        reject_long_strings: bool,
     ) -> Self {
         Index {
             id,
@@ -77,6 +101,10 @@ impl Index {
             properties,
             unique,
             replace,
+            max_string_size,
+            sparse,
+            word_tokenizer,
+            reject_long_strings,
         }
     }
 
@@ -89,7 +117,8 @@ impl Index {
     }
 
     pub fn multiple(&self) -> bool {
-        self.properties.first().unwrap().index_type == IndexType::Words
+        let last = self.properties.last().unwrap();
+        last.index_type == IndexType::Words || last.property.data_type.is_list()
     }
 
     pub fn create_for_object<F>(
@@ -102,6 +131,7 @@ impl Index {
     where
         F: FnMut(&mut Cursors, i64) -> Result<()>,
     {
+        self.check_string_sizes(object)?;
         let id_key = IntKey::new(self.col_id, oid);
         self.create_keys(object, |key| {
             self.create_for_object_key(cursors, id_key, ByteKey::new(key), &mut delete_existing)?;
@@ -109,6 +139,111 @@ impl Index {
         })
     }
 
+    /// Adds the keys of a new object to `keys` instead of writing them. Only used for non-unique
+    /// indexes since unique indexes have to be checked for every object.
+    pub(crate) fn create_batch_keys(
+        &self,
+        oid: i64,
+        object: IsarObject,
+        keys: &mut Vec<(Vec<u8>, IntKey)>,
+    ) -> Result<()> {
+        self.check_string_sizes(object)?;
+        let id_key = IntKey::new(self.col_id, oid);
+        self.create_keys(object, |key| {
+            keys.push((key.to_vec(), id_key));
+            Ok(true)
+        })
+    }
+
+    /// Writes the keys collected by `create_batch_keys` in key order.
+    pub(crate) fn put_batch_keys(
+        cursors: &mut Cursors,
+        keys: &mut Vec<(Vec<u8>, IntKey)>,
+    ) -> Result<()> {
+        keys.sort_unstable_by(|(key1, id1), (key2, id2)| {
+            key1.cmp(key2)
+                .then_with(|| id1.as_bytes().cmp(id2.as_bytes()))
+        });
+        for (key, id_key) in keys.drain(..) {
+            cursors.index.put(ByteKey::new(&key), id_key.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Only touches the entries whose keys differ between the previous and the new object.
+    pub fn update_for_object<F>(
+        &self,
+        cursors: &mut Cursors,
+        oid: i64,
+        old_object: IsarObject,
+        new_object: IsarObject,
+        mut delete_existing: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Cursors, i64) -> Result<()>,
+    {
+        self.check_string_sizes(new_object)?;
+        let mut old_keys = HashSet::new();
+        self.create_keys(old_object, |key| {
+            old_keys.insert(key.to_vec());
+            Ok(true)
+        })?;
+        let mut new_keys = vec![];
+        self.create_keys(new_object, |key| {
+            if !old_keys.remove(key) {
+                new_keys.push(key.to_vec());
+            }
+            Ok(true)
+        })?;
+
+        let id_key = IntKey::new(self.col_id, oid);
+        let oid_bytes = id_key.as_bytes();
+        for key in old_keys {
+            let entry = cursors
+                .index
+                .move_to_key_val(ByteKey::new(&key), &oid_bytes)?;
+            if entry.is_some() {
+                cursors.index.delete_current()?;
+            }
+        }
+        for key in new_keys {
+            self.create_for_object_key(cursors, id_key, ByteKey::new(&key), &mut delete_existing)?;
+        }
+        Ok(())
+    }
+
+    /// Changing `reject_long_strings` rebuilds the index, so the stored values have already been
+    /// checked and only the values of new objects need to be.
+    fn check_string_sizes(&self, object: IsarObject) -> Result<()> {
+        if !self.reject_long_strings {
+            return Ok(());
+        }
+        for ip in &self.properties {
+            if ip.index_type != IndexType::Value {
+                continue;
+            }
+            let values = match ip.property.data_type {
+                DataType::String => vec![object.read_string(ip.property)],
+                DataType::StringList => object.read_string_list(ip.property).unwrap_or_default(),
+                _ => continue,
+            };
+            for value in values.into_iter().flatten() {
+                let size = if ip.case_sensitive.unwrap() {
+                    value.len()
+                } else {
+                    fold_case(value).len()
+                };
+                if size >= self.max_string_size {
+                    return Err(IsarError::IndexKeyTooLarge {
+                        index_id: self.id,
+                        size,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn create_for_object_key<F>(
         &self,
         cursors: &mut Cursors,
@@ -123,9 +258,15 @@ impl Index {
             let success = cursors.index.put_no_override(key, id_key.as_bytes())?;
             if !success {
                 if self.replace {
-                    delete_existing(cursors, id_key.get_id())?;
+                    let (_, existing_id) = cursors.index.move_to(key)?.unwrap();
+                    let existing_id = IntKey::from_bytes(existing_id).get_id();
+                    delete_existing(cursors, existing_id)?;
+                    cursors.index.put(key, id_key.as_bytes())?;
                 } else {
-                    return Err(IsarError::UniqueViolated {});
+                    return Err(IsarError::UniqueViolated {
+                        index_id: self.id,
+                        oid: id_key.get_id(),
+                    });
                 }
             }
         } else {
@@ -154,26 +295,97 @@ impl Index {
     }
 
     pub fn clear(&self, cursors: &mut Cursors) -> Result<()> {
-        IndexWhereClause::new(
-            IndexKey::new(self),
-            IndexKey::new(self),
-            false,
-            Sort::Ascending,
-        )?
-        .iter_ids(&mut cursors.index, |cursor, _| {
-            cursor.delete_current()?;
-            Ok(true)
-        })?;
+        let prefix = self.get_prefix();
+        let mut entry = cursors.index.move_to_gte(ByteKey::new(&prefix))?;
+        while let Some((key, _)) = entry {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            cursors.index.delete_current()?;
+            // the cursor points to the entry after the deleted one
+            entry = cursors.index.move_to_next()?;
+        }
         Ok(())
     }
 
+    pub fn count_keys(&self, cursors: &mut Cursors) -> Result<Vec<(Vec<u8>, u64)>> {
+        let prefix = self.get_prefix();
+        let mut counts = vec![];
+        let mut entry = cursors.index.move_to_gte(ByteKey::new(&prefix))?;
+        while let Some((key, _)) = entry {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let count = cursors.index.count_dups()?;
+            counts.push((key[prefix.len()..].to_vec(), count));
+            entry = cursors.index.move_to_next_key()?;
+        }
+        Ok(counts)
+    }
+
+    /// Returns the number of bytes used by the keys and values of all index entries.
+    pub fn byte_size(&self, cursors: &mut Cursors) -> Result<u64> {
+        let prefix = self.get_prefix();
+        let mut size = 0;
+        let mut entry = cursors.index.move_to_gte(ByteKey::new(&prefix))?;
+        while let Some((key, value)) = entry {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            size += (key.len() + value.len()) as u64;
+            entry = cursors.index.move_to_next()?;
+        }
+        Ok(size)
+    }
+
+    /// Rewrites all entries of a non-unique index so the duplicates of each key are stored
+    /// contiguously. Page counts refer to the whole index db.
+    pub fn optimize(&self, cursors: &mut Cursors) -> Result<IndexOptimizeStats> {
+        let pages_before = cursors.index.page_count()?;
+        let prefix = self.get_prefix();
+        let mut entries: Vec<(Vec<u8>, Vec<Vec<u8>>)> = vec![];
+        let mut entry = cursors.index.move_to_gte(ByteKey::new(&prefix))?;
+        while let Some((key, oid)) = entry {
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            match entries.last_mut() {
+                Some((last_key, oids)) if last_key.as_slice() == key => oids.push(oid.to_vec()),
+                _ => entries.push((key.to_vec(), vec![oid.to_vec()])),
+            }
+            cursors.index.delete_current()?;
+            entry = cursors.index.move_to_next()?;
+        }
+
+        let mut count = 0;
+        for (key, oids) in &entries {
+            for oid in oids {
+                cursors.index.put_append_dup(ByteKey::new(key), oid)?;
+            }
+            count += oids.len();
+        }
+
+        Ok(IndexOptimizeStats {
+            keys: entries.len(),
+            entries: count,
+            pages_before,
+            pages_after: cursors.index.page_count()?,
+        })
+    }
+
     pub fn create_keys(
         &self,
         object: IsarObject,
         mut callback: impl FnMut(&[u8]) -> Result<bool>,
     ) -> Result<()> {
         if self.multiple() {
+            let (_, base_properties) = self.properties.split_last().unwrap();
+            if self.sparse && Self::has_null_value(base_properties, object) {
+                return Ok(());
+            }
             self.create_multiple_keys(object, callback)
+        } else if self.sparse && Self::has_null_value(&self.properties, object) {
+            Ok(())
         } else {
             let bytes = self.create_single_key(object, vec![]);
             callback(&bytes)?;
@@ -181,11 +393,20 @@ impl Index {
         }
     }
 
+    fn has_null_value(properties: &[IndexProperty], object: IsarObject) -> bool {
+        properties.iter().any(|ip| object.is_null(ip.property))
+    }
+
     fn create_single_key(&self, object: IsarObject, buffer: Vec<u8>) -> Vec<u8> {
         let mut key = IndexKey::with_buffer(self, buffer);
-        for ip in &self.properties {
+        Self::add_properties_to_key(&mut key, &self.properties, object);
+        key.bytes
+    }
+
+    fn add_properties_to_key(key: &mut IndexKey, properties: &[IndexProperty], object: IsarObject) {
+        for ip in properties {
             match ip.property.data_type {
-                DataType::Byte => {
+                DataType::Byte | DataType::Bool => {
                     let value = object.read_byte(ip.property);
                     key.add_byte(value);
                 }
@@ -193,7 +414,7 @@ impl Index {
                     let value = object.read_int(ip.property);
                     key.add_int(value);
                 }
-                DataType::Long => {
+                DataType::Long | DataType::DateTime => {
                     let value = object.read_long(ip.property);
                     key.add_long(value);
                 }
@@ -216,35 +437,126 @@ impl Index {
                 _ => unimplemented!(),
             }
         }
-        key.bytes
     }
 
     fn create_multiple_keys(
+        &self,
+        object: IsarObject,
+        callback: impl FnMut(&[u8]) -> Result<bool>,
+    ) -> Result<()> {
+        if self.properties.last().unwrap().index_type == IndexType::Words {
+            self.create_multiple_word_keys(object, callback)
+        } else {
+            self.create_multiple_list_keys(object, callback)
+        }
+    }
+
+    fn create_multiple_list_keys(
         &self,
         object: IsarObject,
         mut callback: impl FnMut(&[u8]) -> Result<bool>,
     ) -> Result<()> {
-        let ip = self.properties.first().unwrap();
-        let value = ip.get_string_with_case(object);
-        let mut result = Ok(());
-        Self::create_word_keys(value.as_deref(), |word_key| match callback(word_key) {
-            Ok(cont) => cont,
-            Err(err) => {
-                result = Err(err);
-                false
+        let (ip, base_properties) = self.properties.split_last().unwrap();
+        let mut base_key = IndexKey::new(self);
+        Self::add_properties_to_key(&mut base_key, base_properties, object);
+        let element_key = |add: &dyn Fn(&mut IndexKey)| {
+            let mut key = base_key.clone();
+            add(&mut key);
+            key.bytes
+        };
+
+        let p = ip.property;
+        let keys: Option<Vec<Vec<u8>>> = match p.data_type {
+            DataType::ByteList => object.read_byte_list(p).map(|list| {
+                list.iter()
+                    .filter(|value| !self.sparse || **value != IsarObject::NULL_BYTE)
+                    .map(|value| element_key(&|key| key.add_byte(*value)))
+                    .collect()
+            }),
+            DataType::IntList => object.read_int_list(p).map(|list| {
+                list.iter()
+                    .filter(|value| !self.sparse || **value != IsarObject::NULL_INT)
+                    .map(|value| element_key(&|key| key.add_int(*value)))
+                    .collect()
+            }),
+            DataType::FloatList => object.read_float_list(p).map(|list| {
+                list.iter()
+                    .filter(|value| !self.sparse || !value.is_nan())
+                    .map(|value| element_key(&|key| key.add_float(*value)))
+                    .collect()
+            }),
+            DataType::LongList => object.read_long_list(p).map(|list| {
+                list.iter()
+                    .filter(|value| !self.sparse || **value != IsarObject::NULL_LONG)
+                    .map(|value| element_key(&|key| key.add_long(*value)))
+                    .collect()
+            }),
+            DataType::DoubleList => object.read_double_list(p).map(|list| {
+                list.iter()
+                    .filter(|value| !self.sparse || !value.is_nan())
+                    .map(|value| element_key(&|key| key.add_double(*value)))
+                    .collect()
+            }),
+            DataType::StringList => object.read_string_list(p).map(|list| {
+                let case_sensitive = ip.case_sensitive.unwrap();
+                list.iter()
+                    .filter(|value| !self.sparse || value.is_some())
+                    .map(|value| {
+                        element_key(&|key| match ip.index_type {
+                            IndexType::Value => key.add_string_value(*value, case_sensitive),
+                            IndexType::Hash => key.add_string_hash(*value, case_sensitive),
+                            _ => unimplemented!(),
+                        })
+                    })
+                    .collect()
+            }),
+            _ => unimplemented!(),
+        };
+
+        for key in keys.unwrap_or_default().into_iter().unique() {
+            if !callback(&key)? {
+                break;
             }
-        });
-        result
+        }
+        Ok(())
     }
 
-    pub fn create_word_keys(value: Option<&str>, mut callback: impl FnMut(&[u8]) -> bool) {
-        if let Some(str) = value {
-            for word in str.unicode_words().unique() {
-                if !callback(word.as_bytes()) {
+    /// Words are split from the original string by the word tokenizer and folded individually so
+    /// the keys match the ones built by `IndexKey::add_string_word()`.
+    fn create_multiple_word_keys(
+        &self,
+        object: IsarObject,
+        mut callback: impl FnMut(&[u8]) -> Result<bool>,
+    ) -> Result<()> {
+        let (ip, base_properties) = self.properties.split_last().unwrap();
+        let mut base_key = IndexKey::new(self);
+        Self::add_properties_to_key(&mut base_key, base_properties, object);
+        if let Some(str) = object.read_string(ip.property) {
+            let case_sensitive = ip.case_sensitive.unwrap();
+            let keys = self.word_tokenizer.tokenize(str).into_iter().map(|word| {
+                let mut key = base_key.clone();
+                key.add_string_word(word, case_sensitive);
+                key.bytes
+            });
+            for key in keys.unique() {
+                if !callback(&key)? {
                     break;
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Words longer than `max_string_size` are cut off and followed by a hash of the full word.
+    pub(crate) fn create_word_key(word: &[u8], max_string_size: usize) -> Cow<[u8]> {
+        if word.len() >= max_string_size {
+            let mut key = word[0..max_string_size].to_vec();
+            key.push(0);
+            key.extend_from_slice(&u64::to_le_bytes(wyhash(word, 0)));
+            Cow::Owned(key)
+        } else {
+            Cow::Borrowed(word)
+        }
     }
 
     #[cfg(test)]
@@ -277,6 +589,10 @@ mod tests {
     use crate::collection::IsarCollection;
     use crate::instance::IsarInstance;
     use crate::object::data_type::DataType;
+    use crate::query::filter::IntBetweenCond;
+    use crate::query::query_builder::WherePlan;
+    use crate::query::Sort;
+    use crate::schema::collection_schema::IndexPropertySchema;
     use crate::{col, ind, isar};
     use float_next_after::NextAfter;
 
@@ -362,9 +678,176 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_words_index_case() {
+        fn test(case_sensitive: bool, expected: &[(&str, Vec<i64>)]) {
+            isar!(isar, col => col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Words, Some(case_sensitive))));
+            let mut txn = isar.begin_txn(true, false).unwrap();
+            let values = ["Hello World", "hello there", "HELLO hello"];
+            for (i, value) in values.iter().enumerate() {
+                let mut builder = col.new_object_builder(None);
+                builder.write_long(i as i64 + 1);
+                builder.write_string(Some(value));
+                col.put(&mut txn, builder.finish()).unwrap();
+            }
+
+            for (word, oids) in expected {
+                let mut key = col.new_index_key(0).unwrap();
+                key.add_string_word(word, case_sensitive);
+                let mut qb = col.new_query_builder();
+                qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+                    .unwrap();
+                let mut result: Vec<i64> = qb
+                    .build()
+                    .find_all_vec(&mut txn)
+                    .unwrap()
+                    .iter()
+                    .map(|o| o.read_long(col.get_oid_property()))
+                    .collect();
+                result.sort_unstable();
+                assert_eq!(&result, oids);
+            }
+            txn.abort();
+            isar.close();
+        }
+
+        test(
+            true,
+            &[
+                ("Hello", vec![1]),
+                ("hello", vec![2, 3]),
+                ("HELLO", vec![3]),
+                ("world", vec![]),
+                ("World", vec![1]),
+            ],
+        );
+        test(
+            false,
+            &[
+                ("Hello", vec![1, 2, 3]),
+                ("HELLO", vec![1, 2, 3]),
+                ("WORLD", vec![1]),
+                ("there", vec![2]),
+            ],
+        );
+    }
+
     #[test]
     fn test_create_for_object_unique() {}
 
+    #[test]
+    fn test_multi_entry_int_list_index() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::IntList; ind!(field)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let lists: [&[i32]; 2] = [&[1, 2, 2, 3], &[3]];
+        for (oid, list) in lists.iter().enumerate() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid as i64 + 1);
+            builder.write_int_list(Some(list));
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let entry = |value: i32, oid: i64| {
+            let mut key = col.new_index_key(0).unwrap();
+            key.add_int(value);
+            (
+                key.bytes,
+                IntKey::new(col.get_id(), oid).as_bytes().to_vec(),
+            )
+        };
+        let index = col.debug_get_index(0);
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            [entry(1, 1), entry(2, 1), entry(3, 1), entry(3, 2)]
+                .iter()
+                .cloned()
+                .collect()
+        );
+
+        col.delete(&mut txn, 1).unwrap();
+        assert_eq!(
+            index.debug_dump(&mut txn),
+            [entry(3, 2)].iter().cloned().collect()
+        );
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_multi_entry_string_list_index() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::StringList; ind!(str field, IndexType::Value, Some(false))));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut builder = col.new_object_builder(None);
+        builder.write_long(1);
+        builder.write_string_list(Some(&[Some("A"), Some("a"), Some("b"), None]));
+        col.put(&mut txn, builder.finish()).unwrap();
+
+        let mut key = col.new_index_key(0).unwrap();
+        key.add_string_value(Some("a"), false);
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+            .unwrap();
+        assert_eq!(qb.build().count(&mut txn).unwrap(), 1);
+
+        let index = col.debug_get_index(0);
+        assert_eq!(index.debug_dump(&mut txn).len(), 3);
+        col.delete(&mut txn, 1).unwrap();
+        assert!(index.debug_dump(&mut txn).is_empty());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_descending_compound_index() {
+        let index = || {
+            (
+                vec![
+                    IndexPropertySchema::new("a", IndexType::Value, None),
+                    IndexPropertySchema::with_sort("b", IndexType::Value, None, Sort::Descending),
+                ],
+                false,
+                false,
+            )
+        };
+        isar!(isar, col => col!(oid => DataType::Long, a => DataType::Int, b => DataType::Int; index()));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for (oid, a, b) in [(1, 1, 1), (2, 1, 3), (3, 2, 5), (4, 1, 2), (5, 2, 4)].iter() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(*oid);
+            builder.write_int(*a);
+            builder.write_int(*b);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let find = |txn: &mut IsarTxn, lower: (i32, Option<i32>), upper: (i32, Option<i32>)| {
+            let key = |(a, b): (i32, Option<i32>)| {
+                let mut key = col.new_index_key(0).unwrap();
+                key.add_int(a);
+                if let Some(b) = b {
+                    key.add_int(b);
+                }
+                key
+            };
+            let mut qb = col.new_query_builder();
+            qb.add_index_where_clause(key(lower), true, key(upper), true, false, Sort::Ascending)
+                .unwrap();
+            qb.build()
+                .find_all_vec(txn)
+                .unwrap()
+                .iter()
+                .map(|o| o.read_long(col.get_oid_property()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            find(&mut txn, (i32::MIN, None), (3, None)),
+            vec![2, 4, 1, 3, 5]
+        );
+        assert_eq!(find(&mut txn, (1, Some(3)), (1, Some(2))), vec![2, 4]);
+        txn.abort();
+        isar.close();
+    }
+
     #[test]
     fn test_create_for_object_violate_unique() {
         isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field; true, false)));
@@ -380,13 +863,233 @@ mod tests {
         ob.write_int(5);
         let result = col.put(&mut txn, ob.finish());
         match result {
+            Err(IsarError::UniqueViolated { index_id, oid }) => {
+                assert_eq!(index_id, col.debug_get_index(0).id);
+                assert_eq!(oid, 2);
+            }
+            _ => panic!("wrong error"),
+        };
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_long_strings_unique_index() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(true); true, false)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let prefix = "a".repeat(1024);
+        let put = |txn: &mut IsarTxn, oid: i64, value: &str| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_string(Some(value));
+            col.put(txn, ob.finish())
+        };
+        put(&mut txn, 1, &format!("{}x", prefix)).unwrap();
+        put(&mut txn, 2, &format!("{}y", prefix)).unwrap();
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        match put(&mut txn, 3, &format!("{}x", prefix)) {
+            Err(IsarError::UniqueViolated { .. }) => {}
+            _ => panic!("wrong error"),
+        };
+        txn.abort();
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let value = format!("{}y", prefix);
+        let mut key = col.new_index_key(0).unwrap();
+        key.add_string_value(Some(&value), true);
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+            .unwrap();
+        let result = qb.build().find_all_vec(&mut txn).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].read_long(col.get_oid_property()), 2);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_max_string_size() {
+        let schema = || {
+            let mut col = col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(true); true, false));
+            col.indexes[0].set_max_string_size(4);
+            col
+        };
+        isar!(isar, col => schema());
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for (oid, value) in [(1, "abc"), (2, "abcdef"), (3, "abcdeg")].iter() {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(*oid);
+            ob.write_string(Some(value));
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+
+        let index = col.debug_get_index(0);
+        let key_lengths: HashSet<usize> = index
+            .debug_dump(&mut txn)
+            .into_iter()
+            .map(|(key, _)| key.len())
+            .collect();
+        assert_eq!(key_lengths, vec![2 + 5, 2 + 14].into_iter().collect());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_reject_long_strings() {
+        let schema = |reject: bool| {
+            let mut col = col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(false); true, false));
+            col.indexes[0].set_max_string_size(4);
+            col.indexes[0].set_reject_long_strings(reject);
+            col
+        };
+        let put = |col: &IsarCollection, txn: &mut IsarTxn, oid: i64, value: &str| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_string(Some(value));
+            col.put(txn, ob.finish())
+        };
+
+        isar!(isar, col => schema(false));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(col, &mut txn, 1, "abcdef").unwrap();
+        put(col, &mut txn, 2, "abcdeg").unwrap();
+        txn.commit().unwrap();
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        match put(col, &mut txn, 3, "ABCDEF") {
+            Err(IsarError::UniqueViolated { .. }) => {}
+            _ => panic!("wrong error"),
+        }
+        txn.abort();
+        isar.close();
+
+        isar!(isar, col => schema(true));
+        let index_id = col.debug_get_index(0).id;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(col, &mut txn, 1, "abc").unwrap();
+        // case folding changes the size of the indexed value
+        put(col, &mut txn, 3, "aẞ").unwrap();
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        match put(col, &mut txn, 2, "abcdef") {
+            Err(IsarError::IndexKeyTooLarge { index_id: id, size }) => {
+                assert_eq!(id, index_id);
+                assert_eq!(size, 6);
+            }
+            _ => panic!("wrong error"),
+        }
+        txn.abort();
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        match put(col, &mut txn, 4, "aẞẞ") {
+            Err(IsarError::IndexKeyTooLarge { size, .. }) => assert_eq!(size, 5),
+            _ => panic!("wrong error"),
+        }
+        txn.abort();
+        isar.close();
+
+        let mut col = col!(oid => DataType::Long, field => DataType::Int; ind!(field));
+        col.indexes[0].set_reject_long_strings(true);
+        assert!(crate::schema::Schema::new(vec![col]).is_err());
+    }
+
+    #[test]
+    fn test_enable_reject_long_strings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let schema = |reject: bool| {
+            let mut col = col!("col", oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(true)));
+            col.indexes[0].set_max_string_size(4);
+            col.indexes[0].set_reject_long_strings(reject);
+            col
+        };
+        let open = |reject: bool| {
+            let schema = crate::schema::Schema::new(vec![schema(reject)]).unwrap();
+            IsarInstance::open(path, dir.path().into(), 10000000, schema, None)
+        };
+
+        isar!(path: path, isar, col => schema(false));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for (oid, value) in &[(1, "abc"), (2, "abcdef")] {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(*oid);
+            ob.write_string(Some(value));
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+        assert!(isar.close());
+
+        // the index is rebuilt and the existing long string is rejected
+        match open(true) {
+            Err(IsarError::IndexKeyTooLarge { size, .. }) => assert_eq!(size, 6),
+            _ => panic!("wrong error"),
+        }
+
+        isar!(path: path, isar, col => schema(false));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        col.delete(&mut txn, 2).unwrap();
+        txn.commit().unwrap();
+        let index_id = col.debug_get_index(0).id;
+        assert!(isar.close());
+
+        isar!(path: path, isar, col => schema(true));
+        assert_ne!(col.debug_get_index(0).id, index_id);
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert_eq!(col.debug_get_index(0).debug_dump(&mut txn).len(), 1);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_sparse_unique_index() {
+        let schema = || {
+            let mut col =
+                col!(oid => DataType::Long, field => DataType::Int; ind!(field; true, false));
+            col.indexes[0].set_sparse(true);
+            col
+        };
+        isar!(isar, col => schema());
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let put = |txn: &mut IsarTxn, oid: i64, value: i32| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(value);
+            col.put(txn, ob.finish())
+        };
+        put(&mut txn, 1, IsarObject::NULL_INT).unwrap();
+        put(&mut txn, 2, IsarObject::NULL_INT).unwrap();
+        put(&mut txn, 3, 5).unwrap();
+        put(&mut txn, 4, IsarObject::NULL_INT).unwrap();
+        match put(&mut txn, 5, 5) {
             Err(IsarError::UniqueViolated { .. }) => {}
             _ => panic!("wrong error"),
         };
+
+        let index = col.debug_get_index(0);
+        assert_eq!(index.debug_dump(&mut txn).len(), 1);
+        assert_eq!(col.new_query_builder().build().count(&mut txn).unwrap(), 4);
+
+        // the index can't be used for filters matching null values
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut qb = col.new_query_builder();
+        let filter = IntBetweenCond::filter(field, IsarObject::NULL_INT, 5).unwrap();
+        assert_eq!(qb.where_optimized(filter).unwrap(), WherePlan::FilterOnly);
+        assert_eq!(qb.build().count(&mut txn).unwrap(), 4);
         txn.abort();
         isar.close();
     }
 
+    #[test]
+    fn test_create_word_key() {
+        assert_eq!(Index::create_word_key(b"abc", 4), &b"abc"[..]);
+        let key = Index::create_word_key(b"abcdef", 4);
+        assert_eq!(&key[..5], b"abcd\0");
+        assert_eq!(key.len(), 13);
+        assert_ne!(key, Index::create_word_key(b"abcdeg", 4));
+    }
+
     #[test]
     fn test_create_for_object_compound() {}
 
@@ -439,7 +1142,7 @@ mod tests {
             (f32::MIN, vec![0, 128, 0, 0]),
             (f32::MIN.next_after(f32::MAX), vec![0, 128, 0, 1]),
             ((-0.0).next_after(f32::MIN), vec![127, 255, 255, 254]),
-            (-0.0, vec![127, 255, 255, 255]),
+            (-0.0, vec![128, 0, 0, 0]),
             (0.0, vec![128, 0, 0, 0]),
             (0.0.next_after(f32::MAX), vec![128, 0, 0, 1]),
             (f32::MAX.next_after(f32::MIN), vec![255, 127, 255, 254]),
@@ -462,7 +1165,7 @@ mod tests {
                 (-0.0).next_after(f64::MIN),
                 vec![127, 255, 255, 255, 255, 255, 255, 254],
             ),
-            (-0.0, vec![127, 255, 255, 255, 255, 255, 255, 255]),
+            (-0.0, vec![128, 0, 0, 0, 0, 0, 0, 0]),
             (0.0, vec![128, 0, 0, 0, 0, 0, 0, 0]),
             (0.0.next_after(f64::MAX), vec![128, 0, 0, 0, 0, 0, 0, 1]),
             (
@@ -523,11 +1226,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_words_index_tokenizer() {
+        let schema = |tokenizer: WordTokenizer| {
+            let mut col = col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Words, Some(false)));
+            col.indexes[0].set_word_tokenizer(tokenizer);
+            col
+        };
+        let find = |tokenizer: WordTokenizer, words: &[&str]| -> Vec<Vec<i64>> {
+            isar!(isar, col => schema(tokenizer));
+            let mut txn = isar.begin_txn(true, false).unwrap();
+            for (i, value) in ["Hello, World", "shell script"].iter().enumerate() {
+                let mut builder = col.new_object_builder(None);
+                builder.write_long(i as i64 + 1);
+                builder.write_string(Some(value));
+                col.put(&mut txn, builder.finish()).unwrap();
+            }
+            let result = words
+                .iter()
+                .map(|word| {
+                    let mut key = col.new_index_key(0).unwrap();
+                    key.add_string_word(word, false);
+                    let mut qb = col.new_query_builder();
+                    qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+                        .unwrap();
+                    let mut oids: Vec<i64> = qb
+                        .build()
+                        .find_all_vec(&mut txn)
+                        .unwrap()
+                        .iter()
+                        .map(|o| o.read_long(col.get_oid_property()))
+                        .collect();
+                    oids.sort_unstable();
+                    oids
+                })
+                .collect();
+            txn.abort();
+            isar.close();
+            result
+        };
+
+        let words = ["hello", "hello,", "ell", "script"];
+        assert_eq!(
+            find(WordTokenizer::UnicodeWords, &words),
+            vec![vec![1], vec![], vec![], vec![2]]
+        );
+        assert_eq!(
+            find(WordTokenizer::Whitespace, &words),
+            vec![vec![], vec![1], vec![], vec![2]]
+        );
+        assert_eq!(
+            find(WordTokenizer::NGrams { n: 3 }, &words),
+            vec![vec![], vec![], vec![1, 2], vec![]]
+        );
+    }
+
     #[test]
     fn test_get_string_word_keys() {
         let pairs: Vec<(Option<&str>, Vec<&str>)> = vec![
             (None, vec![]),
-            (Some(""), vec![""]),
+            (Some(""), vec![]),
             (Some("hello"), vec!["hello"]),
             (
                 Some("The quick brown fox brown can’t jump 32.3 feet right."),
@@ -537,12 +1295,10 @@ mod tests {
             ),
         ];
         for (str, words) in pairs {
-            let mut i = 0;
-            Index::create_word_keys(str, |word| {
-                assert_eq!(word, words[i].as_bytes());
-                i += 1;
-                true
-            })
+            let tokens = str
+                .map(|str| WordTokenizer::UnicodeWords.tokenize(str))
+                .unwrap_or_default();
+            assert_eq!(tokens.into_iter().unique().collect_vec(), words);
         }
     }
 }
@@ -1,3 +1,4 @@
+use crate::batch_writer::BatchWriter;
 use crate::collection::IsarCollection;
 use crate::error::*;
 use crate::lmdb::cursor::Cursor;
@@ -7,22 +8,125 @@ use crate::lmdb::txn::Txn;
 use crate::query::Query;
 use crate::schema::schema_manager::SchemaManger;
 use crate::schema::Schema;
-use crate::txn::{Cursors, IsarTxn};
+use crate::snapshot::Snapshot;
+use crate::txn::{Cursors, IsarTxn, TxnFuture};
 use crate::watch::change_set::ChangeSet;
 use crate::watch::isar_watchers::{IsarWatchers, WatcherModifier};
-use crate::watch::watcher::WatcherCallback;
+use crate::watch::watcher::{
+    ChangeWatcherCallback, CountWatcherCallback, QueryChangeWatcherCallback, WatcherCallback,
+};
 use crate::watch::WatchHandle;
 use crossbeam_channel::{unbounded, Sender};
 use hashbrown::hash_map::Entry;
 use hashbrown::HashMap;
+use lmdb_sys as ffi;
 use once_cell::sync::Lazy;
 use rand::random;
-use std::path::PathBuf;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use wyhash::wyhash;
+
+const FREE_DBI: ffi::MDB_dbi = 0;
+const MAIN_DBI: ffi::MDB_dbi = 1;
+
+/// Value of `IsarInstance::open_txns` while the map is being resized.
+const RESIZING: usize = usize::MAX;
 
 static INSTANCES: Lazy<RwLock<HashMap<String, Arc<IsarInstance>>>> =
     Lazy::new(|| RwLock::new(HashMap::new()));
 
+/// In-memory instances are kept apart so they never shadow a persisted instance with the same name.
+static MEMORY_INSTANCES: Lazy<RwLock<HashMap<String, Arc<IsarInstance>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Controls when committed transactions are flushed to disk.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Durability {
+    /// Data and metadata are synced on every commit.
+    Full,
+    /// The metadata page is not synced on commit. A system crash may undo the last
+    /// transaction but the database stays consistent.
+    NoMetaSync,
+    /// Nothing is synced on commit; the OS decides when data is written. A system crash may
+    /// lose recent transactions and, depending on the file system, corrupt the database.
+    /// Use `IsarInstance::sync()` to flush manually.
+    NoSync,
+}
+
+impl Default for Durability {
+    fn default() -> Self {
+        Durability::Full
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct OpenOptions {
+    /// Disables LMDB's lock file. The caller has to guarantee that no other process opens the
+    /// database and that transactions are not used concurrently.
+    pub no_lock: bool,
+    /// Opens the database without write access. Write transactions fail with
+    /// `IsarError::ReadOnly` and the schema cannot add indexes to existing collections.
+    pub read_only: bool,
+    pub durability: Durability,
+}
+
+impl OpenOptions {
+    fn env_flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.no_lock {
+            flags |= ffi::MDB_NOLOCK;
+        }
+        if self.read_only {
+            flags |= ffi::MDB_RDONLY;
+        }
+        match self.durability {
+            Durability::Full => {}
+            Durability::NoMetaSync => flags |= ffi::MDB_NOMETASYNC,
+            Durability::NoSync => flags |= ffi::MDB_NOSYNC,
+        }
+        flags
+    }
+}
+
+/// Controls how the map is enlarged when a write transaction runs out of space.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MapGrowth {
+    pub factor: usize,
+    pub max_size: usize,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CompactStats {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Serialize, Clone, Eq, PartialEq, Debug)]
+pub struct CollectionStats {
+    pub name: String,
+    pub objects: u64,
+    #[serde(rename = "indexEntries")]
+    pub index_entries: u64,
+    #[serde(rename = "storageSize")]
+    pub storage_size: u64,
+}
+
+#[derive(Serialize, Clone, Eq, PartialEq, Debug)]
+pub struct IsarStats {
+    pub collections: Vec<CollectionStats>,
+    #[serde(rename = "fileSize")]
+    pub file_size: u64,
+    #[serde(rename = "pageSize")]
+    pub page_size: u64,
+    #[serde(rename = "freePages")]
+    pub free_pages: u64,
+}
+
 pub struct IsarInstance {
     env: Env,
     dbs: DataDbs,
@@ -30,8 +134,16 @@ pub struct IsarInstance {
     collections: Vec<IsarCollection>,
     watchers: Mutex<IsarWatchers>,
     watcher_modifier_sender: Sender<WatcherModifier>,
+    pub(crate) write_txn_active: AtomicBool,
+    pub(crate) open_txns: AtomicUsize,
+    read_only: bool,
+    in_memory: bool,
+    async_writer: Mutex<Option<Sender<AsyncJob>>>,
+    key_fingerprint: Option<u64>,
 }
 
+type AsyncJob = Box<dyn FnOnce() + Send + 'static>;
+
 impl IsarInstance {
     pub const ENCRYPTION_KEY_LEN: usize = 32;
 
@@ -41,13 +153,104 @@ impl IsarInstance {
         max_size: usize,
         schema: Schema,
         encryption_key: Option<&[u8]>,
+    ) -> Result<Arc<Self>> {
+        Self::open_with_options(
+            name,
+            dir,
+            max_size,
+            schema,
+            encryption_key,
+            OpenOptions::default(),
+        )
+    }
+
+    /// Opens an instance whose pages are encrypted with AES-256-GCM. Opening an existing
+    /// instance with a different key fails with `IsarError::CryptoError`.
+    pub fn open_encrypted(
+        name: &str,
+        dir: PathBuf,
+        max_size: usize,
+        schema: Schema,
+        encryption_key: &[u8],
+    ) -> Result<Arc<Self>> {
+        Self::open(name, dir, max_size, schema, Some(encryption_key))
+    }
+
+    /// Opens an existing instance without write access. Multiple processes may open the same
+    /// database this way.
+    pub fn open_readonly(
+        name: &str,
+        dir: PathBuf,
+        max_size: usize,
+        schema: Schema,
+    ) -> Result<Arc<Self>> {
+        let options = OpenOptions {
+            read_only: true,
+            ..OpenOptions::default()
+        };
+        Self::open_with_options(name, dir, max_size, schema, None, options)
+    }
+
+    pub fn open_with_options(
+        name: &str,
+        dir: PathBuf,
+        max_size: usize,
+        schema: Schema,
+        encryption_key: Option<&[u8]>,
+        options: OpenOptions,
     ) -> Result<Arc<Self>> {
         let mut lock = INSTANCES.write().unwrap();
+        match lock.entry(name.to_string()) {
+            Entry::Occupied(e) => {
+                // the key of an open instance is not checked by LMDB again
+                if e.get().key_fingerprint != encryption_key.map(Self::fingerprint_key) {
+                    return Err(IsarError::CryptoError {});
+                }
+                Ok(e.get().clone())
+            }
+            Entry::Vacant(e) => {
+                let mut path = dir;
+                path.push(e.key());
+                let new_instance = Self::open_internal(
+                    e.key(),
+                    path.to_str().unwrap(),
+                    max_size,
+                    schema,
+                    encryption_key,
+                    options.env_flags(),
+                )?;
+                let instance_ref = e.insert(Arc::new(new_instance));
+                Ok(instance_ref.clone())
+            }
+        }
+    }
+
+    /// Opens an instance that is not persisted. In-memory instances have their own namespace and
+    /// can be retrieved with `get_in_memory_instance()`.
+    ///
+    /// LMDB needs a file to map, so the data lives in an unlinked file that is never synced. Where
+    /// `/dev/shm` is available, the file is backed by memory. Otherwise it is created in the
+    /// temporary directory and the OS may write its pages to disk. On platforms that do not
+    /// allow removing open files, the file is left behind.
+    pub fn open_in_memory(name: &str, max_size: usize, schema: Schema) -> Result<Arc<Self>> {
+        let mut lock = MEMORY_INSTANCES.write().unwrap();
         match lock.entry(name.to_string()) {
             Entry::Occupied(e) => Ok(e.get().clone()),
             Entry::Vacant(e) => {
-                let new_instance =
-                    Self::open_internal(e.key(), dir, max_size, schema, encryption_key)?;
+                let shm = Path::new("/dev/shm");
+                let dir = if shm.is_dir() {
+                    shm.to_path_buf()
+                } else {
+                    std::env::temp_dir()
+                };
+                let path = dir.join(format!("isar-{:016x}.mdb", random::<u64>()));
+                let path = path.to_str().unwrap();
+                let flags = ffi::MDB_NOSUBDIR | ffi::MDB_NOSYNC | ffi::MDB_NOMETASYNC;
+                let mut new_instance =
+                    Self::open_internal(e.key(), path, max_size, schema, None, flags)?;
+                new_instance.in_memory = true;
+                let _ = std::fs::remove_file(path);
+                let _ = std::fs::remove_file(format!("{}-lock", path));
                 let instance_ref = e.insert(Arc::new(new_instance));
                 Ok(instance_ref.clone())
             }
@@ -56,10 +259,11 @@ impl IsarInstance {
 
     fn open_internal(
         name: &str,
-        mut dir: PathBuf,
+        path: &str,
         max_size: usize,
         schema: Schema,
         encryption_key: Option<&[u8]>,
+        flags: u32,
     ) -> Result<Self> {
         if let Some(encryption_key) = encryption_key {
             if encryption_key.len() != IsarInstance::ENCRYPTION_KEY_LEN {
@@ -67,18 +271,17 @@ impl IsarInstance {
             }
         }
 
-        dir.push(name);
-        let path = dir.to_str().unwrap();
-        let env = Env::create(path, 4, max_size, encryption_key)?;
-        let dbs = IsarInstance::open_databases(&env)?;
+        let read_only = flags & ffi::MDB_RDONLY != 0;
+        let env = Env::create(path, 4, max_size, encryption_key, flags)?;
+        let dbs = IsarInstance::open_databases(&env, read_only)?;
 
-        let txn = env.txn(true)?;
+        let txn = env.txn(!read_only)?;
         let collections = {
             let info_cursor = dbs.open_info_cursor(&txn)?;
             let cursors = dbs.open_cursors(&txn)?;
             let cursors2 = dbs.open_cursors(&txn)?;
 
-            let mut manager = SchemaManger::new(info_cursor, cursors, cursors2);
+            let mut manager = SchemaManger::new(info_cursor, cursors, cursors2, read_only);
             manager.check_isar_version()?;
             manager.get_collections(schema)?
         };
@@ -93,15 +296,30 @@ impl IsarInstance {
             collections,
             watchers: Mutex::new(IsarWatchers::new(rx)),
             watcher_modifier_sender: tx,
+            write_txn_active: AtomicBool::new(false),
+            open_txns: AtomicUsize::new(0),
+            read_only,
+            in_memory: false,
+            async_writer: Mutex::new(None),
+            key_fingerprint: encryption_key.map(Self::fingerprint_key),
         })
     }
 
+    fn fingerprint_key(encryption_key: &[u8]) -> u64 {
+        wyhash(encryption_key, 0)
+    }
+
     pub fn get_instance(name: &str) -> Option<Arc<Self>> {
         INSTANCES.read().unwrap().get(name).cloned()
     }
 
-    fn open_databases(env: &Env) -> Result<DataDbs> {
-        let txn = env.txn(true)?;
+    pub fn get_in_memory_instance(name: &str) -> Option<Arc<Self>> {
+        MEMORY_INSTANCES.read().unwrap().get(name).cloned()
+    }
+
+    fn open_databases(env: &Env, read_only: bool) -> Result<DataDbs> {
+        // the databases of a read-only instance have to exist already
+        let txn = env.txn(!read_only)?;
         let info = Db::open(&txn, "info", false, false, false)?;
         let data = Db::open(&txn, "data", true, false, false)?;
         let index = Db::open(&txn, "index", false, true, true)?;
@@ -119,7 +337,14 @@ impl IsarInstance {
         self.dbs.open_cursors(txn)
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     pub fn begin_txn(&self, write: bool, silent: bool) -> Result<IsarTxn> {
+        if write && self.read_only {
+            return Err(IsarError::ReadOnly {});
+        }
         let change_set = if write && !silent {
             let mut watchers_lock = self.watchers.lock().unwrap();
             watchers_lock.sync();
@@ -129,8 +354,288 @@ impl IsarInstance {
             None
         };
 
-        let txn = self.env.txn(write)?;
-        IsarTxn::new(self, txn, write, change_set)
+        self.register_txn();
+        let txn = self
+            .env
+            .txn(write)
+            .and_then(|txn| IsarTxn::new(self, txn, write, change_set));
+        if txn.is_err() {
+            self.open_txns.fetch_sub(1, Ordering::SeqCst);
+        }
+        txn
+    }
+
+    /// Counts a new transaction. Waits while the map is being resized since LMDB does not allow
+    /// transactions to begin during a resize.
+    fn register_txn(&self) {
+        while self
+            .open_txns
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                if open == RESIZING {
+                    None
+                } else {
+                    Some(open + 1)
+                }
+            })
+            .is_err()
+        {
+            thread::yield_now();
+        }
+    }
+
+    /// Runs `job`, which uses LMDB transactions without an `IsarTxn`, while it is counted as an
+    /// open transaction.
+    fn with_registered_txn<T>(&self, job: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.register_txn();
+        let result = job();
+        self.open_txns.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    /// Runs `job` in a transaction on a background thread. The returned future resolves once the
+    /// transaction has been committed. Write transactions run one after another on a dedicated
+    /// writer thread since only one write transaction may be open at a time. Each read
+    /// transaction runs on its own thread.
+    pub fn begin_txn_async<T, F>(
+        self: &Arc<Self>,
+        write: bool,
+        silent: bool,
+        job: F,
+    ) -> TxnFuture<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&IsarInstance, &mut IsarTxn) -> Result<T> + Send + 'static,
+    {
+        let (future, completer) = TxnFuture::new();
+        let isar = self.clone();
+        let run = move || {
+            let result = isar.begin_txn(write, silent).and_then(|mut txn| {
+                let result = job(&isar, &mut txn)?;
+                if write {
+                    txn.commit()?;
+                } else {
+                    txn.abort();
+                }
+                Ok(result)
+            });
+            // release the instance before the future resolves so it can be closed
+            drop(isar);
+            completer.complete(result);
+        };
+
+        if write {
+            let mut async_writer = self.async_writer.lock().unwrap();
+            let sender = async_writer.get_or_insert_with(|| {
+                let (tx, rx) = unbounded::<AsyncJob>();
+                thread::spawn(move || {
+                    for job in rx {
+                        // a panicking job must not stop the writer
+                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+                    }
+                });
+                tx
+            });
+            sender.send(Box::new(run)).unwrap();
+        } else {
+            thread::spawn(run);
+        }
+        future
+    }
+
+    /// Runs `job` in a new transaction. Write transactions are committed if `job` succeeds and
+    /// aborted otherwise, so none of its changes are kept if it fails.
+    pub fn transaction<T>(
+        &self,
+        write: bool,
+        job: impl FnOnce(&mut IsarTxn) -> Result<T>,
+    ) -> Result<T> {
+        let mut txn = self.begin_txn(write, false)?;
+        let result = job(&mut txn)?;
+        if write {
+            txn.commit()?;
+        } else {
+            txn.abort();
+        }
+        Ok(result)
+    }
+
+    /// Runs `job` in a write transaction and commits it. If the database is full, the map size
+    /// is multiplied by `growth.factor` (at most `growth.max_size`) and `job` is retried once in
+    /// a new transaction. The map cannot be resized while other transactions or snapshots of this
+    /// instance are open, in that case an `IllegalArg` error is returned.
+    pub fn write_txn_autogrow<T>(
+        &self,
+        silent: bool,
+        growth: MapGrowth,
+        mut job: impl FnMut(&mut IsarTxn) -> Result<T>,
+    ) -> Result<T> {
+        match self.run_write_txn(silent, &mut job) {
+            Err(IsarError::DbFull {}) => {
+                let size = self.env.map_size()?;
+                let new_size = size.saturating_mul(growth.factor).min(growth.max_size);
+                if new_size <= size {
+                    return Err(IsarError::DbFull {});
+                }
+                if self
+                    .open_txns
+                    .compare_exchange(0, RESIZING, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_err()
+                {
+                    return illegal_arg(
+                        "Cannot resize the database while other transactions are open.",
+                    );
+                }
+                let result = self.env.set_map_size(new_size);
+                self.open_txns.store(0, Ordering::SeqCst);
+                result?;
+                self.run_write_txn(silent, &mut job)
+            }
+            result => result,
+        }
+    }
+
+    fn run_write_txn<T>(
+        &self,
+        silent: bool,
+        job: &mut impl FnMut(&mut IsarTxn) -> Result<T>,
+    ) -> Result<T> {
+        let mut txn = self.begin_txn(true, silent)?;
+        let result = job(&mut txn)?;
+        txn.commit()?;
+        Ok(result)
+    }
+
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let txn = self.begin_txn(false, false)?;
+        Ok(Snapshot::new(txn))
+    }
+
+    /// Begins a `BatchWriter` that commits its writes every `chunk_size` writes.
+    pub fn batch_writer(&self, silent: bool, chunk_size: usize) -> Result<BatchWriter> {
+        if chunk_size == 0 {
+            return illegal_arg("The chunk size needs to be at least one.");
+        }
+        BatchWriter::new(self, silent, chunk_size)
+    }
+
+    /// Flushes committed transactions to disk. Only needed for instances opened with relaxed
+    /// `Durability`.
+    pub fn sync(&self) -> Result<()> {
+        self.env.sync()
+    }
+
+    /// Writes a copy of the database without free pages to `data.mdb` in `target_dir`. The copy
+    /// can be opened in place of the original file.
+    pub fn compact(&self, target_dir: &str) -> Result<CompactStats> {
+        if self.write_txn_active.load(Ordering::SeqCst) {
+            return illegal_arg("Cannot compact while a write transaction is open.");
+        }
+        let bytes_before = self.env.used_size()?;
+        std::fs::create_dir_all(target_dir)?;
+        let path = Path::new(target_dir).join("data.mdb");
+        let file = File::create(&path)?;
+        self.with_registered_txn(|| self.env.copy_to_file(&file, true))?;
+        file.sync_all()?;
+        Ok(CompactStats {
+            bytes_before,
+            bytes_after: file.metadata()?.len(),
+        })
+    }
+
+    /// Writes a consistent copy of the last committed state to `writer` and returns the number of
+    /// bytes written. Writes may continue during the backup. The output is an LMDB environment
+    /// that can be restored by saving it as `data.mdb` in the directory of an instance.
+    ///
+    /// On Unix, the copy is streamed to `writer` through a pipe. Other platforms write it to a
+    /// temporary file first.
+    #[cfg(unix)]
+    pub fn backup_to<W: Write>(&self, mut writer: W) -> Result<u64> {
+        let (mut reader, pipe_writer) = pipe()?;
+        let env = &self.env;
+        self.with_registered_txn(|| {
+            thread::scope(|s| {
+                let copy = s.spawn(move || env.copy_to_file(&pipe_writer, true));
+                let result = std::io::copy(&mut reader, &mut writer);
+                if result.is_err() {
+                    // keep draining the pipe so the copy neither blocks nor fails with EPIPE
+                    let _ = std::io::copy(&mut reader, &mut std::io::sink());
+                }
+                copy.join().unwrap()?;
+                Ok(result?)
+            })
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn backup_to<W: Write>(&self, mut writer: W) -> Result<u64> {
+        let path = std::env::temp_dir().join(format!("isar-backup-{:016x}", random::<u64>()));
+        let result = (|| {
+            let file = File::create(&path)?;
+            self.with_registered_txn(|| self.env.copy_to_file(&file, true))?;
+            let mut file = File::open(&path)?;
+            Ok(std::io::copy(&mut file, &mut writer)?)
+        })();
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// Returns the schema stored in the database with the ids and property offsets that have
+    /// been assigned to it.
+    pub fn get_schema(&self) -> Result<Schema> {
+        let schema = self.with_registered_txn(|| {
+            let txn = self.env.txn(false)?;
+            let mut info_cursor = self.dbs.open_info_cursor(&txn)?;
+            let schema = SchemaManger::read_schema(&mut info_cursor)?;
+            drop(info_cursor);
+            txn.abort();
+            Ok(schema)
+        })?;
+        schema.ok_or(IsarError::DbCorrupted {
+            message: "Schema does not exist.".to_string(),
+        })
+    }
+
+    pub fn stats(&self) -> Result<IsarStats> {
+        let mut txn = self.begin_txn(false, false)?;
+        let mut collections = vec![];
+        for collection in &self.collections {
+            let mut qb = collection.new_query_builder();
+            qb.skip_default_sort();
+            let objects = qb.build().count(&mut txn)?;
+            collections.push(CollectionStats {
+                name: collection.get_name().to_string(),
+                objects: objects as u64,
+                index_entries: collection.count_index_entries(&mut txn)?,
+                storage_size: collection.storage_size(&mut txn)?,
+            });
+        }
+        txn.abort();
+
+        let (pages, page_size) = self.env.page_info()?;
+        let used_pages = self.with_registered_txn(|| {
+            let txn = self.env.txn(false)?;
+            // the two meta pages, the free list db and the main db are not part of DataDbs
+            let mut used_pages = 2;
+            for dbi in &[FREE_DBI, MAIN_DBI] {
+                let db = Db {
+                    dbi: *dbi,
+                    dup: false,
+                };
+                used_pages += db.page_count(&txn)?;
+            }
+            for db in &[self.dbs.info, self.dbs.data, self.dbs.index, self.dbs.links] {
+                used_pages += db.page_count(&txn)?;
+            }
+            txn.abort();
+            Ok(used_pages)
+        })?;
+
+        Ok(IsarStats {
+            collections,
+            file_size: pages * page_size,
+            page_size,
+            free_pages: pages.saturating_sub(used_pages),
+        })
     }
 
     pub fn get_collection(&self, collection_index: usize) -> Option<&IsarCollection> {
@@ -143,9 +648,22 @@ impl IsarInstance {
             .find(|c| c.get_name() == collection_name)
     }
 
+    /// Clears all collections in a single write and returns the number of deleted objects.
+    /// Watchers are notified like for `IsarCollection::clear()`.
+    pub fn clear_all(&self, txn: &mut IsarTxn) -> Result<usize> {
+        let mut counter = 0;
+        for collection in &self.collections {
+            counter += collection.clear(txn)?;
+        }
+        Ok(counter)
+    }
+
     fn new_watcher(&self, start: WatcherModifier, stop: WatcherModifier) -> WatchHandle {
         self.watcher_modifier_sender.try_send(start).unwrap();
+        self.new_watch_handle(stop)
+    }
 
+    fn new_watch_handle(&self, stop: WatcherModifier) -> WatchHandle {
         let sender = self.watcher_modifier_sender.clone();
         WatchHandle::new(Box::new(move || {
             let _ = sender.try_send(stop);
@@ -190,28 +708,153 @@ impl IsarInstance {
         )
     }
 
+    /// If `fire_immediately` is set, the callback is invoked once on the calling thread after the
+    /// watcher has been registered, without holding the watchers lock.
     pub fn watch_query(
         &self,
         collection: &IsarCollection,
         query: Query,
+        fire_immediately: bool,
         callback: WatcherCallback,
+    ) -> WatchHandle {
+        let watcher_id = random();
+        let col_id = collection.get_id();
+        let callback: Arc<dyn Fn() + Send + Sync> = Arc::from(callback);
+        let watcher_callback = callback.clone();
+        let handle = self.new_watcher(
+            Box::new(move |iw| {
+                iw.get_col_watchers(col_id).add_query_watcher(
+                    watcher_id,
+                    query,
+                    Box::new(move || watcher_callback()),
+                );
+            }),
+            Box::new(move |iw| {
+                iw.get_col_watchers(col_id).remove_query_watcher(watcher_id);
+            }),
+        );
+        if fire_immediately {
+            // the registration is only queued, so it is applied before the callback runs
+            self.watchers.lock().unwrap().sync();
+            callback();
+        }
+        handle
+    }
+
+    /// The callback is invoked after each committed transaction for every changed object that
+    /// entered, left or stayed in the results of `query`. Only the where clauses and the filter
+    /// of the query are considered.
+    pub fn watch_query_changes(
+        &self,
+        collection: &IsarCollection,
+        query: Query,
+        callback: QueryChangeWatcherCallback,
     ) -> WatchHandle {
         let watcher_id = random();
         let col_id = collection.get_id();
         self.new_watcher(
             Box::new(move |iw| {
                 iw.get_col_watchers(col_id)
-                    .add_query_watcher(watcher_id, query, callback);
+                    .add_query_change_watcher(watcher_id, query, callback);
             }),
             Box::new(move |iw| {
-                iw.get_col_watchers(col_id).remove_query_watcher(watcher_id);
+                iw.get_col_watchers(col_id)
+                    .remove_query_change_watcher(watcher_id);
+            }),
+        )
+    }
+
+    /// The callback receives the id, the kind of change and the new object for every put or
+    /// delete in the collection after the transaction has been committed.
+    pub fn watch_collection_changes(
+        &self,
+        collection: &IsarCollection,
+        callback: ChangeWatcherCallback,
+    ) -> WatchHandle {
+        let watcher_id = random();
+        let col_id = collection.get_id();
+        self.new_watcher(
+            Box::new(move |iw| {
+                iw.get_col_watchers(col_id)
+                    .add_change_watcher(watcher_id, callback);
+            }),
+            Box::new(move |iw| {
+                iw.get_col_watchers(col_id)
+                    .remove_change_watcher(watcher_id);
             }),
         )
     }
 
+    /// The callback receives the number of objects in the collection whenever it changes. The
+    /// initial count is computed while no other write transaction can register changes, so this
+    /// must not be called while the current thread has an open write transaction.
+    pub fn watch_collection_count(
+        &self,
+        collection: &IsarCollection,
+        callback: CountWatcherCallback,
+    ) -> Result<WatchHandle> {
+        let watcher_id = random();
+        let col_id = collection.get_id();
+        let mut watchers_lock = self.watchers.lock().unwrap();
+        watchers_lock.sync();
+        let mut txn = self.begin_txn(false, false)?;
+        let count = Self::count_objects(&mut txn, collection)?;
+        txn.abort();
+        watchers_lock
+            .get_col_watchers(col_id)
+            .add_count_watcher(watcher_id, count, callback);
+        Ok(self.new_watch_handle(Box::new(move |iw| {
+            iw.get_col_watchers(col_id).remove_count_watcher(watcher_id);
+        })))
+    }
+
+    fn count_objects(txn: &mut IsarTxn, collection: &IsarCollection) -> Result<u64> {
+        let mut qb = collection.new_query_builder();
+        qb.skip_default_sort();
+        let count = qb.build().count(txn)?;
+        Ok(count as u64)
+    }
+
+    /// Notifies all watchers of `collection`, e.g. after changes in a silent transaction. Count
+    /// watchers receive the number of objects visible to `txn`, which has to be a write
+    /// transaction so that no other transaction can commit in the meantime.
+    ///
+    /// If `txn` is not silent, the watchers are notified when it is committed. Otherwise they are
+    /// notified immediately.
+    pub fn notify_collection(&self, txn: &mut IsarTxn, collection: &IsarCollection) -> Result<()> {
+        if !txn.is_write() {
+            return Err(IsarError::WriteTxnRequired {});
+        }
+        let col_id = collection.get_id();
+        if let Some(change_set) = txn.change_set() {
+            change_set.register_all(col_id);
+            if change_set.has_count_watchers(col_id) {
+                let count = Self::count_objects(txn, collection)?;
+                txn.change_set().unwrap().register_count(col_id, count);
+            }
+            return Ok(());
+        }
+
+        let mut watchers_lock = self.watchers.lock().unwrap();
+        watchers_lock.sync();
+        let mut change_set = ChangeSet::new(watchers_lock);
+        change_set.register_all(col_id);
+        if change_set.has_count_watchers(col_id) {
+            let count = Self::count_objects(txn, collection)?;
+            change_set.register_count(col_id, count);
+        }
+        change_set.notify_watchers();
+        Ok(())
+    }
+
     pub fn close(self: Arc<Self>) -> bool {
         if Arc::strong_count(&self) == 2 {
-            INSTANCES.write().unwrap().remove(&self.name);
+            let instances = if self.in_memory {
+                &MEMORY_INSTANCES
+            } else {
+                &INSTANCES
+            };
+            instances.write().unwrap().remove(&self.name);
             Arc::downgrade(&self);
             true
         } else {
@@ -220,6 +863,17 @@ impl IsarInstance {
     }
 }
 
+/// Returns the read and the write end of a new pipe.
+#[cfg(unix)]
+fn pipe() -> Result<(File, File)> {
+    use std::os::unix::io::FromRawFd;
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    unsafe { Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))) }
+}
+
 struct DataDbs {
     pub info: Db,
     pub data: Db,
@@ -244,9 +898,15 @@ impl DataDbs {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::object::data_type::DataType;
     use crate::object::isar_object::IsarObject;
-    use crate::{col, isar};
+    use crate::query::filter::IntBetweenCond;
+    use crate::query::Sort;
+    use crate::watch::watcher::{ChangeKind, QueryChange};
+    use crate::{col, ind, isar};
+    use crossbeam_channel::unbounded;
+    use std::thread;
     use tempfile::tempdir;
 
     #[test]
@@ -266,6 +926,995 @@ mod tests {
         isar.close();
     }
 
+    #[test]
+    fn test_read_txn_snapshot() {
+        isar!(isar, col => col!(f1 => DataType::Long));
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(1);
+        col.put(&mut txn, ob.finish()).unwrap();
+        txn.commit().unwrap();
+
+        let mut read_txn = isar.begin_txn(false, false).unwrap();
+        let q = col.new_query_builder().build();
+        assert_eq!(q.count(&mut read_txn).unwrap(), 1);
+
+        let isar2 = isar.clone();
+        thread::spawn(move || {
+            let col = isar2.get_collection(0).unwrap();
+            let mut txn = isar2.begin_txn(true, false).unwrap();
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(2);
+            col.put(&mut txn, ob.finish()).unwrap();
+            txn.commit().unwrap();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(q.count(&mut read_txn).unwrap(), 1);
+        assert_eq!(col.get(&mut read_txn, 2).unwrap(), None);
+        read_txn.abort();
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert_eq!(q.count(&mut txn).unwrap(), 2);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_silent_txn_notify_collection() {
+        isar!(isar, col => col!(f1 => DataType::Long));
+
+        let (tx, rx) = unbounded();
+        let handle = isar.watch_collection(col, Box::new(move || tx.send(true).unwrap()));
+
+        let mut txn = isar.begin_txn(true, true).unwrap();
+        for oid in 1..=3 {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+        assert!(col.get(&mut txn, 2).unwrap().is_some());
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 0);
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert!(matches!(
+            isar.notify_collection(&mut txn, col),
+            Err(IsarError::WriteTxnRequired {})
+        ));
+        txn.abort();
+
+        let mut txn = isar.begin_txn(true, true).unwrap();
+        isar.notify_collection(&mut txn, col).unwrap();
+        txn.abort();
+        assert_eq!(rx.len(), 1);
+
+        handle.stop();
+        isar.close();
+    }
+
+    #[test]
+    fn test_watch_collection_changes() {
+        isar!(isar, col => col!(f1 => DataType::Long, f2 => DataType::Int));
+
+        let property = col.get_properties().get(1).unwrap().1;
+        let (tx, rx) = unbounded();
+        let handle = isar.watch_collection_changes(
+            col,
+            Box::new(move |oid, kind, object| {
+                let value = object.map(|bytes| IsarObject::from_bytes(bytes).read_int(property));
+                tx.send((oid, kind, value)).unwrap()
+            }),
+        );
+
+        let put = |txn: &mut IsarTxn, oid: i64, value: i32| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(value);
+            col.put(txn, ob.finish()).unwrap();
+        };
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 1, 10);
+        put(&mut txn, 2, 20);
+        assert_eq!(rx.len(), 0);
+        txn.commit().unwrap();
+        let changes: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            changes,
+            vec![
+                (1, ChangeKind::Put, Some(10)),
+                (2, ChangeKind::Put, Some(20))
+            ]
+        );
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 1, 11);
+        col.delete(&mut txn, 2).unwrap();
+        txn.commit().unwrap();
+        let changes: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            changes,
+            vec![
+                (1, ChangeKind::Put, Some(11)),
+                (2, ChangeKind::Delete, None)
+            ]
+        );
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 3, 30);
+        txn.abort();
+        assert_eq!(rx.len(), 0);
+
+        handle.stop();
+        isar.close();
+    }
+
+    #[test]
+    fn test_watch_collection_count() {
+        isar!(isar, col => col!(f1 => DataType::Long, f2 => DataType::Int));
+
+        let put = |txn: &mut IsarTxn, oid: i64, value: i32| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(value);
+            col.put(txn, ob.finish()).unwrap();
+        };
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 1, 1);
+        txn.commit().unwrap();
+
+        let (tx, rx) = unbounded();
+        let handle = isar
+            .watch_collection_count(col, Box::new(move |count| tx.send(count).unwrap()))
+            .unwrap();
+        assert_eq!(rx.len(), 0);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 2, 2);
+        put(&mut txn, 3, 3);
+        txn.commit().unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![3]);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 2, 20);
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 0);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        col.delete(&mut txn, 1).unwrap();
+        put(&mut txn, 4, 4);
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 0);
+
+        let mut txn = isar.begin_txn(true, true).unwrap();
+        col.delete(&mut txn, 4).unwrap();
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 0);
+        let mut txn = isar.begin_txn(true, true).unwrap();
+        isar.notify_collection(&mut txn, col).unwrap();
+        txn.abort();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![2]);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 5, 5);
+        isar.notify_collection(&mut txn, col).unwrap();
+        put(&mut txn, 6, 6);
+        assert_eq!(rx.len(), 0);
+        txn.commit().unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![4]);
+
+        handle.stop();
+        isar.close();
+    }
+
+    #[test]
+    fn test_watch_query_fire_immediately() {
+        isar!(isar, col => col!(f1 => DataType::Long));
+
+        let (tx, rx) = unbounded();
+        let q = col.new_query_builder().build();
+        let handle = isar.watch_query(col, q, true, Box::new(move || tx.send(true).unwrap()));
+        assert_eq!(rx.len(), 1);
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(1);
+        col.put(&mut txn, ob.finish()).unwrap();
+        assert_eq!(rx.len(), 1);
+        txn.commit().unwrap();
+        assert_eq!(rx.len(), 2);
+
+        handle.stop();
+        isar.close();
+    }
+
+    #[test]
+    fn test_watch_query_changes() {
+        isar!(isar, col => col!(f1 => DataType::Long, f2 => DataType::Int));
+
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut qb = col.new_query_builder();
+        qb.set_filter(IntBetweenCond::filter(field, 10, i32::MAX).unwrap());
+        let (tx, rx) = unbounded();
+        let handle =
+            isar.watch_query_changes(col, qb.build(), Box::new(move |c| tx.send(c).unwrap()));
+
+        let put = |txn: &mut IsarTxn, oid: i64, value: i32| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(value);
+            col.put(txn, ob.finish()).unwrap();
+        };
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 1, 5);
+        put(&mut txn, 2, 20);
+        txn.commit().unwrap();
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![QueryChange::Added(2)]
+        );
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 1, 15);
+        put(&mut txn, 2, 25);
+        txn.commit().unwrap();
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![QueryChange::Added(1), QueryChange::Updated(2)]
+        );
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 2, 3);
+        col.delete(&mut txn, 1).unwrap();
+        put(&mut txn, 3, 30);
+        col.delete(&mut txn, 3).unwrap();
+        txn.commit().unwrap();
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![QueryChange::Removed(2), QueryChange::Removed(1)]
+        );
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 4, 40);
+        txn.abort();
+        assert_eq!(rx.len(), 0);
+
+        handle.stop();
+        isar.close();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        isar!(isar, col => col!(f1 => DataType::Long, f2 => DataType::Int));
+
+        let put = |txn: &mut IsarTxn, oid: i64, value: i32| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(value);
+            col.put(txn, ob.finish()).unwrap();
+        };
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put(&mut txn, 1, 1);
+        txn.commit().unwrap();
+
+        let mut snapshot = isar.snapshot().unwrap();
+        let q = col.new_query_builder().build();
+        assert_eq!(snapshot.count(&q).unwrap(), 1);
+
+        let isar2 = isar.clone();
+        thread::spawn(move || {
+            let col = isar2.get_collection(0).unwrap();
+            let mut txn = isar2.begin_txn(true, false).unwrap();
+            for (oid, value) in [(1, 10), (2, 2)].iter() {
+                let mut ob = col.new_object_builder(None);
+                ob.write_long(*oid);
+                ob.write_int(*value);
+                col.put(&mut txn, ob.finish()).unwrap();
+            }
+            txn.commit().unwrap();
+        })
+        .join()
+        .unwrap();
+
+        let property = col.get_properties().get(1).unwrap().1;
+        let object = snapshot.get(col, 1).unwrap().unwrap();
+        assert_eq!(object.read_int(property), 1);
+        assert_eq!(snapshot.find_all_vec(&q).unwrap().len(), 1);
+        assert_eq!(snapshot.get(col, 2).unwrap(), None);
+        snapshot.close();
+
+        let mut snapshot = isar.snapshot().unwrap();
+        assert_eq!(snapshot.count(&q).unwrap(), 2);
+        let object = snapshot.get(col, 1).unwrap().unwrap();
+        assert_eq!(object.read_int(property), 10);
+        snapshot.close();
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_in_memory() {
+        isar!(memory: isar, col => col!(f1 => DataType::Long, f2 => DataType::Int; ind!(f2)));
+        assert!(IsarInstance::get_instance(&isar.name).is_none());
+        assert!(IsarInstance::get_in_memory_instance(&isar.name).is_some());
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 1..=3 {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(oid as i32);
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let mut key = col.new_index_key(0).unwrap();
+        key.add_int(2);
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+            .unwrap();
+        let result = qb.build().find_all_vec(&mut txn).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].read_long(col.get_oid_property()), 2);
+        txn.abort();
+        let name = isar.name.clone();
+        assert!(isar.close());
+        assert!(IsarInstance::get_in_memory_instance(&name).is_none());
+    }
+
+    #[test]
+    fn test_compact() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        isar!(path: path, isar, col => col!(f1 => DataType::Long, f2 => DataType::String));
+        let value = "x".repeat(1000);
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 0..1000 {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_string(Some(&value));
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 10..1000 {
+            col.delete(&mut txn, oid).unwrap();
+        }
+
+        let target_dir = tempdir().unwrap();
+        let target = target_dir.path().to_str().unwrap();
+        assert!(isar.compact(target).is_err());
+        txn.commit().unwrap();
+
+        let stats = isar.compact(target).unwrap();
+        assert!(stats.bytes_after < stats.bytes_before);
+        let original_size = std::fs::metadata(dir.path().join("data.mdb"))
+            .unwrap()
+            .len();
+        let compacted_size = std::fs::metadata(target_dir.path().join("data.mdb"))
+            .unwrap()
+            .len();
+        assert!(compacted_size < original_size);
+        isar.close();
+
+        isar!(path: target, isar, col => col!(f1 => DataType::Long, f2 => DataType::String));
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let objects = col
+            .new_query_builder()
+            .build()
+            .find_all_vec(&mut txn)
+            .unwrap();
+        let oids: Vec<i64> = objects
+            .iter()
+            .map(|o| o.read_long(col.get_oid_property()))
+            .collect();
+        assert_eq!(oids, (0..10).collect::<Vec<_>>());
+        let property = col.get_properties()[1].1;
+        assert_eq!(objects[0].read_string(property), Some(value.as_str()));
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_backup_to() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        isar!(path: path, isar, col => col!(f1 => DataType::Long, f2 => DataType::Int));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        // larger than the pipe buffer
+        for oid in 1..=10000 {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(oid as i32 * 10);
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+
+        let mut backup = vec![];
+        let written = isar.backup_to(&mut backup).unwrap();
+        assert_eq!(written, backup.len() as u64);
+        isar.close();
+
+        let restore_dir = tempdir().unwrap();
+        std::fs::write(restore_dir.path().join("data.mdb"), &backup).unwrap();
+        let restore = restore_dir.path().to_str().unwrap();
+        isar!(path: restore, isar, col => col!(f1 => DataType::Long, f2 => DataType::Int));
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let property = col.get_properties()[1].1;
+        let values: Vec<i32> = col
+            .new_query_builder()
+            .build()
+            .find_all_vec(&mut txn)
+            .unwrap()
+            .iter()
+            .map(|o| o.read_int(property))
+            .collect();
+        assert_eq!(values, (1..=10000).map(|v| v * 10).collect::<Vec<_>>());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_encrypted() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let open = |key: &[u8]| {
+            let schema = Schema::new(vec![col!(f1 => DataType::Long, f2 => DataType::String)]);
+            IsarInstance::open_encrypted(path, dir.path().into(), 10000000, schema.unwrap(), key)
+        };
+
+        let isar = open(&[1; 32]).unwrap();
+        let col = isar.get_collection(0).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(1);
+        ob.write_string(Some("secret value"));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        col.put(&mut txn, ob.finish()).unwrap();
+        txn.commit().unwrap();
+
+        // the open instance is returned only for the same key
+        match open(&[2; 32]) {
+            Err(IsarError::CryptoError {}) => {}
+            _ => panic!("wrong error"),
+        }
+        let schema = Schema::new(vec![col!(f1 => DataType::Long, f2 => DataType::String)]);
+        match IsarInstance::open(path, dir.path().into(), 10000000, schema.unwrap(), None) {
+            Err(IsarError::CryptoError {}) => {}
+            _ => panic!("wrong error"),
+        }
+        assert!(Arc::ptr_eq(&open(&[1; 32]).unwrap(), &isar));
+        assert!(isar.close());
+
+        let file = std::fs::read(dir.path().join("data.mdb")).unwrap();
+        assert!(!file.windows(12).any(|w| w == b"secret value"));
+
+        match open(&[2; 32]) {
+            Err(IsarError::CryptoError {}) => {}
+            _ => panic!("wrong error"),
+        }
+
+        let isar = open(&[1; 32]).unwrap();
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let object = col.get(&mut txn, 1).unwrap().unwrap();
+        let property = col.get_properties()[1].1;
+        assert_eq!(object.read_string(property), Some("secret value"));
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_stats() {
+        isar!(isar, col1 => col!("col1", f1 => DataType::Long, f2 => DataType::Int; ind!(f2)), col2 => col!("col2", f1 => DataType::Long));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 1..=5 {
+            let mut ob = col1.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(oid as i32 % 2);
+            col1.put(&mut txn, ob.finish()).unwrap();
+        }
+        let mut ob = col2.new_object_builder(None);
+        ob.write_long(1);
+        col2.put(&mut txn, ob.finish()).unwrap();
+        txn.commit().unwrap();
+
+        let stats = isar.stats().unwrap();
+        let counts: Vec<(&str, u64, u64)> = stats
+            .collections
+            .iter()
+            .map(|c| (c.name.as_str(), c.objects, c.index_entries))
+            .collect();
+        assert_eq!(counts, vec![("col1", 5, 5), ("col2", 1, 0)]);
+        assert!(stats.page_size > 0);
+        assert!(stats.free_pages * stats.page_size < stats.file_size);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["collections"][0]["indexEntries"], 5);
+        isar.close();
+    }
+
+    #[test]
+    fn test_clear_all() {
+        isar!(isar, col1 => col!("col1", f1 => DataType::Long, f2 => DataType::Int; ind!(f2)), col2 => col!("col2", f1 => DataType::Long), _col3 => col!("col3", f1 => DataType::Long));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 1..=3 {
+            let mut ob = col1.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(oid as i32);
+            col1.put(&mut txn, ob.finish()).unwrap();
+        }
+        let mut ob = col2.new_object_builder(None);
+        ob.write_long(1);
+        col2.put(&mut txn, ob.finish()).unwrap();
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        assert_eq!(isar.clear_all(&mut txn).unwrap(), 4);
+        txn.commit().unwrap();
+
+        let stats = isar.stats().unwrap();
+        let counts: Vec<(u64, u64)> = stats
+            .collections
+            .iter()
+            .map(|c| (c.objects, c.index_entries))
+            .collect();
+        assert_eq!(counts, vec![(0, 0), (0, 0), (0, 0)]);
+        isar.close();
+    }
+
+    #[test]
+    fn test_schema_json_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let schema = Schema::new(vec![
+            col!("col1", f1 => DataType::Long, f2 => DataType::Int, f3 => DataType::String; ind!(f2), ind!(f2, f1)),
+            col!("col2", f1 => DataType::Long),
+        ]);
+        let isar =
+            IsarInstance::open(path, dir.path().into(), 1000000, schema.unwrap(), None).unwrap();
+        let json = isar.get_schema().unwrap().to_json().unwrap();
+        isar.close();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value[0]["id"].is_u64());
+        assert!(value[0]["indexes"][1]["id"].is_u64());
+        assert_eq!(value[0]["properties"][2]["offset"], 14);
+
+        let schema = Schema::from_json(json.as_bytes()).unwrap();
+        assert_eq!(schema.to_json().unwrap(), json);
+
+        let isar = IsarInstance::open(path, dir.path().into(), 1000000, schema, None).unwrap();
+        assert_eq!(isar.get_schema().unwrap().to_json().unwrap(), json);
+        isar.close();
+    }
+
+    #[test]
+    fn test_write_txn_autogrow() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let schema = Schema::new(vec![col!(f1 => DataType::Long, f2 => DataType::String)]);
+        let isar =
+            IsarInstance::open(path, dir.path().into(), 200000, schema.unwrap(), None).unwrap();
+        let col = isar.get_collection(0).unwrap();
+        let value = "x".repeat(1000);
+        let put_all = |txn: &mut IsarTxn| {
+            for oid in 1..=1000 {
+                let mut ob = col.new_object_builder(None);
+                ob.write_long(oid);
+                ob.write_string(Some(&value));
+                col.put(txn, ob.finish())?;
+            }
+            Ok(())
+        };
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        match put_all(&mut txn) {
+            Err(IsarError::DbFull {}) => {}
+            _ => panic!("wrong error"),
+        }
+        txn.abort();
+
+        let growth = MapGrowth {
+            factor: 2,
+            max_size: 1000000,
+        };
+        match isar.write_txn_autogrow(false, growth, put_all) {
+            Err(IsarError::DbFull {}) => {}
+            _ => panic!("wrong error"),
+        }
+
+        let growth = MapGrowth {
+            factor: 50,
+            max_size: 100000000,
+        };
+        let snapshot = isar.snapshot().unwrap();
+        match isar.write_txn_autogrow(false, growth, put_all) {
+            Err(IsarError::IllegalArg { .. }) => {}
+            _ => panic!("wrong error"),
+        }
+        drop(snapshot);
+
+        let read_txn = isar.begin_txn(false, false).unwrap();
+        match isar.write_txn_autogrow(false, growth, put_all) {
+            Err(IsarError::IllegalArg { .. }) => {}
+            _ => panic!("wrong error"),
+        }
+        read_txn.abort();
+
+        isar.write_txn_autogrow(false, growth, put_all).unwrap();
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert_eq!(
+            col.new_query_builder().build().count(&mut txn).unwrap(),
+            1000
+        );
+        txn.abort();
+        isar.close();
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        struct ThreadWaker(thread::Thread);
+
+        impl std::task::Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = std::task::Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = std::task::Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                std::task::Poll::Ready(result) => return result,
+                std::task::Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_begin_txn_async() {
+        isar!(isar, _col => col!(f1 => DataType::Long, f2 => DataType::Int));
+        let put = |oid: i64| {
+            isar.begin_txn_async(true, false, move |isar, txn| {
+                let col = isar.get_collection(0).unwrap();
+                let mut ob = col.new_object_builder(None);
+                ob.write_long(oid);
+                ob.write_int(oid as i32 * 10);
+                col.put(txn, ob.finish())?;
+                Ok(oid)
+            })
+        };
+        let futures: Vec<_> = (1..=3).map(put).collect();
+        for (oid, future) in (1..=3).zip(futures) {
+            assert_eq!(block_on(future).unwrap(), oid);
+        }
+
+        let count = isar.begin_txn_async(false, false, |isar, txn| {
+            let col = isar.get_collection(0).unwrap();
+            let object = col.get(txn, 2)?.unwrap();
+            assert_eq!(object.read_int(col.get_properties()[1].1), 20);
+            col.new_query_builder().build().count(txn)
+        });
+        assert_eq!(block_on(count).unwrap(), 3);
+
+        let failed =
+            isar.begin_txn_async(true, false, |_, _| -> Result<()> { illegal_arg("Failed") });
+        assert!(block_on(failed).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_no_lock() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let schema = Schema::new(vec![col!(f1 => DataType::Long)]).unwrap();
+        let options = OpenOptions {
+            no_lock: true,
+            ..OpenOptions::default()
+        };
+        let isar = IsarInstance::open_with_options(
+            path,
+            dir.path().into(),
+            10000000,
+            schema,
+            None,
+            options,
+        )
+        .unwrap();
+        let col = isar.get_collection(0).unwrap();
+
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(123);
+        let o = ob.finish();
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        col.put(&mut txn, o).unwrap();
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert_eq!(col.get(&mut txn, 123).unwrap().unwrap(), o);
+        txn.abort();
+        assert!(!dir.path().join("lock.mdb").exists());
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_readonly() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let schema = || Schema::new(vec![col!(f1 => DataType::Long, f2 => DataType::Int)]).unwrap();
+        assert!(IsarInstance::open_readonly(path, dir.path().into(), 10000000, schema()).is_err());
+
+        let isar = IsarInstance::open(path, dir.path().into(), 10000000, schema(), None).unwrap();
+        let col = isar.get_collection(0).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(123);
+        ob.write_int(5);
+        let object_bytes = ob.finish().as_bytes().to_vec();
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        col.put(&mut txn, IsarObject::from_bytes(&object_bytes))
+            .unwrap();
+        txn.commit().unwrap();
+        assert!(isar.close());
+
+        let indexed_schema = Schema::new(vec![
+            col!(f1 => DataType::Long, f2 => DataType::Int; ind!(f2)),
+        ])
+        .unwrap();
+        match IsarInstance::open_readonly(path, dir.path().into(), 10000000, indexed_schema) {
+            Err(IsarError::SchemaError { .. }) => {}
+            _ => panic!("wrong error"),
+        }
+
+        let isar =
+            IsarInstance::open_readonly(path, dir.path().into(), 10000000, schema()).unwrap();
+        assert!(isar.is_read_only());
+        let col = isar.get_collection(0).unwrap();
+        match isar.begin_txn(true, false) {
+            Err(IsarError::ReadOnly {}) => {}
+            _ => panic!("wrong error"),
+        }
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert_eq!(
+            col.get(&mut txn, 123).unwrap(),
+            Some(IsarObject::from_bytes(&object_bytes))
+        );
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_transaction() {
+        isar!(isar, col1 => col!("col1", f1 => DataType::Long), col2 => col!("col2", f1 => DataType::Long));
+        let put = |col: &IsarCollection, txn: &mut IsarTxn, oid: i64| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            col.put(txn, ob.finish())
+        };
+        let count = |col: &IsarCollection| {
+            isar.transaction(false, |txn| col.new_query_builder().build().count(txn))
+                .unwrap()
+        };
+
+        let result = isar.transaction(true, |txn| {
+            put(col1, txn, 1)?;
+            put(col2, txn, 1)?;
+            Ok(2)
+        });
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!((count(col1), count(col2)), (1, 1));
+
+        let result = isar.transaction(true, |txn| {
+            put(col1, txn, 2)?;
+            put(col2, txn, 2)?;
+            illegal_arg::<()>("rollback")
+        });
+        assert!(result.is_err());
+        assert_eq!((count(col1), count(col2)), (1, 1));
+
+        let result = isar.transaction(false, |txn| put(col1, txn, 3));
+        assert!(result.is_err());
+        assert_eq!(count(col1), 1);
+        isar.close();
+    }
+
+    #[test]
+    fn test_batch_writer() {
+        isar!(isar, col => col!(f1 => DataType::Long));
+        assert!(isar.batch_writer(false, 0).is_err());
+
+        let put = |writer: &mut BatchWriter, oid: i64| {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            let object = ob.finish();
+            writer.write(|txn| col.put(txn, object))
+        };
+
+        let mut writer = isar.batch_writer(false, 5000).unwrap();
+        for oid in 1..=12000 {
+            put(&mut writer, oid).unwrap();
+        }
+        assert_eq!(writer.committed(), 10000);
+        assert_eq!(writer.pending(), 2000);
+        writer.checkpoint().unwrap();
+        assert_eq!(writer.committed(), 12000);
+        for oid in 12001..=50000 {
+            put(&mut writer, oid).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 50000);
+
+        let mut writer = isar.batch_writer(true, 2).unwrap();
+        for oid in 50001..=50003 {
+            put(&mut writer, oid).unwrap();
+        }
+        assert!(put(&mut writer, i64::MAX).is_err());
+        assert_eq!(writer.committed(), 2);
+        assert!(put(&mut writer, 50004).is_err());
+        assert!(writer.finish().is_err());
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let count = col.new_query_builder().build().count(&mut txn).unwrap();
+        assert_eq!(count, 50002);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_durability() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let schema = || Schema::new(vec![col!(f1 => DataType::Long)]).unwrap();
+        for durability in &[Durability::NoSync, Durability::NoMetaSync, Durability::Full] {
+            let options = OpenOptions {
+                durability: *durability,
+                ..OpenOptions::default()
+            };
+            let isar = IsarInstance::open_with_options(
+                path,
+                dir.path().into(),
+                10000000,
+                schema(),
+                None,
+                options,
+            )
+            .unwrap();
+            let col = isar.get_collection(0).unwrap();
+            let mut txn = isar.begin_txn(true, false).unwrap();
+            let count = col.new_query_builder().build().count(&mut txn).unwrap();
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(count as i64 + 1);
+            col.put(&mut txn, ob.finish()).unwrap();
+            txn.commit().unwrap();
+            isar.sync().unwrap();
+            assert!(isar.close());
+        }
+
+        let isar = IsarInstance::open(path, dir.path().into(), 10000000, schema(), None).unwrap();
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        assert_eq!(col.new_query_builder().build().count(&mut txn).unwrap(), 3);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_instance_renamed_property() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        isar!(path: path, isar, col => col!("col", oid => DataType::Long, a => DataType::Int; ind!(a)));
+        let index_id = col.get_indexes()[0].id;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 1..=3 {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(oid);
+            ob.write_int(oid as i32 * 10);
+            col.put(&mut txn, ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+        assert!(isar.close());
+
+        let renamed_col = || {
+            let mut col = col!("col", oid => DataType::Long, b => DataType::Int; ind!(b));
+            col.rename_property("a", "b");
+            col
+        };
+        isar!(path: path, isar, col => renamed_col());
+        assert_eq!(col.get_indexes()[0].id, index_id);
+        assert_eq!(col.get_properties()[1].0, "b");
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let mut lower = col.new_index_key(0).unwrap();
+        lower.add_int(20);
+        let mut upper = col.new_index_key(0).unwrap();
+        upper.add_int(30);
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(lower, true, upper, true, false, Sort::Ascending)
+            .unwrap();
+        let property = col.get_properties()[1].1;
+        let values: Vec<i32> = qb
+            .build()
+            .find_all_vec(&mut txn)
+            .unwrap()
+            .iter()
+            .map(|o| o.read_int(property))
+            .collect();
+        assert_eq!(values, vec![20, 30]);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_instance_renamed_property_reused_name() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        isar!(path: path, isar, col => col!("col", oid => DataType::Long, a => DataType::Int));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(1);
+        ob.write_int(10);
+        col.put(&mut txn, ob.finish()).unwrap();
+        txn.commit().unwrap();
+        assert!(isar.close());
+
+        let renamed_col = || {
+            let mut col =
+                col!("col", oid => DataType::Long, a => DataType::Int, b => DataType::Int);
+            col.rename_property("a", "b");
+            col
+        };
+        isar!(path: path, isar, col => renamed_col());
+        let a = col.get_properties()[1].1;
+        let b = col.get_properties()[2].1;
+        assert_ne!(a.offset, b.offset);
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let object = col.get(&mut txn, 1).unwrap().unwrap();
+        assert_eq!(object.read_int(b), 10);
+        assert!(object.is_null(a));
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_open_instance_changed_property_type() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+
+        isar!(path: path, isar, col => col!("col", oid => DataType::Long, a => DataType::Int, d => DataType::Long));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(1);
+        ob.write_int(10);
+        ob.write_long(1000);
+        col.put(&mut txn, ob.finish()).unwrap();
+        txn.commit().unwrap();
+        assert!(isar.close());
+
+        let schema = Schema::new(vec![
+            col!("col", oid => DataType::Long, a => DataType::Long, d => DataType::Long),
+        ])
+        .unwrap();
+        match IsarInstance::open(path, dir.path().into(), 10000000, schema, None) {
+            Err(IsarError::SchemaError { message }) => {
+                assert!(message.contains("from Int to Long"))
+            }
+            _ => panic!("wrong error"),
+        }
+
+        // DateTime is stored like Long
+        isar!(path: path, isar, col => col!("col", oid => DataType::Long, a => DataType::Int, d => DataType::DateTime));
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let property = col.get_properties()[2].1;
+        let object = col.get(&mut txn, 1).unwrap().unwrap();
+        assert_eq!(object.read_long(property), 1000);
+        txn.abort();
+        isar.close();
+    }
+
     #[test]
     fn test_open_instance_added_collection() {
         let dir = tempdir().unwrap();
@@ -7,6 +7,7 @@ compile_error!("Only little endian systems are supported.");
 #[cfg(not(target_pointer_width = "64"))]
 compile_error!("Only 64-bit systems are supported at this time.");
 
+pub mod batch_writer;
 pub mod collection;
 pub mod error;
 pub mod index;
@@ -15,7 +16,9 @@ mod link;
 mod lmdb;
 pub mod object;
 pub mod query;
+pub mod raw_cursor;
 pub mod schema;
+pub mod snapshot;
 pub mod txn;
 mod utils;
 pub mod watch;
@@ -177,7 +177,7 @@ impl Link {
     }
 
     fn clear_internal(links_cursor: &mut Cursor, min_key: IntKey, max_key: IntKey) -> Result<()> {
-        links_cursor.iter_between(min_key, max_key, false, true, |cursor, _, _| {
+        links_cursor.iter_between(min_key, true, max_key, true, false, true, |cursor, _, _| {
             cursor.delete_current()?;
             Ok(true)
         })?;
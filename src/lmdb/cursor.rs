@@ -52,6 +52,10 @@ impl<'txn> Cursor<'txn> {
         }
     }
 
+    pub fn current(&mut self) -> Result<Option<KeyVal<'txn>>> {
+        self.op_get(ffi::MDB_GET_CURRENT, None, None)
+    }
+
     pub fn move_to(&mut self, key: impl Key) -> Result<Option<KeyVal<'txn>>> {
         self.op_get(ffi::MDB_SET_KEY, Some(key.as_bytes()), None)
     }
@@ -76,15 +80,36 @@ impl<'txn> Cursor<'txn> {
         self.op_get(ffi::MDB_PREV_NODUP, None, None)
     }
 
+    pub fn move_to_next(&mut self) -> Result<Option<KeyVal<'txn>>> {
+        self.op_get(ffi::MDB_NEXT, None, None)
+    }
+
+    pub fn move_to_next_key(&mut self) -> Result<Option<KeyVal<'txn>>> {
+        self.op_get(ffi::MDB_NEXT_NODUP, None, None)
+    }
+
     pub fn move_to_last(&mut self) -> Result<Option<KeyVal<'txn>>> {
         self.op_get(ffi::MDB_LAST, None, None)
     }
 
+    /// Requires the cursor to have a valid position
+    pub fn count_dups(&self) -> Result<u64> {
+        let mut count: ffi::mdb_size_t = 0;
+        unsafe { lmdb_result(ffi::mdb_cursor_count(self.cursor, &mut count))? };
+        Ok(count as u64)
+    }
+
     pub fn put(&self, key: impl Key, data: &[u8]) -> Result<()> {
         self.put_internal(key, data, 0)?;
         Ok(())
     }
 
+    /// The data has to be greater than all existing duplicates of the key
+    pub fn put_append_dup(&self, key: impl Key, data: &[u8]) -> Result<()> {
+        self.put_internal(key, data, ffi::MDB_APPENDDUP)?;
+        Ok(())
+    }
+
     #[allow(clippy::try_err)]
     pub fn put_no_override(&self, key: impl Key, data: &[u8]) -> Result<bool> {
         let result = self.put_internal(key, data, ffi::MDB_NOOVERWRITE);
@@ -110,6 +135,24 @@ impl<'txn> Cursor<'txn> {
         Ok(())
     }
 
+    pub fn page_count(&self) -> Result<u64> {
+        let mut stat = ffi::MDB_stat {
+            ms_psize: 0,
+            ms_depth: 0,
+            ms_branch_pages: 0,
+            ms_leaf_pages: 0,
+            ms_overflow_pages: 0,
+            ms_entries: 0,
+        };
+        unsafe {
+            let txn = ffi::mdb_cursor_txn(self.cursor);
+            let dbi = ffi::mdb_cursor_dbi(self.cursor);
+            lmdb_result(ffi::mdb_stat(txn, dbi, &mut stat))?;
+        }
+        let pages = stat.ms_branch_pages + stat.ms_leaf_pages + stat.ms_overflow_pages;
+        Ok(pages as u64)
+    }
+
     /// Requires the cursor to have a valid position
     pub fn delete_current(&mut self) -> Result<()> {
         assert!(self.write);
@@ -118,64 +161,92 @@ impl<'txn> Cursor<'txn> {
         Ok(())
     }
 
+    fn is_in_range<K: Key>(
+        key: &[u8],
+        lower_key: K,
+        include_lower: bool,
+        upper_key: K,
+        include_upper: bool,
+    ) -> bool {
+        let above_lower = match lower_key.cmp_bytes(key) {
+            Ordering::Less => true,
+            Ordering::Equal => include_lower,
+            Ordering::Greater => false,
+        };
+        let below_upper = match upper_key.cmp_bytes(key) {
+            Ordering::Greater => true,
+            Ordering::Equal => include_upper,
+            Ordering::Less => false,
+        };
+        above_lower && below_upper
+    }
+
     #[inline(never)]
     fn iter_between_first<K: Key>(
         &mut self,
         lower_key: K,
+        include_lower: bool,
         upper_key: K,
+        include_upper: bool,
         ascending: bool,
     ) -> Result<Option<KeyVal<'txn>>> {
         if upper_key < lower_key {
             return Ok(None);
         }
 
-        let first_entry = if !ascending {
-            if let Some(first_entry) = self.move_to_gte(upper_key)? {
-                Some(first_entry)
-            } else {
-                // If some key between upper_key and lower_key happens to be the last key in the db
-                self.move_to_last()?
+        let first_entry = if ascending {
+            match self.move_to_gte(lower_key)? {
+                Some((key, _)) if !include_lower && lower_key.cmp_bytes(key) == Ordering::Equal => {
+                    self.move_to_next_key()?
+                }
+                entry => entry,
             }
         } else {
-            self.move_to_gte(lower_key)?
+            match self.move_to_gte(upper_key)? {
+                Some((key, _)) if include_upper && upper_key.cmp_bytes(key) == Ordering::Equal => {
+                    // start with the last duplicate of the upper key
+                    if self.move_to_next_key()?.is_some() {
+                        self.move_to_prev()?
+                    } else {
+                        self.move_to_last()?
+                    }
+                }
+                Some(_) => self.move_to_prev()?,
+                // If some key between upper_key and lower_key happens to be the last key in the db
+                None => self.move_to_last()?,
+            }
         };
 
         if let Some((key, _)) = first_entry {
-            if upper_key.cmp_bytes(key) == Ordering::Less {
-                if !ascending {
-                    if let Some((prev_key, prev_val)) = self.move_to_prev()? {
-                        if lower_key.cmp_bytes(prev_key) == Ordering::Less {
-                            return Ok(Some((prev_key, prev_val)));
-                        }
-                    }
-                }
-                Ok(None)
-            } else {
-                Ok(first_entry)
+            if Self::is_in_range(key, lower_key, include_lower, upper_key, include_upper) {
+                return Ok(first_entry);
             }
-        } else {
-            Ok(None)
         }
+        Ok(None)
     }
 
-    pub fn iter_between<K: Key>(
+    /// Returns the next entry between `lower_key` and `upper_key` or `None` once the range is
+    /// exhausted. The first call has to set `first` to position the cursor at the start of the
+    /// range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn next_between<K: Key>(
         &mut self,
         lower_key: K,
+        include_lower: bool,
         upper_key: K,
+        include_upper: bool,
         skip_duplicates: bool,
         ascending: bool,
-        mut callback: impl FnMut(&mut Cursor<'txn>, &'txn [u8], &'txn [u8]) -> Result<bool>,
-    ) -> Result<bool> {
-        if upper_key < lower_key {
-            return Ok(true);
-        }
-
-        if let Some((key, val)) = self.iter_between_first(lower_key, upper_key, ascending)? {
-            if !callback(self, key, val)? {
-                return Ok(false);
-            }
-        } else {
-            return Ok(true);
+        first: bool,
+    ) -> Result<Option<KeyVal<'txn>>> {
+        if first {
+            return self.iter_between_first(
+                lower_key,
+                include_lower,
+                upper_key,
+                include_upper,
+                ascending,
+            );
         }
 
         let next = match (ascending, skip_duplicates) {
@@ -184,19 +255,45 @@ impl<'txn> Cursor<'txn> {
             (false, true) => ffi::MDB_PREV_NODUP,
             (false, false) => ffi::MDB_PREV,
         };
-        loop {
-            if let Some((key, val)) = self.op_get(next, None, None)? {
-                if (ascending && upper_key.cmp_bytes(key) == Ordering::Less)
-                    || (!ascending && lower_key.cmp_bytes(key) == Ordering::Greater)
-                {
-                    return Ok(true);
-                } else if !callback(self, key, val)? {
-                    return Ok(false);
-                }
-            } else {
-                return Ok(true);
+        match self.op_get(next, None, None)? {
+            Some((key, val))
+                if Self::is_in_range(key, lower_key, include_lower, upper_key, include_upper) =>
+            {
+                Ok(Some((key, val)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Iterates all entries between `lower_key` and `upper_key`. The bounds are only included
+    /// if `include_lower` or `include_upper` is set.
+    #[allow(clippy::too_many_arguments)]
+    pub fn iter_between<K: Key>(
+        &mut self,
+        lower_key: K,
+        include_lower: bool,
+        upper_key: K,
+        include_upper: bool,
+        skip_duplicates: bool,
+        ascending: bool,
+        mut callback: impl FnMut(&mut Cursor<'txn>, &'txn [u8], &'txn [u8]) -> Result<bool>,
+    ) -> Result<bool> {
+        let mut first = true;
+        while let Some((key, val)) = self.next_between(
+            lower_key,
+            include_lower,
+            upper_key,
+            include_upper,
+            skip_duplicates,
+            ascending,
+            first,
+        )? {
+            if !callback(self, key, val)? {
+                return Ok(false);
             }
+            first = false;
         }
+        Ok(true)
     }
 
     pub fn iter_dups<'a>(
@@ -4,6 +4,7 @@ use crate::lmdb::error::lmdb_result;
 use crate::lmdb::txn::Txn;
 use lmdb_sys as ffi;
 use std::ffi::CString;
+use std::mem::MaybeUninit;
 
 #[derive(Copy, Clone)]
 pub struct Db {
@@ -35,6 +36,16 @@ impl Db {
     pub fn cursor<'txn>(&self, txn: &'txn Txn) -> Result<Cursor<'txn>> {
         Cursor::open(txn, &self)
     }
+
+    pub fn page_count(&self, txn: &Txn) -> Result<u64> {
+        let mut stat = MaybeUninit::<ffi::MDB_stat>::uninit();
+        let stat = unsafe {
+            lmdb_result(ffi::mdb_stat(txn.txn, self.dbi, stat.as_mut_ptr()))?;
+            stat.assume_init()
+        };
+        let pages = stat.ms_branch_pages + stat.ms_leaf_pages + stat.ms_overflow_pages;
+        Ok(pages as u64)
+    }
 }
 
 #[cfg(test)]
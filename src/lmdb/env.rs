@@ -8,6 +8,8 @@ use core::ptr;
 use lmdb_sys as ffi;
 use lmdb_sys::MDB_val;
 use std::ffi::CString;
+use std::fs::File;
+use std::mem::MaybeUninit;
 
 const GCM_TAG_SIZE: usize = 16;
 
@@ -24,6 +26,7 @@ impl Env {
         max_dbs: u32,
         max_size: usize,
         encryption_key: Option<&[u8]>,
+        flags: u32,
     ) -> Result<Env> {
         let path = CString::new(path.as_bytes()).unwrap();
         let mut env: *mut ffi::MDB_env = ptr::null_mut();
@@ -56,7 +59,7 @@ impl Env {
                 }
             }
 
-            let err_code = ffi::mdb_env_open(env, path.as_ptr(), 0, 0o600);
+            let err_code = ffi::mdb_env_open(env, path.as_ptr(), flags, 0o600);
             if err_code != ffi::MDB_SUCCESS {
                 ffi::mdb_env_close(env);
                 if err_code == 2 {
@@ -82,6 +85,56 @@ impl Env {
         }
         Ok(Txn::new(txn, write))
     }
+
+    /// Returns the number of pages in the data file, including free pages, and the page size.
+    pub fn page_info(&self) -> Result<(u64, u64)> {
+        unsafe {
+            let mut info = MaybeUninit::<ffi::MDB_envinfo>::uninit();
+            lmdb_result(ffi::mdb_env_info(self.env, info.as_mut_ptr()))?;
+            let mut stat = MaybeUninit::<ffi::MDB_stat>::uninit();
+            lmdb_result(ffi::mdb_env_stat(self.env, stat.as_mut_ptr()))?;
+            let pages = info.assume_init().me_last_pgno as u64 + 1;
+            Ok((pages, stat.assume_init().ms_psize as u64))
+        }
+    }
+
+    pub fn map_size(&self) -> Result<usize> {
+        let info = unsafe {
+            let mut info = MaybeUninit::<ffi::MDB_envinfo>::uninit();
+            lmdb_result(ffi::mdb_env_info(self.env, info.as_mut_ptr()))?;
+            info.assume_init()
+        };
+        Ok(info.me_mapsize as usize)
+    }
+
+    /// Must not be called while this process has an open transaction.
+    pub fn set_map_size(&self, size: usize) -> Result<()> {
+        unsafe { lmdb_result(ffi::mdb_env_set_mapsize(self.env, size))? };
+        Ok(())
+    }
+
+    /// Number of bytes used by the data file, including free pages.
+    pub fn used_size(&self) -> Result<u64> {
+        let (pages, page_size) = self.page_info()?;
+        Ok(pages * page_size)
+    }
+
+    /// Forces a flush of the data buffers to disk, even if the env was opened with `MDB_NOSYNC`.
+    pub fn sync(&self) -> Result<()> {
+        unsafe { lmdb_result(ffi::mdb_env_sync(self.env, 1))? };
+        Ok(())
+    }
+
+    /// Writes a consistent copy of the environment to `file`. Compacting copies omit free pages.
+    pub fn copy_to_file(&self, file: &File, compact: bool) -> Result<()> {
+        let flags = if compact { ffi::MDB_CP_COMPACT } else { 0 };
+        #[cfg(unix)]
+        let handle = std::os::unix::io::AsRawFd::as_raw_fd(file);
+        #[cfg(windows)]
+        let handle = std::os::windows::io::AsRawHandle::as_raw_handle(file);
+        unsafe { lmdb_result(ffi::mdb_env_copyfd2(self.env, handle as _, flags))? };
+        Ok(())
+    }
 }
 
 impl Drop for Env {
@@ -155,6 +208,6 @@ pub mod tests {
 
     pub fn get_env() -> Env {
         let dir = tempdir().unwrap();
-        Env::create(dir.path().to_str().unwrap(), 50, 100000, None).unwrap()
+        Env::create(dir.path().to_str().unwrap(), 50, 100000, None, 0).unwrap()
     }
 }
@@ -0,0 +1,76 @@
+use crate::collection::IsarCollection;
+use crate::error::{IsarError, Result};
+use crate::object::isar_object::{IsarObject, IsarValue, Property};
+use crate::utils::date_time::format_iso8601;
+use serde_json::json;
+use std::io::Write;
+
+pub(crate) struct CsvEncode {}
+
+impl CsvEncode {
+    pub fn write_header<W: Write>(
+        collection: &IsarCollection,
+        properties: &[Property],
+        writer: &mut W,
+    ) -> Result<()> {
+        let mut names = vec![];
+        for property in properties {
+            let (name, _) = collection
+                .get_properties()
+                .iter()
+                .find(|(_, p)| p == property)
+                .ok_or(IsarError::IllegalArg {
+                    message: "Property does not exist".to_string(),
+                })?;
+            names.push(name.clone());
+        }
+        Self::write_row(&names, writer)
+    }
+
+    pub fn write_object<W: Write>(
+        object: IsarObject,
+        properties: &[Property],
+        writer: &mut W,
+    ) -> Result<()> {
+        let cells: Vec<String> = properties
+            .iter()
+            .map(|property| Self::encode_property(object, *property))
+            .collect();
+        Self::write_row(&cells, writer)
+    }
+
+    fn encode_property(object: IsarObject, property: Property) -> String {
+        match object.read_value(property) {
+            IsarValue::Null => String::new(),
+            IsarValue::Byte(value) => value.to_string(),
+            IsarValue::Bool(value) => value.to_string(),
+            IsarValue::Int(value) => value.to_string(),
+            IsarValue::Float(value) => value.to_string(),
+            IsarValue::Long(value) => value.to_string(),
+            IsarValue::Double(value) => value.to_string(),
+            IsarValue::DateTime(value) => format_iso8601(value),
+            IsarValue::String(value) => value.to_string(),
+            IsarValue::ByteList(list) => json!(list).to_string(),
+            IsarValue::IntList(list) => json!(list).to_string(),
+            IsarValue::FloatList(list) => json!(list).to_string(),
+            IsarValue::LongList(list) => json!(list).to_string(),
+            IsarValue::DoubleList(list) => json!(list).to_string(),
+            IsarValue::StringList(list) => json!(list).to_string(),
+        }
+    }
+
+    fn write_row<W: Write>(cells: &[String], writer: &mut W) -> Result<()> {
+        for (i, cell) in cells.iter().enumerate() {
+            if i != 0 {
+                writer.write_all(b",")?;
+            }
+            if cell.contains(&[',', '"', '\n', '\r'][..]) {
+                write!(writer, "\"{}\"", cell.replace('"', "\"\""))?;
+            } else {
+                writer.write_all(cell.as_bytes())?;
+            }
+        }
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
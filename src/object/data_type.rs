@@ -16,13 +16,21 @@ pub enum DataType {
     LongList = 9,
     DoubleList = 10,
     StringList = 11,
+    DateTime = 12,
+    Bool = 13,
 }
 
 impl DataType {
     pub fn is_static(&self) -> bool {
         matches!(
             &self,
-            DataType::Int | DataType::Long | DataType::Float | DataType::Double | DataType::Byte
+            DataType::Int
+                | DataType::Long
+                | DataType::Float
+                | DataType::Double
+                | DataType::Byte
+                | DataType::DateTime
+                | DataType::Bool
         )
     }
 
@@ -30,9 +38,32 @@ impl DataType {
         !self.is_static()
     }
 
+    pub fn is_list(&self) -> bool {
+        matches!(
+            &self,
+            DataType::ByteList
+                | DataType::IntList
+                | DataType::FloatList
+                | DataType::LongList
+                | DataType::DoubleList
+                | DataType::StringList
+        )
+    }
+
+    /// The type used to store values of this type. `DateTime` is stored as milliseconds since
+    /// the epoch like a `Long` and `Bool` is stored like a `Byte` using `IsarObject::FALSE_BYTE`
+    /// and `IsarObject::TRUE_BYTE`.
+    pub fn storage_type(&self) -> DataType {
+        match *self {
+            DataType::DateTime => DataType::Long,
+            DataType::Bool => DataType::Byte,
+            data_type => data_type,
+        }
+    }
+
     pub fn get_static_size(&self) -> usize {
         match *self {
-            DataType::Byte => 1,
+            DataType::Byte | DataType::Bool => 1,
             DataType::Int | DataType::Float => 4,
             _ => 8,
         }
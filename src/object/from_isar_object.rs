@@ -0,0 +1,8 @@
+use crate::error::Result;
+use crate::object::isar_object::IsarObject;
+
+/// Converts a raw object into a typed value. Implementations read the properties they need
+/// using the known offsets of the collection.
+pub trait FromIsarObject<'a>: Sized {
+    fn from_isar_object(object: IsarObject<'a>) -> Result<Self>;
+}
@@ -1,5 +1,11 @@
+use crate::collection::IsarCollection;
+use crate::error::Result;
 use crate::object::data_type::DataType;
+use crate::object::object_deserializer::ObjectDeserializer;
+use crate::utils::fold_case;
 use byteorder::{ByteOrder, LittleEndian};
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::hash::Hasher;
 
@@ -15,6 +21,27 @@ impl Property {
     }
 }
 
+/// A property value read without knowing its type at compile time. Null values of every type,
+/// including null elements of static types, are represented by `Null`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum IsarValue<'a> {
+    Null,
+    Byte(u8),
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    DateTime(i64),
+    String(&'a str),
+    ByteList(&'a [u8]),
+    IntList(Vec<i32>),
+    FloatList(Vec<f32>),
+    LongList(Vec<i64>),
+    DoubleList(Vec<f64>),
+    StringList(Vec<Option<&'a str>>),
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct IsarObject<'a> {
     bytes: &'a [u8],
@@ -35,10 +62,18 @@ impl<'a> IsarObject<'a> {
         IsarObject { bytes, static_size }
     }
 
+    pub fn deserialize<T: DeserializeOwned>(&self, collection: &IsarCollection) -> Result<T> {
+        T::deserialize(ObjectDeserializer::new(*self, collection.get_properties()))
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         self.bytes
     }
 
+    pub fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
     pub(crate) fn contains_offset(&self, offset: usize) -> bool {
         self.static_size > offset
     }
@@ -49,9 +84,9 @@ impl<'a> IsarObject<'a> {
 
     pub fn is_null(&self, property: Property) -> bool {
         match property.data_type {
-            DataType::Byte => self.read_byte(property) == Self::NULL_BYTE,
+            DataType::Byte | DataType::Bool => self.read_byte(property) == Self::NULL_BYTE,
             DataType::Int => self.read_int(property) == Self::NULL_INT,
-            DataType::Long => self.read_long(property) == Self::NULL_LONG,
+            DataType::Long | DataType::DateTime => self.read_long(property) == Self::NULL_LONG,
             DataType::Float => self.read_float(property).is_nan(),
             DataType::Double => self.read_double(property).is_nan(),
             _ => self.get_offset_length(property.offset, false).is_none(),
@@ -59,7 +94,7 @@ impl<'a> IsarObject<'a> {
     }
 
     pub fn read_byte(&self, property: Property) -> u8 {
-        assert_eq!(property.data_type, DataType::Byte);
+        assert_eq!(property.data_type.storage_type(), DataType::Byte);
         if self.contains_property(property) {
             self.bytes[property.offset]
         } else {
@@ -90,7 +125,7 @@ impl<'a> IsarObject<'a> {
     }
 
     pub fn read_long(&self, property: Property) -> i64 {
-        assert_eq!(property.data_type, DataType::Long);
+        assert_eq!(property.data_type.storage_type(), DataType::Long);
         if self.contains_property(property) {
             LittleEndian::read_i64(&self.bytes[property.offset..])
         } else {
@@ -129,6 +164,12 @@ impl<'a> IsarObject<'a> {
         self.read_string_at(property.offset, false)
     }
 
+    pub fn read_list_length(&self, property: Property) -> Option<usize> {
+        assert!(property.data_type.is_list());
+        let (_, length) = self.get_offset_length(property.offset, false)?;
+        Some(length)
+    }
+
     pub fn read_byte_list(&self, property: Property) -> Option<&'a [u8]> {
         assert_eq!(property.data_type, DataType::ByteList);
         let (offset, length) = self.get_offset_length(property.offset, false)?;
@@ -190,6 +231,30 @@ impl<'a> IsarObject<'a> {
         Some(list)
     }
 
+    pub fn read_value(&self, property: Property) -> IsarValue<'a> {
+        if self.is_null(property) {
+            return IsarValue::Null;
+        }
+        match property.data_type {
+            DataType::Byte => IsarValue::Byte(self.read_byte(property)),
+            DataType::Bool => IsarValue::Bool(self.read_bool(property)),
+            DataType::Int => IsarValue::Int(self.read_int(property)),
+            DataType::Float => IsarValue::Float(self.read_float(property)),
+            DataType::Long => IsarValue::Long(self.read_long(property)),
+            DataType::Double => IsarValue::Double(self.read_double(property)),
+            DataType::DateTime => IsarValue::DateTime(self.read_long(property)),
+            DataType::String => {
+                IsarValue::String(self.read_string_at(property.offset, false).unwrap())
+            }
+            DataType::ByteList => IsarValue::ByteList(self.read_byte_list(property).unwrap()),
+            DataType::IntList => IsarValue::IntList(self.read_int_list(property).unwrap()),
+            DataType::FloatList => IsarValue::FloatList(self.read_float_list(property).unwrap()),
+            DataType::LongList => IsarValue::LongList(self.read_long_list(property).unwrap()),
+            DataType::DoubleList => IsarValue::DoubleList(self.read_double_list(property).unwrap()),
+            DataType::StringList => IsarValue::StringList(self.read_string_list(property).unwrap()),
+        }
+    }
+
     pub fn hash_property<H: Hasher>(
         &self,
         property: Property,
@@ -197,85 +262,158 @@ impl<'a> IsarObject<'a> {
         hasher: &mut H,
     ) {
         match property.data_type {
-            DataType::Byte => hasher.write_u8(self.read_byte(property)),
+            DataType::Byte | DataType::Bool => hasher.write_u8(self.read_byte(property)),
             DataType::Int => hasher.write_i32(self.read_int(property)),
-            DataType::Float => hasher.write(&self.read_float(property).to_le_bytes()),
-            DataType::Long => hasher.write_i64(self.read_long(property)),
-            DataType::Double => hasher.write(&self.read_double(property).to_le_bytes()),
+            // -0.0 and 0.0 as well as all NaNs are equal so they need the same hash
+            DataType::Float => {
+                let value = self.read_float(property);
+                let value = if value.is_nan() {
+                    f32::NAN
+                } else if value == 0.0 {
+                    0.0
+                } else {
+                    value
+                };
+                hasher.write(&value.to_le_bytes())
+            }
+            DataType::Long | DataType::DateTime => hasher.write_i64(self.read_long(property)),
+            DataType::Double => {
+                let value = self.read_double(property);
+                let value = if value.is_nan() {
+                    f64::NAN
+                } else if value == 0.0 {
+                    0.0
+                } else {
+                    value
+                };
+                hasher.write(&value.to_le_bytes())
+            }
             DataType::String => {
                 let str = self.read_string(property);
                 if let Some(str) = str {
-                    hasher.write_usize(str.len());
-                    if case_sensitive {
-                        hasher.write(str.as_bytes());
+                    let str = if case_sensitive {
+                        Cow::Borrowed(str)
                     } else {
-                        hasher.write(str.to_lowercase().as_bytes());
-                    }
+                        Cow::Owned(fold_case(str))
+                    };
+                    hasher.write_usize(str.len());
+                    hasher.write(str.as_bytes());
                 }
             }
             _ => unimplemented!(),
         }
     }
 
-    pub fn compare_property(&self, other: &IsarObject, property: Property) -> Ordering {
+    /// Null values and null lists are ordered first, `false` is ordered before `true`. Lists are
+    /// compared lexicographically and strings are case folded like in indexes if
+    /// `case_sensitive` is false.
+    pub fn compare_property(
+        &self,
+        other: &IsarObject,
+        property: Property,
+        case_sensitive: bool,
+    ) -> Ordering {
         match property.data_type {
-            DataType::Byte => self.read_byte(property).cmp(&other.read_byte(property)),
+            DataType::Byte | DataType::Bool => {
+                self.read_byte(property).cmp(&other.read_byte(property))
+            }
             DataType::Int => self.read_int(property).cmp(&other.read_int(property)),
-            DataType::Float => {
-                let f1 = self.read_float(property);
-                let f2 = other.read_float(property);
-                if !f1.is_nan() {
-                    if !f2.is_nan() {
-                        if f1 > f2 {
-                            Ordering::Greater
-                        } else {
-                            Ordering::Less
-                        }
-                    } else {
-                        Ordering::Greater
-                    }
-                } else if !f2.is_nan() {
-                    Ordering::Less
-                } else {
-                    Ordering::Equal
-                }
+            DataType::Float => Self::compare_float(
+                self.read_float(property) as f64,
+                other.read_float(property) as f64,
+            ),
+            DataType::Long | DataType::DateTime => {
+                self.read_long(property).cmp(&other.read_long(property))
             }
-            DataType::Long => self.read_long(property).cmp(&other.read_long(property)),
             DataType::Double => {
-                let f1 = self.read_double(property);
-                let f2 = other.read_double(property);
-                if !f1.is_nan() {
-                    if !f2.is_nan() {
-                        if f1 > f2 {
-                            Ordering::Greater
-                        } else {
-                            Ordering::Less
-                        }
-                    } else {
-                        Ordering::Greater
-                    }
-                } else if !f2.is_nan() {
-                    Ordering::Less
-                } else {
-                    Ordering::Equal
-                }
+                Self::compare_float(self.read_double(property), other.read_double(property))
             }
-            DataType::String => {
-                let s1 = self.read_string(property);
-                let s2 = other.read_string(property);
-                if let Some(s1) = s1 {
-                    if let Some(s2) = s2 {
-                        s1.cmp(s2)
-                    } else {
-                        Ordering::Greater
-                    }
-                } else if s2.is_some() {
-                    Ordering::Less
-                } else {
-                    Ordering::Equal
+            DataType::String => Self::compare_option(
+                self.read_string(property),
+                other.read_string(property),
+                |s1, s2| Self::compare_string(s1, s2, case_sensitive),
+            ),
+            DataType::ByteList => Self::compare_list(
+                self.read_byte_list(property),
+                other.read_byte_list(property),
+                |b1, b2| b1.cmp(b2),
+            ),
+            DataType::IntList => Self::compare_list(
+                self.read_int_list(property),
+                other.read_int_list(property),
+                |i1, i2| i1.cmp(i2),
+            ),
+            DataType::FloatList => Self::compare_list(
+                self.read_float_list(property),
+                other.read_float_list(property),
+                |f1, f2| Self::compare_float(*f1 as f64, *f2 as f64),
+            ),
+            DataType::LongList => Self::compare_list(
+                self.read_long_list(property),
+                other.read_long_list(property),
+                |l1, l2| l1.cmp(l2),
+            ),
+            DataType::DoubleList => Self::compare_list(
+                self.read_double_list(property),
+                other.read_double_list(property),
+                |f1, f2| Self::compare_float(*f1, *f2),
+            ),
+            DataType::StringList => Self::compare_list(
+                self.read_string_list(property),
+                other.read_string_list(property),
+                |s1, s2| {
+                    Self::compare_option(*s1, *s2, |s1, s2| {
+                        Self::compare_string(s1, s2, case_sensitive)
+                    })
+                },
+            ),
+        }
+    }
+
+    fn compare_option<T, F>(v1: Option<T>, v2: Option<T>, compare: F) -> Ordering
+    where
+        F: Fn(T, T) -> Ordering,
+    {
+        match (v1, v2) {
+            (Some(v1), Some(v2)) => compare(v1, v2),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn compare_list<T, L, F>(l1: Option<L>, l2: Option<L>, compare: F) -> Ordering
+    where
+        L: AsRef<[T]>,
+        F: Fn(&T, &T) -> Ordering,
+    {
+        Self::compare_option(l1, l2, |l1, l2| {
+            let (l1, l2) = (l1.as_ref(), l2.as_ref());
+            for (e1, e2) in l1.iter().zip(l2.iter()) {
+                let ord = compare(e1, e2);
+                if ord != Ordering::Equal {
+                    return ord;
                 }
             }
-            _ => unimplemented!(),
+            l1.len().cmp(&l2.len())
+        })
+    }
+
+    fn compare_string(s1: &str, s2: &str, case_sensitive: bool) -> Ordering {
+        if case_sensitive {
+            s1.cmp(s2)
+        } else {
+            fold_case(s1).cmp(&fold_case(s2))
+        }
+    }
+
+    /// NaN (null) is ordered first and -0.0 is equal to 0.0 like in float index keys.
+    fn compare_float(f1: f64, f2: f64) -> Ordering {
+        match (f1.is_nan(), f2.is_nan()) {
+            (false, false) => f1.partial_cmp(&f2).unwrap(),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (true, true) => Ordering::Equal,
         }
     }
 }
@@ -283,7 +421,7 @@ impl<'a> IsarObject<'a> {
 #[cfg(test)]
 mod tests {
     use crate::object::data_type::DataType::*;
-    use crate::object::isar_object::IsarObject;
+    use crate::object::isar_object::{IsarObject, IsarValue};
     use crate::{col, isar};
 
     macro_rules! builder {
@@ -534,4 +672,97 @@ mod tests {
         assert!(!b.finish().is_null(p));
         isar.close();
     }
+
+    #[test]
+    fn test_read_value() {
+        let data_types = vec![
+            Byte, Int, Float, Long, Double, String, ByteList, IntList, FloatList, LongList,
+            DoubleList, StringList, DateTime, Bool,
+        ];
+        for data_type in data_types {
+            builder!(isar, b, p, data_type);
+            b.write_null();
+            assert_eq!(b.finish().read_value(p), IsarValue::Null);
+            isar.close();
+        }
+
+        builder!(isar, b, p, Byte);
+        b.write_byte(3);
+        assert_eq!(b.finish().read_value(p), IsarValue::Byte(3));
+        isar.close();
+
+        builder!(isar, b, p, Bool);
+        b.write_bool(false);
+        assert_eq!(b.finish().read_value(p), IsarValue::Bool(false));
+        isar.close();
+
+        builder!(isar, b, p, Int);
+        b.write_int(-3);
+        assert_eq!(b.finish().read_value(p), IsarValue::Int(-3));
+        isar.close();
+
+        builder!(isar, b, p, Float);
+        b.write_float(1.5);
+        assert_eq!(b.finish().read_value(p), IsarValue::Float(1.5));
+        isar.close();
+
+        builder!(isar, b, p, Long);
+        b.write_long(123123123123);
+        assert_eq!(b.finish().read_value(p), IsarValue::Long(123123123123));
+        isar.close();
+
+        builder!(isar, b, p, Double);
+        b.write_double(-2.25);
+        assert_eq!(b.finish().read_value(p), IsarValue::Double(-2.25));
+        isar.close();
+
+        builder!(isar, b, p, DateTime);
+        b.write_long(1000);
+        assert_eq!(b.finish().read_value(p), IsarValue::DateTime(1000));
+        isar.close();
+
+        builder!(isar, b, p, String);
+        b.write_string(Some("hello"));
+        assert_eq!(b.finish().read_value(p), IsarValue::String("hello"));
+        isar.close();
+
+        builder!(isar, b, p, ByteList);
+        b.write_byte_list(Some(&[1, 2]));
+        assert_eq!(b.finish().read_value(p), IsarValue::ByteList(&[1, 2]));
+        isar.close();
+
+        builder!(isar, b, p, IntList);
+        b.write_int_list(Some(&[1, IsarObject::NULL_INT]));
+        assert_eq!(
+            b.finish().read_value(p),
+            IsarValue::IntList(vec![1, IsarObject::NULL_INT])
+        );
+        isar.close();
+
+        builder!(isar, b, p, FloatList);
+        b.write_float_list(Some(&[1.5]));
+        assert_eq!(b.finish().read_value(p), IsarValue::FloatList(vec![1.5]));
+        isar.close();
+
+        builder!(isar, b, p, LongList);
+        b.write_long_list(Some(&[]));
+        assert_eq!(b.finish().read_value(p), IsarValue::LongList(vec![]));
+        isar.close();
+
+        builder!(isar, b, p, DoubleList);
+        b.write_double_list(Some(&[2.5, 3.5]));
+        assert_eq!(
+            b.finish().read_value(p),
+            IsarValue::DoubleList(vec![2.5, 3.5])
+        );
+        isar.close();
+
+        builder!(isar, b, p, StringList);
+        b.write_string_list(Some(&[Some("a"), None]));
+        assert_eq!(
+            b.finish().read_value(p),
+            IsarValue::StringList(vec![Some("a"), None])
+        );
+        isar.close();
+    }
 }
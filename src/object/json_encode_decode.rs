@@ -3,6 +3,7 @@ use crate::error::{IsarError, Result};
 use crate::object::data_type::DataType;
 use crate::object::isar_object::IsarObject;
 use crate::object::object_builder::ObjectBuilder;
+use crate::utils::date_time::{format_iso8601, parse_iso8601};
 use serde_json::{json, Map, Value};
 
 pub(crate) struct JsonEncodeDecode {}
@@ -30,10 +31,24 @@ impl<'a> JsonEncodeDecode {
                                 json!(object.read_byte(property))
                             }
                         }
+                        DataType::Bool => {
+                            if object.is_null(property) {
+                                Value::Null
+                            } else {
+                                json!(object.read_bool(property))
+                            }
+                        }
                         DataType::Int => json!(object.read_int(property)),
                         DataType::Float => json!(object.read_float(property)),
                         DataType::Long => json!(object.read_long(property)),
                         DataType::Double => json!(object.read_double(property)),
+                        DataType::DateTime => {
+                            if object.is_null(property) {
+                                Value::Null
+                            } else {
+                                json!(format_iso8601(object.read_long(property)))
+                            }
+                        }
                         DataType::String => json!(object.read_string(property)),
                         DataType::ByteList => json!(object.read_byte_list(property)),
                         DataType::IntList => json!(object.read_int_list(property)),
@@ -62,6 +77,7 @@ impl<'a> JsonEncodeDecode {
             if let Some(value) = object.get(name) {
                 match property.data_type {
                     DataType::Byte => ob.write_byte(Self::value_to_byte(value)?),
+                    DataType::Bool => ob.write_byte(Self::value_to_bool(value)?),
                     DataType::Int => ob.write_int(Self::value_to_int(value)?),
                     DataType::Float => ob.write_float(Self::value_to_float(value)?),
                     DataType::Long => {
@@ -72,6 +88,7 @@ impl<'a> JsonEncodeDecode {
                         ob.write_long(long_value)
                     }
                     DataType::Double => ob.write_double(Self::value_to_double(value)?),
+                    DataType::DateTime => ob.write_long(Self::value_to_date_time(value)?),
                     DataType::String => ob.write_string(Self::value_to_string(value)?),
                     DataType::ByteList => {
                         let list = Self::value_to_array(value, Self::value_to_byte)?;
@@ -118,6 +135,21 @@ impl<'a> JsonEncodeDecode {
         Ok(ob)
     }
 
+    fn value_to_bool(value: &Value) -> Result<u8> {
+        if value.is_null() {
+            Ok(IsarObject::NULL_BYTE)
+        } else if let Some(value) = value.as_bool() {
+            let byte = if value {
+                IsarObject::TRUE_BYTE
+            } else {
+                IsarObject::FALSE_BYTE
+            };
+            Ok(byte)
+        } else {
+            Err(IsarError::InvalidJson {})
+        }
+    }
+
     fn value_to_byte(value: &Value) -> Result<u8> {
         if value.is_null() {
             return Ok(IsarObject::NULL_BYTE);
@@ -168,6 +200,14 @@ impl<'a> JsonEncodeDecode {
         }
     }
 
+    fn value_to_date_time(value: &Value) -> Result<i64> {
+        if let Some(value) = value.as_str() {
+            parse_iso8601(value).ok_or(IsarError::InvalidJson {})
+        } else {
+            Self::value_to_long(value)
+        }
+    }
+
     fn value_to_double(value: &Value) -> Result<f64> {
         if value.is_null() {
             Ok(IsarObject::NULL_DOUBLE)
@@ -202,3 +242,80 @@ impl<'a> JsonEncodeDecode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{col, isar};
+
+    #[test]
+    fn test_date_time_roundtrip() {
+        isar!(isar, col => col!(oid => DataType::Long, date => DataType::DateTime));
+        let date = col.get_properties().get(1).unwrap().1;
+
+        let inputs = vec![
+            (
+                json!("1970-01-01T00:00:00Z"),
+                json!("1970-01-01T00:00:00.000Z"),
+                0,
+            ),
+            (
+                json!("1969-12-31T23:59:59.5Z"),
+                json!("1969-12-31T23:59:59.500Z"),
+                -500,
+            ),
+            (
+                json!("2021-04-01T02:00:00.123+02:00"),
+                json!("2021-04-01T00:00:00.123Z"),
+                1_617_235_200_123,
+            ),
+            (
+                json!(86_400_000),
+                json!("1970-01-02T00:00:00.000Z"),
+                86_400_000,
+            ),
+        ];
+        for (input, output, millis) in inputs {
+            let ob =
+                JsonEncodeDecode::decode(col, &json!({"oid": 1, "date": input}), None).unwrap();
+            let object = ob.finish();
+            assert_eq!(object.read_long(date), millis);
+            let encoded = JsonEncodeDecode::encode(col, object, false, false);
+            assert_eq!(encoded["date"], output);
+        }
+
+        let ob = JsonEncodeDecode::decode(col, &json!({"oid": 1, "date": null}), None).unwrap();
+        let encoded = JsonEncodeDecode::encode(col, ob.finish(), false, false);
+        assert_eq!(encoded["date"], Value::Null);
+
+        for invalid in &["yesterday", "2021-02-31T00:00:00Z"] {
+            let json = json!({"oid": 1, "date": invalid});
+            match JsonEncodeDecode::decode(col, &json, None) {
+                Err(IsarError::InvalidJson {}) => {}
+                _ => panic!("wrong error"),
+            }
+        }
+        isar.close();
+    }
+
+    #[test]
+    fn test_bool_roundtrip() {
+        isar!(isar, col => col!(oid => DataType::Long, flag => DataType::Bool, byte => DataType::Byte));
+
+        for value in &[json!(true), json!(false), Value::Null] {
+            let json = json!({"oid": 1, "flag": value, "byte": 2});
+            let ob = JsonEncodeDecode::decode(col, &json, None).unwrap();
+            let object = ob.finish();
+            let encoded = JsonEncodeDecode::encode(col, object, false, false);
+            assert_eq!(&encoded["flag"], value);
+            assert_eq!(encoded["byte"], json!(2));
+            let encoded = JsonEncodeDecode::encode(col, object, false, true);
+            assert_eq!(&encoded["flag"], value);
+            assert_eq!(encoded["byte"], json!(true));
+        }
+
+        let result = JsonEncodeDecode::decode(col, &json!({"oid": 1, "flag": 1}), None);
+        assert!(result.is_err());
+        isar.close();
+    }
+}
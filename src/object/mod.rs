@@ -1,5 +1,8 @@
+pub mod csv_encode;
 pub mod data_type;
+pub mod from_isar_object;
 pub mod isar_object;
 pub mod json_encode_decode;
 pub mod object_builder;
+pub mod object_deserializer;
 pub mod object_info;
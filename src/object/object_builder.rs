@@ -50,10 +50,10 @@ impl<'a> ObjectBuilder<'a> {
     pub fn write_null(&mut self) {
         let property = self.get_next_property(true);
         match property.data_type {
-            DataType::Byte => self.write_byte(IsarObject::NULL_BYTE),
+            DataType::Byte | DataType::Bool => self.write_byte(IsarObject::NULL_BYTE),
             DataType::Int => self.write_int(IsarObject::NULL_INT),
             DataType::Float => self.write_float(IsarObject::NULL_FLOAT),
-            DataType::Long => self.write_long(IsarObject::NULL_LONG),
+            DataType::Long | DataType::DateTime => self.write_long(IsarObject::NULL_LONG),
             DataType::Double => self.write_double(IsarObject::NULL_DOUBLE),
             DataType::String => self.write_string(None),
             DataType::ByteList => self.write_byte_list(None),
@@ -67,7 +67,7 @@ impl<'a> ObjectBuilder<'a> {
 
     pub fn write_byte(&mut self, value: u8) {
         let property = self.get_next_property(false);
-        assert_eq!(property.data_type, DataType::Byte);
+        assert_eq!(property.data_type.storage_type(), DataType::Byte);
         self.write_at(property.offset, &[value]);
     }
 
@@ -94,7 +94,7 @@ impl<'a> ObjectBuilder<'a> {
 
     pub fn write_long(&mut self, value: i64) {
         let property = self.get_next_property(false);
-        assert_eq!(property.data_type, DataType::Long);
+        assert_eq!(property.data_type.storage_type(), DataType::Long);
         self.write_at(property.offset, &value.to_le_bytes());
     }
 
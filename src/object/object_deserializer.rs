@@ -0,0 +1,308 @@
+use crate::error::{IsarError, Result};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::{IsarObject, Property};
+use serde::de::{DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use std::slice::Iter;
+
+/// Reads an object directly into a serde type. Structs are matched by property name,
+/// tuples and sequences by property order.
+pub(crate) struct ObjectDeserializer<'a, 'p> {
+    object: IsarObject<'a>,
+    properties: &'p [(String, Property)],
+}
+
+impl<'a, 'p> ObjectDeserializer<'a, 'p> {
+    pub fn new(object: IsarObject<'a>, properties: &'p [(String, Property)]) -> Self {
+        ObjectDeserializer { object, properties }
+    }
+}
+
+impl<'de, 'a, 'p> Deserializer<'de> for ObjectDeserializer<'a, 'p> {
+    type Error = IsarError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(PropertyMap {
+            object: self.object,
+            properties: self.properties.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(PropertySeq {
+            object: self.object,
+            properties: self.properties.iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct map struct enum identifier ignored_any
+    }
+}
+
+struct PropertyMap<'a, 'p> {
+    object: IsarObject<'a>,
+    properties: Iter<'p, (String, Property)>,
+    value: Option<Property>,
+}
+
+impl<'de, 'a, 'p> MapAccess<'de> for PropertyMap<'a, 'p> {
+    type Error = IsarError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if let Some((name, property)) = self.properties.next() {
+            self.value = Some(*property);
+            seed.deserialize(name.as_str().into_deserializer())
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let property = self.value.take().unwrap();
+        seed.deserialize(PropertyDeserializer {
+            object: self.object,
+            property,
+        })
+    }
+}
+
+struct PropertySeq<'a, 'p> {
+    object: IsarObject<'a>,
+    properties: Iter<'p, (String, Property)>,
+}
+
+impl<'de, 'a, 'p> SeqAccess<'de> for PropertySeq<'a, 'p> {
+    type Error = IsarError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        let object = self.object;
+        self.properties
+            .next()
+            .map(|(_, property)| {
+                seed.deserialize(PropertyDeserializer {
+                    object,
+                    property: *property,
+                })
+            })
+            .transpose()
+    }
+}
+
+struct PropertyDeserializer<'a> {
+    object: IsarObject<'a>,
+    property: Property,
+}
+
+impl<'de, 'a> Deserializer<'de> for PropertyDeserializer<'a> {
+    type Error = IsarError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let object = self.object;
+        let p = self.property;
+        match p.data_type {
+            DataType::Byte => visitor.visit_u8(object.read_byte(p)),
+            DataType::Bool => visitor.visit_bool(object.read_bool(p)),
+            DataType::Int => visitor.visit_i32(object.read_int(p)),
+            DataType::Float => visitor.visit_f32(object.read_float(p)),
+            DataType::Long | DataType::DateTime => visitor.visit_i64(object.read_long(p)),
+            DataType::Double => visitor.visit_f64(object.read_double(p)),
+            DataType::String => match object.read_string(p) {
+                Some(value) => visitor.visit_str(value),
+                None => visitor.visit_none(),
+            },
+            DataType::ByteList => visit_list(
+                object
+                    .read_byte_list(p)
+                    .map(|list| list.iter().map(|value| Element::Byte(*value)).collect()),
+                visitor,
+            ),
+            DataType::IntList => visit_list(
+                object
+                    .read_int_list(p)
+                    .map(|list| list.into_iter().map(Element::Int).collect()),
+                visitor,
+            ),
+            DataType::FloatList => visit_list(
+                object
+                    .read_float_list(p)
+                    .map(|list| list.into_iter().map(Element::Float).collect()),
+                visitor,
+            ),
+            DataType::LongList => visit_list(
+                object
+                    .read_long_list(p)
+                    .map(|list| list.into_iter().map(Element::Long).collect()),
+                visitor,
+            ),
+            DataType::DoubleList => visit_list(
+                object
+                    .read_double_list(p)
+                    .map(|list| list.into_iter().map(Element::Double).collect()),
+                visitor,
+            ),
+            DataType::StringList => visit_list(
+                object
+                    .read_string_list(p)
+                    .map(|list| list.into_iter().map(Element::String).collect()),
+                visitor,
+            ),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.property.data_type == DataType::Byte {
+            visitor.visit_bool(self.object.read_bool(self.property))
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.object.is_null(self.property) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+fn visit_list<'de, V: Visitor<'de>>(list: Option<Vec<Element>>, visitor: V) -> Result<V::Value> {
+    if let Some(list) = list {
+        visitor.visit_seq(ElementSeq {
+            elements: list.into_iter(),
+        })
+    } else {
+        visitor.visit_none()
+    }
+}
+
+enum Element<'a> {
+    Byte(u8),
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(Option<&'a str>),
+}
+
+struct ElementSeq<'a> {
+    elements: std::vec::IntoIter<Element<'a>>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for ElementSeq<'a> {
+    type Error = IsarError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        self.elements
+            .next()
+            .map(|element| seed.deserialize(element))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+impl<'de, 'a> Deserializer<'de> for Element<'a> {
+    type Error = IsarError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Element::Byte(value) => visitor.visit_u8(value),
+            Element::Int(value) => visitor.visit_i32(value),
+            Element::Float(value) => visitor.visit_f32(value),
+            Element::Long(value) => visitor.visit_i64(value),
+            Element::Double(value) => visitor.visit_f64(value),
+            Element::String(Some(value)) => visitor.visit_str(value),
+            Element::String(None) => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Element::String(None) => visitor.visit_none(),
+            element => visitor.visit_some(element),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::IsarError;
+    use crate::object::data_type::DataType;
+    use crate::{col, isar};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Person {
+        oid: i64,
+        name: Option<String>,
+        age: Option<i32>,
+        active: bool,
+        tags: Option<Vec<Option<String>>>,
+        scores: Vec<i64>,
+    }
+
+    #[test]
+    fn test_deserialize() {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String, age => DataType::Int, active => DataType::Byte, tags => DataType::StringList, scores => DataType::LongList));
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(1);
+        ob.write_string(None);
+        ob.write_null();
+        ob.write_bool(true);
+        ob.write_string_list(Some(&[Some("a"), None]));
+        ob.write_long_list(Some(&[3, 4]));
+        let object = ob.finish();
+
+        let person: Person = object.deserialize(col).unwrap();
+        assert_eq!(
+            person,
+            Person {
+                oid: 1,
+                name: None,
+                age: None,
+                active: true,
+                tags: Some(vec![Some("a".to_string()), None]),
+                scores: vec![3, 4],
+            }
+        );
+
+        let tuple: (i64, Option<String>, i32) = object.deserialize(col).unwrap();
+        assert_eq!(tuple, (1, None, i32::MIN));
+
+        let result: Result<(i64, String), IsarError> = object.deserialize(col);
+        assert!(result.is_err());
+        isar.close();
+    }
+}
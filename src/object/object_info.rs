@@ -1,4 +1,7 @@
+use crate::object::data_type::DataType;
 use crate::object::isar_object::{IsarObject, Property};
+use byteorder::{ByteOrder, LittleEndian};
+use std::ops::Range;
 
 #[cfg_attr(test, derive(Clone))]
 pub(crate) struct ObjectInfo {
@@ -37,73 +40,73 @@ impl ObjectInfo {
         &self.properties
     }
 
+    /// Checks that the static section of the object matches this schema and that all dynamic
+    /// data is within the bounds of the object and contains valid strings.
     pub fn verify_object(&self, object: IsarObject) -> bool {
-        /*let alignment = object.as_ref().as_ptr() as usize - CollectionObjectId::get_size();
-        if alignment % 8 != 0 {
+        let bytes = object.as_bytes();
+        if bytes.len() < self.static_size
+            || bytes.len() < 2
+            || LittleEndian::read_u16(bytes) as usize != self.static_size
+        {
             return false;
         }
-        let check_padding = |index: usize, count: usize| -> bool {
-            if object.len() < index + count {
-                return false;
-            }
-            for padding_byte in &object[index..index + count] {
-                if *padding_byte != 0 {
-                    return false;
-                }
-            }
-            true
-        };
 
-        if (CollectionObjectId::get_size() + object.len()) % 8 != 0 {
-            return false;
-        }
-
-        let mut static_offset = 0;
-        let mut dynamic_offset = self.static_size;
+        let mut dynamic_end = self.static_size;
         for (_, property) in &self.properties {
-            let required_padding = property.offset - static_offset;
-            if !check_padding(static_offset, required_padding) {
-                return false;
-            }
-            static_offset += required_padding;
-
-            if property.offset != static_offset {
-                return false;
-            }
-            static_offset += property.data_type.get_static_size();
-
-            if property.data_type.is_dynamic() && !property.is_null(object) {
-                let pos = property.get_dynamic_position(object).unwrap();
-                let alignment_wrong = (dynamic_offset + CollectionObjectId::get_size())
-                    % property.data_type.get_element_size()
-                    != 0;
-                if pos.offset as usize != dynamic_offset || alignment_wrong {
-                    return false;
+            let element_size = match property.data_type {
+                DataType::String | DataType::ByteList => 1,
+                DataType::IntList | DataType::FloatList => 4,
+                DataType::LongList | DataType::DoubleList | DataType::StringList => 8,
+                _ => continue,
+            };
+            let range = match self.get_dynamic_range(bytes, property.offset, element_size) {
+                Some(range) => range,
+                None => return false,
+            };
+            dynamic_end = dynamic_end.max(range.end);
+            match property.data_type {
+                DataType::String => {
+                    if std::str::from_utf8(&bytes[range]).is_err() {
+                        return false;
+                    }
                 }
-
-                if property.data_type == DataType::StringList {
-                    let list_positions = property.get_dynamic_positions(object).unwrap();
-                    let last_with_length = list_positions.iter().rev().find(|p| p.length != 0);
-                    if let Some(last_pos) = last_with_length {
-                        dynamic_offset += last_pos.length as usize;
+                DataType::StringList => {
+                    for pos in range.step_by(8) {
+                        let str_range = match self.get_dynamic_range(bytes, pos, 1) {
+                            Some(range) => range,
+                            None => return false,
+                        };
+                        dynamic_end = dynamic_end.max(str_range.end);
+                        if std::str::from_utf8(&bytes[str_range]).is_err() {
+                            return false;
+                        }
                     }
-                } else {
-                    dynamic_offset += pos.length as usize * property.data_type.get_element_size();
                 }
+                _ => {}
             }
         }
 
-        if static_offset != self.static_size {
-            return false;
-        }
+        dynamic_end == bytes.len()
+    }
 
-        let required_padding = (8 - (dynamic_offset + CollectionObjectId::get_size()) % 8) % 8;
-        if !check_padding(dynamic_offset, required_padding as usize) {
-            return false;
+    /// Reads the offset and length at `pos` and returns the range of the dynamic data. Null
+    /// values return an empty range.
+    fn get_dynamic_range(
+        &self,
+        bytes: &[u8],
+        pos: usize,
+        element_size: usize,
+    ) -> Option<Range<usize>> {
+        let offset = LittleEndian::read_u32(bytes.get(pos..pos + 8)?) as usize;
+        if offset == 0 {
+            return Some(0..0);
         }
-
-        dynamic_offset + required_padding == object.len()*/
-        true
+        let length = LittleEndian::read_u32(&bytes[pos + 4..]) as usize;
+        let end = offset.checked_add(length.checked_mul(element_size)?)?;
+        if offset < self.static_size || end > bytes.len() {
+            return None;
+        }
+        Some(offset..end)
     }
 }
 
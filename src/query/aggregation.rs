@@ -0,0 +1,177 @@
+use crate::error::{illegal_arg, Result};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::Property;
+use crate::query::Query;
+use crate::txn::IsarTxn;
+use enum_ordinalize::Ordinalize;
+
+#[derive(Ordinalize, Copy, Clone, Eq, PartialEq, Debug)]
+#[repr(u8)]
+pub enum AggregationOp {
+    Min,
+    Max,
+    Sum,
+    Average,
+    Count,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AggregateValue {
+    Long(i64),
+    Double(f64),
+}
+
+impl<'txn> Query {
+    /// Aggregates the property over all results. Null values are skipped and `None` is returned
+    /// if there is no value to aggregate. `Count` counts all results regardless of the property.
+    pub fn aggregate(
+        &self,
+        txn: &mut IsarTxn<'txn>,
+        property: Property,
+        op: AggregationOp,
+    ) -> Result<Option<AggregateValue>> {
+        let is_double = match property.data_type {
+            DataType::Int | DataType::Long | DataType::DateTime => false,
+            DataType::Float | DataType::Double => true,
+            _ if op == AggregationOp::Count => false,
+            _ => return illegal_arg("Property does not support aggregation."),
+        };
+
+        let mut count = 0usize;
+        let mut long_value = 0i64;
+        let mut double_value = 0f64;
+
+        txn.read(|cursors| {
+            self.find_all_internal(cursors, self.can_skip_sorting(), |object| {
+                if op == AggregationOp::Count {
+                    count += 1;
+                    return Ok(true);
+                }
+                if object.is_null(property) {
+                    return Ok(true);
+                }
+
+                let first = count == 0;
+                count += 1;
+                if is_double {
+                    let value = if property.data_type == DataType::Float {
+                        object.read_float(property) as f64
+                    } else {
+                        object.read_double(property)
+                    };
+                    double_value = match op {
+                        AggregationOp::Min if first || value < double_value => value,
+                        AggregationOp::Max if first || value > double_value => value,
+                        AggregationOp::Sum | AggregationOp::Average => double_value + value,
+                        _ => double_value,
+                    };
+                } else {
+                    let value = if property.data_type == DataType::Int {
+                        object.read_int(property) as i64
+                    } else {
+                        object.read_long(property)
+                    };
+                    long_value = match op {
+                        AggregationOp::Min if first || value < long_value => value,
+                        AggregationOp::Max if first || value > long_value => value,
+                        AggregationOp::Sum | AggregationOp::Average => {
+                            long_value.saturating_add(value)
+                        }
+                        _ => long_value,
+                    };
+                }
+                Ok(true)
+            })
+        })?;
+
+        if op == AggregationOp::Count {
+            return Ok(Some(AggregateValue::Long(count as i64)));
+        } else if count == 0 {
+            return Ok(None);
+        }
+
+        let result = match op {
+            AggregationOp::Average if is_double => {
+                AggregateValue::Double(double_value / count as f64)
+            }
+            AggregationOp::Average => AggregateValue::Double(long_value as f64 / count as f64),
+            _ if is_double => AggregateValue::Double(double_value),
+            _ => AggregateValue::Long(long_value),
+        };
+        Ok(Some(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object::data_type::DataType;
+    use crate::query::filter::IntBetweenCond;
+    use crate::{col, isar};
+
+    use super::*;
+
+    #[test]
+    fn test_aggregate() -> Result<()> {
+        isar!(isar, col => col!(oid => DataType::Long, int => DataType::Int, double => DataType::Double));
+        let mut txn = isar.begin_txn(true, false)?;
+        let values = [
+            (1, 5, 1.5),
+            (2, -3, f64::NAN),
+            (3, crate::object::isar_object::IsarObject::NULL_INT, 4.0),
+        ];
+        for (oid, int, double) in values.iter() {
+            let mut o = col.new_object_builder(None);
+            o.write_long(*oid);
+            o.write_int(*int);
+            o.write_double(*double);
+            col.put(&mut txn, o.finish())?;
+        }
+        let int = col.get_properties().get(1).unwrap().1;
+        let double = col.get_properties().get(2).unwrap().1;
+
+        let q = col.new_query_builder().build();
+        let aggregate = |txn: &mut IsarTxn, property, op| q.aggregate(txn, property, op).unwrap();
+        assert_eq!(
+            aggregate(&mut txn, int, AggregationOp::Min),
+            Some(AggregateValue::Long(-3))
+        );
+        assert_eq!(
+            aggregate(&mut txn, int, AggregationOp::Max),
+            Some(AggregateValue::Long(5))
+        );
+        assert_eq!(
+            aggregate(&mut txn, int, AggregationOp::Sum),
+            Some(AggregateValue::Long(2))
+        );
+        assert_eq!(
+            aggregate(&mut txn, int, AggregationOp::Average),
+            Some(AggregateValue::Double(1.0))
+        );
+        assert_eq!(
+            aggregate(&mut txn, int, AggregationOp::Count),
+            Some(AggregateValue::Long(3))
+        );
+        assert_eq!(
+            aggregate(&mut txn, double, AggregationOp::Min),
+            Some(AggregateValue::Double(1.5))
+        );
+        assert_eq!(
+            aggregate(&mut txn, double, AggregationOp::Sum),
+            Some(AggregateValue::Double(5.5))
+        );
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(IntBetweenCond::filter(int, 100, 200)?);
+        let q = qb.build();
+        assert_eq!(q.aggregate(&mut txn, int, AggregationOp::Max)?, None);
+        assert_eq!(q.aggregate(&mut txn, double, AggregationOp::Sum)?, None);
+        assert_eq!(
+            q.aggregate(&mut txn, int, AggregationOp::Count)?,
+            Some(AggregateValue::Long(0))
+        );
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+}
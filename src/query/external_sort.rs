@@ -0,0 +1,153 @@
+use crate::error::Result;
+use crate::object::isar_object::{IsarObject, Property};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// Sorted queries write sorted runs to temporary files once the buffered objects use more than
+/// `max_memory` bytes. The objects are read again by id after merging the runs.
+#[derive(Copy, Clone)]
+pub(crate) struct SortSpill {
+    pub max_memory: usize,
+    pub col_id: u16,
+    pub oid_property: Property,
+}
+
+/// The maximum number of runs merged at once. Every run keeps a file and a read buffer open
+/// while it is merged so larger numbers of runs are merged in multiple passes.
+const MAX_MERGE_RUNS: usize = 64;
+
+/// Copies of sorted objects stored in a temporary file.
+pub(crate) struct SortRun {
+    reader: BufReader<File>,
+}
+
+impl SortRun {
+    pub fn write(objects: &[IsarObject]) -> Result<SortRun> {
+        let mut writer = BufWriter::new(tempfile::tempfile()?);
+        for object in objects {
+            Self::write_bytes(&mut writer, object.as_bytes())?;
+        }
+        Self::finish(writer)
+    }
+
+    fn write_bytes(writer: &mut BufWriter<File>, bytes: &[u8]) -> Result<()> {
+        writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(bytes)?;
+        Ok(())
+    }
+
+    fn finish(writer: BufWriter<File>) -> Result<SortRun> {
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(SortRun {
+            reader: BufReader::new(file),
+        })
+    }
+
+    fn read_next(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut length = [0; 4];
+        match self.reader.read_exact(&mut length) {
+            Ok(()) => {
+                let mut bytes = vec![0; u32::from_le_bytes(length) as usize];
+                self.reader.read_exact(&mut bytes)?;
+                Ok(Some(bytes))
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Merges the runs and calls `callback` with the object copies in sorted order. Equal
+    /// objects are returned in the order of their runs.
+    pub fn merge<C, F>(mut runs: Vec<SortRun>, compare: C, mut callback: F) -> Result<()>
+    where
+        C: Fn(&IsarObject, &IsarObject) -> Ordering,
+        F: FnMut(IsarObject) -> Result<bool>,
+    {
+        while runs.len() > MAX_MERGE_RUNS {
+            // adjacent runs are merged so equal objects keep the order of their runs
+            let mut merged_runs = vec![];
+            let mut remaining = runs.into_iter().peekable();
+            while remaining.peek().is_some() {
+                let group = remaining.by_ref().take(MAX_MERGE_RUNS).collect();
+                let mut writer = BufWriter::new(tempfile::tempfile()?);
+                Self::merge_group(group, &compare, |bytes| {
+                    Self::write_bytes(&mut writer, bytes)?;
+                    Ok(true)
+                })?;
+                merged_runs.push(Self::finish(writer)?);
+            }
+            runs = merged_runs;
+        }
+        Self::merge_group(runs, &compare, |bytes| {
+            callback(IsarObject::from_bytes(bytes))
+        })
+    }
+
+    fn merge_group<C, F>(mut runs: Vec<SortRun>, compare: &C, mut callback: F) -> Result<()>
+    where
+        C: Fn(&IsarObject, &IsarObject) -> Ordering,
+        F: FnMut(&[u8]) -> Result<bool>,
+    {
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (run_index, run) in runs.iter_mut().enumerate() {
+            if let Some(bytes) = run.read_next()? {
+                heap.push(MergeEntry {
+                    bytes,
+                    run_index,
+                    compare,
+                });
+            }
+        }
+        while let Some(entry) = heap.pop() {
+            if !callback(&entry.bytes)? {
+                break;
+            }
+            if let Some(bytes) = runs[entry.run_index].read_next()? {
+                heap.push(MergeEntry { bytes, ..entry });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The next object of a run. `BinaryHeap` is a max-heap so the order is reversed to pop the
+/// smallest object and, for equal objects, the one of the first run.
+struct MergeEntry<'a, C> {
+    bytes: Vec<u8>,
+    run_index: usize,
+    compare: &'a C,
+}
+
+impl<'a, C> Ord for MergeEntry<'a, C>
+where
+    C: Fn(&IsarObject, &IsarObject) -> Ordering,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        let object = IsarObject::from_bytes(&self.bytes);
+        let other_object = IsarObject::from_bytes(&other.bytes);
+        (self.compare)(&other_object, &object).then(other.run_index.cmp(&self.run_index))
+    }
+}
+
+impl<'a, C> PartialOrd for MergeEntry<'a, C>
+where
+    C: Fn(&IsarObject, &IsarObject) -> Ordering,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, C> PartialEq for MergeEntry<'a, C>
+where
+    C: Fn(&IsarObject, &IsarObject) -> Ordering,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a, C> Eq for MergeEntry<'a, C> where C: Fn(&IsarObject, &IsarObject) -> Ordering {}
@@ -1,22 +1,34 @@
 use crate::collection::IsarCollection;
 use crate::error::{illegal_arg, IsarError, Result};
+use crate::index::index_key::IndexKey;
+use crate::index::Index;
 use crate::link::Link;
 use crate::lmdb::cursor::Cursor;
+use crate::lmdb::IntKey;
+use crate::object::data_type::DataType;
 use crate::object::isar_object::{IsarObject, Property};
 use crate::query::fast_wild_match::fast_wild_match;
+use crate::schema::collection_schema::{IndexType, WordTokenizer};
+use crate::utils::fold_case;
 use enum_dispatch::enum_dispatch;
 use hashbrown::HashSet;
 use paste::paste;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[enum_dispatch]
 #[derive(Clone)]
 pub enum Filter {
+    IdBetween(IdBetweenCond),
     ByteBetween(ByteBetweenCond),
     IntBetween(IntBetweenCond),
     LongBetween(LongBetweenCond),
     FloatBetween(FloatBetweenCond),
     DoubleBetween(DoubleBetweenCond),
 
+    IntIn(IntInCond),
+    LongIn(LongInCond),
+
     ByteListContains(ByteListContainsCond),
     IntListContains(IntListContainsCond),
     LongListContains(LongListContainsCond),
@@ -24,15 +36,109 @@ pub enum Filter {
     StringBetween(StringBetweenCond),
     StringStartsWith(StringStartsWithCond),
     StringEndsWith(StringEndsWithCond),
-    StringMatches(StringMatchesCond),
+    StringWildcard(StringWildcardCond),
+    StringRegex(StringRegexCond),
+    StringWord(StringWordCond),
+    StringIn(StringInCond),
+    StringSimilar(StringSimilarCond),
 
     StringListContains(StringListContainsCond),
 
+    BytesEqual(BytesEqualCond),
+    BytesStartsWith(BytesStartsWithCond),
+
+    ListLength(ListLengthCond),
+
     And(AndCond),
     Or(OrCond),
     Not(NotCond),
     Static(StaticCond),
     Link(LinkCond),
+    Reference(ReferenceCond),
+}
+
+impl Filter {
+    /// Returns equivalent index bounds if this filter can be answered by a scan of `index`.
+    pub(crate) fn index_bounds<'a>(
+        &self,
+        index: &'a Index,
+    ) -> Option<(IndexKey<'a>, IndexKey<'a>)> {
+        if index.properties.len() != 1 {
+            return None;
+        }
+        let ip = index.properties[0];
+        let property = ip.property;
+        if ip.index_type == IndexType::Words {
+            return match self {
+                // the filter always splits unicode words
+                Filter::StringWord(cond)
+                    if cond.property == property
+                        && Some(cond.case_sensitive) == ip.case_sensitive
+                        && index.word_tokenizer == WordTokenizer::UnicodeWords =>
+                {
+                    let word = cond.value.as_deref()?;
+                    let mut key = IndexKey::new(index);
+                    key.add_string_word(word, cond.case_sensitive);
+                    Some((key.clone(), key))
+                }
+                _ => None,
+            };
+        } else if ip.index_type != IndexType::Value {
+            return None;
+        }
+        // sparse indexes don't contain null values
+        let sparse = index.sparse;
+        let mut lower = IndexKey::new(index);
+        let mut upper = IndexKey::new(index);
+        match self {
+            Filter::ByteBetween(cond)
+                if cond.property == property
+                    && !(sparse && cond.lower == IsarObject::NULL_BYTE) =>
+            {
+                lower.add_byte(cond.lower);
+                upper.add_byte(cond.upper);
+            }
+            Filter::IntBetween(cond)
+                if cond.property == property && !(sparse && cond.lower == IsarObject::NULL_INT) =>
+            {
+                lower.add_int(cond.lower);
+                upper.add_int(cond.upper);
+            }
+            Filter::LongBetween(cond)
+                if cond.property == property
+                    && !(sparse && cond.lower == IsarObject::NULL_LONG) =>
+            {
+                lower.add_long(cond.lower);
+                upper.add_long(cond.upper);
+            }
+            // NaN bounds have special null semantics in the filter
+            Filter::FloatBetween(cond)
+                if cond.property == property && !cond.lower.is_nan() && !cond.upper.is_nan() =>
+            {
+                lower.add_float(cond.lower);
+                upper.add_float(cond.upper);
+            }
+            Filter::DoubleBetween(cond)
+                if cond.property == property && !cond.lower.is_nan() && !cond.upper.is_nan() =>
+            {
+                lower.add_double(cond.lower);
+                upper.add_double(cond.upper);
+            }
+            _ => return None,
+        }
+        if index.properties[0].descending {
+            Some((upper, lower))
+        } else {
+            Some((lower, upper))
+        }
+    }
+
+    pub(crate) fn into_conjunction(self) -> Vec<Filter> {
+        match self {
+            Filter::And(cond) => cond.filters,
+            filter => vec![filter],
+        }
+    }
 }
 
 pub(crate) struct FilterCursors<'txn, 'a>(&'a mut Cursor<'txn>, &'a mut Cursor<'txn>);
@@ -50,6 +156,32 @@ pub(crate) trait Condition {
     fn get_linked_collections(&self, col_ids: &mut HashSet<u16>);
 }
 
+#[derive(Clone)]
+pub struct IdBetweenCond {
+    lower: i64,
+    upper: i64,
+    id_property: Property,
+}
+
+impl Condition for IdBetweenCond {
+    fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        let oid = object.read_long(self.id_property);
+        Ok(self.lower <= oid && self.upper >= oid)
+    }
+
+    fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+}
+
+impl IdBetweenCond {
+    pub fn filter(collection: &IsarCollection, lower: i64, upper: i64) -> Filter {
+        Filter::IdBetween(IdBetweenCond {
+            lower,
+            upper,
+            id_property: collection.get_oid_property(),
+        })
+    }
+}
+
 #[macro_export]
 macro_rules! filter_between_struct {
     ($name:ident, $data_type:ident, $type:ty) => {
@@ -63,7 +195,7 @@ macro_rules! filter_between_struct {
 
             impl [<$name Cond>] {
                 pub fn filter(property: Property, lower: $type, upper: $type) -> Result<Filter> {
-                    if property.data_type == crate::object::data_type::DataType::$data_type {
+                    if property.data_type.storage_type() == crate::object::data_type::DataType::$data_type {
                         Ok(Filter::$name(Self {
                             property,
                             lower,
@@ -125,6 +257,45 @@ primitive_filter_between!(LongBetween, Long, i64, read_long);
 float_filter_between!(FloatBetween, Float, f32, read_float);
 float_filter_between!(DoubleBetween, Double, f64, read_double);
 
+/// Keeps the values sorted and deduplicated so membership can be checked using a binary search.
+#[macro_export]
+macro_rules! primitive_filter_in {
+    ($name:ident, $data_type:ident, $type:ty, $prop_accessor:ident) => {
+        paste! {
+            #[derive(Clone)]
+            pub struct [<$name Cond>] {
+                values: Vec<$type>,
+                property: Property,
+            }
+
+            impl [<$name Cond>] {
+                pub fn filter(property: Property, values: &[$type]) -> Result<Filter> {
+                    if property.data_type.storage_type() == crate::object::data_type::DataType::$data_type {
+                        let mut values = values.to_vec();
+                        values.sort_unstable();
+                        values.dedup();
+                        Ok(Filter::$name(Self { property, values }))
+                    } else {
+                        illegal_arg("Property does not support this filter.")
+                    }
+                }
+            }
+
+            impl Condition for [<$name Cond>] {
+                fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+                    let val = object.$prop_accessor(self.property);
+                    Ok(self.values.binary_search(&val).is_ok())
+                }
+
+                fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+            }
+        }
+    };
+}
+
+primitive_filter_in!(IntIn, Int, i32, read_int);
+primitive_filter_in!(LongIn, Long, i64, read_long);
+
 #[macro_export]
 macro_rules! filter_not_equal_struct {
     ($name:ident, $data_type:ident, $type:ty) => {
@@ -177,7 +348,9 @@ primitive_list_filter!(LongListContains, Long, i64, read_long_list);
 pub struct StringBetweenCond {
     property: Property,
     lower: Option<String>,
+    include_lower: bool,
     upper: Option<String>,
+    include_upper: bool,
     case_sensitive: bool,
 }
 
@@ -185,24 +358,28 @@ impl StringBetweenCond {
     pub fn filter(
         property: Property,
         lower: Option<&str>,
+        include_lower: bool,
         upper: Option<&str>,
+        include_upper: bool,
         case_sensitive: bool,
     ) -> Result<Filter> {
         let lower = if case_sensitive {
             lower.map(|s| s.to_string())
         } else {
-            lower.map(|s| s.to_lowercase())
+            lower.map(fold_case)
         };
         let upper = if case_sensitive {
             upper.map(|s| s.to_string())
         } else {
-            upper.map(|s| s.to_lowercase())
+            upper.map(fold_case)
         };
         if property.data_type == crate::object::data_type::DataType::String {
             Ok(Filter::StringBetween(StringBetweenCond {
                 property,
                 lower,
+                include_lower,
                 upper,
+                include_upper,
                 case_sensitive,
             }))
         } else {
@@ -213,25 +390,85 @@ impl StringBetweenCond {
 
 impl Condition for StringBetweenCond {
     fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        // null is smaller than every string like in string indexes
         let other_str = object.read_string(self.property);
-        let result = match (self.lower.as_ref(), other_str) {
-            (Some(lower), Some(other)) => {
-                if let Some(upper) = self.upper.as_ref() {
-                    if self.case_sensitive {
-                        lower.as_str() <= other && upper.as_str() >= other
-                    } else {
-                        let lowercase_other = other.to_lowercase();
-                        lower <= &lowercase_other && upper >= &lowercase_other
-                    }
+        let folded;
+        let other = if self.case_sensitive {
+            other_str
+        } else {
+            folded = other_str.map(fold_case);
+            folded.as_deref()
+        };
+        let lower = self.lower.as_deref();
+        let upper = self.upper.as_deref();
+        let above_lower = if self.include_lower {
+            lower <= other
+        } else {
+            lower < other
+        };
+        let below_upper = if self.include_upper {
+            upper >= other
+        } else {
+            upper > other
+        };
+        Ok(above_lower && below_upper)
+    }
+
+    fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+}
+
+#[derive(Clone)]
+pub struct StringInCond {
+    property: Property,
+    values: Vec<Option<String>>,
+    case_sensitive: bool,
+}
+
+impl StringInCond {
+    /// A `None` value matches null strings.
+    pub fn filter(
+        property: Property,
+        values: &[Option<&str>],
+        case_sensitive: bool,
+    ) -> Result<Filter> {
+        if property.data_type != DataType::String {
+            return illegal_arg("Property does not support this filter.");
+        }
+        let mut values: Vec<Option<String>> = values
+            .iter()
+            .map(|value| {
+                if case_sensitive {
+                    value.map(|s| s.to_string())
                 } else {
-                    false
+                    value.map(fold_case)
                 }
-            }
-            (Some(_), None) => false,
-            (None, Some(_)) => true,
-            (None, None) => self.upper.is_none(),
+            })
+            .collect();
+        values.sort_unstable();
+        values.dedup();
+        Ok(Filter::StringIn(StringInCond {
+            property,
+            values,
+            case_sensitive,
+        }))
+    }
+}
+
+impl Condition for StringInCond {
+    fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        let other_str = object.read_string(self.property);
+        let folded;
+        let other = if self.case_sensitive {
+            other_str
+        } else {
+            folded = other_str.map(fold_case);
+            folded.as_deref()
         };
-        Ok(result)
+        let found = self
+            .values
+            .binary_search_by(|value| value.as_deref().cmp(&other))
+            .is_ok();
+        Ok(found)
     }
 
     fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
@@ -257,7 +494,7 @@ macro_rules! string_filter_struct {
                     let value = if case_sensitive {
                         value.map(|s| s.to_string())
                     } else {
-                        value.map(|s| s.to_lowercase())
+                        value.map(fold_case)
                     };
                     if property.data_type == crate::object::data_type::DataType::String {
                         Ok(Filter::$name([<$name Cond>] {
@@ -286,7 +523,7 @@ macro_rules! string_filter {
                         if self.case_sensitive {
                             string_filter!($name filter_str, other_str)
                         } else {
-                            let lowercase_string = other_str.to_lowercase();
+                            let lowercase_string = fold_case(other_str);
                             let lowercase_str = &lowercase_string;
                             string_filter!($name filter_str, lowercase_str)
                         }
@@ -309,14 +546,164 @@ macro_rules! string_filter {
         $other_str.ends_with($filter_str)
     };
 
-    (StringMatches $filter_str:ident, $other_str:ident) => {
+    (StringWildcard $filter_str:ident, $other_str:ident) => {
         fast_wild_match($other_str, $filter_str)
     };
 }
 
 string_filter!(StringStartsWith);
 string_filter!(StringEndsWith);
-string_filter!(StringMatches);
+string_filter!(StringWildcard);
+
+#[derive(Clone)]
+pub struct StringRegexCond {
+    property: Property,
+    regex: Regex,
+}
+
+impl StringRegexCond {
+    pub fn filter(property: Property, pattern: &str, case_sensitive: bool) -> Result<Filter> {
+        if property.data_type != crate::object::data_type::DataType::String {
+            return illegal_arg("Property does not support this filter.");
+        }
+        let pattern = if case_sensitive {
+            pattern.to_string()
+        } else {
+            format!("(?i){}", pattern)
+        };
+        match Regex::new(&pattern) {
+            Ok(regex) => Ok(Filter::StringRegex(StringRegexCond { property, regex })),
+            Err(err) => illegal_arg(&format!("Invalid regular expression: {}", err)),
+        }
+    }
+}
+
+impl Condition for StringRegexCond {
+    fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        let result = if let Some(str) = object.read_string(self.property) {
+            self.regex.is_match(str)
+        } else {
+            false
+        };
+        Ok(result)
+    }
+
+    fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+}
+
+#[derive(Clone)]
+pub struct StringSimilarCond {
+    property: Property,
+    value: Vec<char>,
+    max_distance: usize,
+    case_sensitive: bool,
+}
+
+impl StringSimilarCond {
+    /// Matches strings with a Levenshtein distance of at most `max_distance` to `value`. A
+    /// transposition of two characters counts as two edits.
+    pub fn filter(
+        property: Property,
+        value: &str,
+        max_distance: u32,
+        case_sensitive: bool,
+    ) -> Result<Filter> {
+        if property.data_type != DataType::String {
+            return illegal_arg("Property does not support this filter.");
+        }
+        let value = if case_sensitive {
+            value.chars().collect()
+        } else {
+            fold_case(value).chars().collect()
+        };
+        Ok(Filter::StringSimilar(StringSimilarCond {
+            property,
+            value,
+            max_distance: max_distance as usize,
+            case_sensitive,
+        }))
+    }
+
+    /// Only the cells within `max` of the diagonal are computed and the computation stops once
+    /// a whole row exceeds `max`, so the runtime is linear in the string length.
+    fn distance_within(a: &[char], b: &[char], max: usize) -> bool {
+        let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+        if long.len() - short.len() > max {
+            return false;
+        }
+        let exceeded = max + 1;
+        let mut prev: Vec<usize> = (0..=short.len()).map(|j| j.min(exceeded)).collect();
+        let mut cur = vec![exceeded; short.len() + 1];
+        for i in 1..=long.len() {
+            let lower = i.saturating_sub(max).max(1);
+            let upper = (i + max).min(short.len());
+            cur[lower - 1] = if lower == 1 {
+                i.min(exceeded)
+            } else {
+                exceeded
+            };
+            let mut row_min = cur[lower - 1];
+            for j in lower..=upper {
+                let cost = (long[i - 1] != short[j - 1]) as usize;
+                let distance = (prev[j - 1] + cost)
+                    .min(prev[j] + 1)
+                    .min(cur[j - 1] + 1)
+                    .min(exceeded);
+                cur[j] = distance;
+                row_min = row_min.min(distance);
+            }
+            if upper < short.len() {
+                cur[upper + 1] = exceeded;
+            }
+            if row_min > max {
+                return false;
+            }
+            std::mem::swap(&mut prev, &mut cur);
+        }
+        prev[short.len()] <= max
+    }
+}
+
+impl Condition for StringSimilarCond {
+    fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        let result = if let Some(str) = object.read_string(self.property) {
+            let chars: Vec<char> = if self.case_sensitive {
+                str.chars().collect()
+            } else {
+                fold_case(str).chars().collect()
+            };
+            Self::distance_within(&self.value, &chars, self.max_distance)
+        } else {
+            false
+        };
+        Ok(result)
+    }
+
+    fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+}
+
+string_filter_struct!(StringWord);
+
+impl Condition for StringWordCond {
+    fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        let result = if let (Some(word), Some(str)) =
+            (self.value.as_deref(), object.read_string(self.property))
+        {
+            str.unicode_words().any(|other_word| {
+                if self.case_sensitive {
+                    other_word == word
+                } else {
+                    fold_case(other_word) == word
+                }
+            })
+        } else {
+            false
+        };
+        Ok(result)
+    }
+
+    fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+}
 
 string_filter_struct!(StringListContains);
 
@@ -333,6 +720,97 @@ impl Condition for StringListContainsCond {
     fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
 }
 
+macro_rules! bytes_filter {
+    ($name:ident) => {
+        paste! {
+            #[derive(Clone)]
+            pub struct [<$name Cond>] {
+                property: Property,
+                value: Option<Vec<u8>>,
+            }
+
+            impl [<$name Cond>] {
+                pub fn filter(property: Property, value: Option<&[u8]>) -> Result<Filter> {
+                    if property.data_type == DataType::ByteList {
+                        Ok(Filter::$name([<$name Cond>] {
+                            property,
+                            value: value.map(|v| v.to_vec()),
+                        }))
+                    } else {
+                        illegal_arg("Property does not support this filter.")
+                    }
+                }
+            }
+
+            impl Condition for [<$name Cond>] {
+                fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+                    let other_bytes = object.read_byte_list(self.property);
+                    let result = if let (Some(filter_bytes), Some(other_bytes)) = (self.value.as_deref(), other_bytes) {
+                        bytes_filter!($name filter_bytes, other_bytes)
+                    } else {
+                        self.value.is_none() && other_bytes.is_none()
+                    };
+                    Ok(result)
+                }
+
+                fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+            }
+        }
+    };
+
+    (BytesEqual $filter_bytes:ident, $other_bytes:ident) => {
+        $other_bytes == $filter_bytes
+    };
+
+    (BytesStartsWith $filter_bytes:ident, $other_bytes:ident) => {
+        $other_bytes.starts_with($filter_bytes)
+    };
+}
+
+bytes_filter!(BytesEqual);
+bytes_filter!(BytesStartsWith);
+
+#[derive(Clone)]
+pub struct ListLengthCond {
+    property: Property,
+    lower: usize,
+    upper: usize,
+    null_as_empty: bool,
+}
+
+impl ListLengthCond {
+    pub fn filter(
+        property: Property,
+        lower: usize,
+        upper: usize,
+        null_as_empty: bool,
+    ) -> Result<Filter> {
+        if property.data_type.is_list() {
+            Ok(Filter::ListLength(ListLengthCond {
+                property,
+                lower,
+                upper,
+                null_as_empty,
+            }))
+        } else {
+            illegal_arg("Property does not support this filter.")
+        }
+    }
+}
+
+impl Condition for ListLengthCond {
+    fn evaluate(&self, object: IsarObject, _: Option<&mut FilterCursors>) -> Result<bool> {
+        let length = match object.read_list_length(self.property) {
+            Some(length) => length,
+            None if self.null_as_empty => 0,
+            None => return Ok(false),
+        };
+        Ok(self.lower <= length && self.upper >= length)
+    }
+
+    fn get_linked_collections(&self, _: &mut HashSet<u16>) {}
+}
+
 #[derive(Clone)]
 pub struct AndCond {
     filters: Vec<Filter>,
@@ -482,3 +960,534 @@ impl LinkCond {
         }))
     }
 }
+
+/// Matches objects whose `property` holds the id of an object in the target collection that
+/// matches `filter`. Null ids and ids without an object do not match.
+#[derive(Clone)]
+pub struct ReferenceCond {
+    property: Property,
+    target_col_id: u16,
+    filter: Box<Filter>,
+}
+
+impl Condition for ReferenceCond {
+    fn evaluate(&self, object: IsarObject, cursors: Option<&mut FilterCursors>) -> Result<bool> {
+        if object.is_null(self.property) {
+            return Ok(false);
+        }
+        let oid = object.read_long(self.property);
+        if let Some(cursors) = cursors {
+            let entry = cursors.0.move_to(IntKey::new(self.target_col_id, oid))?;
+            if let Some((_, target)) = entry {
+                self.filter.evaluate(IsarObject::from_bytes(target), None)
+            } else {
+                Ok(false)
+            }
+        } else {
+            illegal_arg("Reference filters need to be evaluated within a query.")
+        }
+    }
+
+    fn get_linked_collections(&self, col_ids: &mut HashSet<u16>) {
+        col_ids.insert(self.target_col_id);
+    }
+}
+
+impl ReferenceCond {
+    pub fn filter(
+        property: Property,
+        target_collection: &IsarCollection,
+        filter: Filter,
+    ) -> Result<Filter> {
+        if property.data_type != DataType::Long {
+            return illegal_arg("Property does not support this filter.");
+        }
+        Ok(Filter::Reference(ReferenceCond {
+            property,
+            target_col_id: target_collection.get_id(),
+            filter: Box::new(filter),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::query_builder::WherePlan;
+    use crate::query::Sort;
+    use crate::{col, ind, isar};
+
+    #[test]
+    fn test_reference() {
+        isar!(isar, customers => col!("customers", oid => DataType::Long, name => DataType::String), orders => col!("orders", oid => DataType::Long, customer => DataType::Long));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for (oid, name) in [(1, "Alice"), (2, "Bob"), (3, "Anna")].iter() {
+            let mut builder = customers.new_object_builder(None);
+            builder.write_long(*oid);
+            builder.write_string(Some(name));
+            customers.put(&mut txn, builder.finish()).unwrap();
+        }
+        for (oid, customer) in [
+            (10, Some(1)),
+            (11, Some(2)),
+            (12, Some(3)),
+            (13, None),
+            (14, Some(99)),
+        ]
+        .iter()
+        {
+            let mut builder = orders.new_object_builder(None);
+            builder.write_long(*oid);
+            match customer {
+                Some(customer) => builder.write_long(*customer),
+                None => builder.write_null(),
+            }
+            orders.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let name = customers.get_properties()[1].1;
+        let customer = orders.get_properties()[1].1;
+        let starts_with_a = StringStartsWithCond::filter(name, Some("A"), true).unwrap();
+        let filter = ReferenceCond::filter(customer, customers, starts_with_a.clone()).unwrap();
+        let mut qb = orders.new_query_builder();
+        qb.set_filter(filter);
+        let oids: Vec<i64> = qb
+            .build()
+            .find_all_vec(&mut txn)
+            .unwrap()
+            .iter()
+            .map(|o| o.read_long(orders.get_oid_property()))
+            .collect();
+        assert_eq!(oids, vec![10, 12]);
+
+        assert!(ReferenceCond::filter(name, customers, starts_with_a).is_err());
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_string_regex() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::String));
+        let property = col.get_properties().get(1).unwrap().1;
+        let matches = |filter: &Filter, value: Option<&str>| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_string(value);
+            filter.evaluate(builder.finish(), None).unwrap()
+        };
+
+        let filter = StringRegexCond::filter(property, "^SKU-[0-9]{3}$", true).unwrap();
+        assert!(matches(&filter, Some("SKU-123")));
+        assert!(!matches(&filter, Some("sku-123")));
+        assert!(!matches(&filter, Some("SKU-1234")));
+        assert!(!matches(&filter, None));
+
+        let filter = StringRegexCond::filter(property, "^SKU-[0-9]{3}$", false).unwrap();
+        assert!(matches(&filter, Some("sku-123")));
+
+        assert!(StringRegexCond::filter(property, "(unclosed", true).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_string_wildcard() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::String));
+        let property = col.get_properties().get(1).unwrap().1;
+        let matches = |pattern: &str, case_sensitive: bool, value: Option<&str>| {
+            let filter =
+                StringWildcardCond::filter(property, Some(pattern), case_sensitive).unwrap();
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_string(value);
+            filter.evaluate(builder.finish(), None).unwrap()
+        };
+
+        assert!(matches("*.png", true, Some("image.png")));
+        assert!(!matches("*.png", true, Some("image.jpg")));
+        assert!(matches("img*", true, Some("img_001")));
+        assert!(!matches("img*", true, Some("an img")));
+        assert!(matches("*img*", true, Some("an img file")));
+        assert!(matches("f?o", true, Some("foo")));
+        assert!(!matches("f?o", true, Some("fo")));
+        assert!(!matches("F?O", true, Some("foo")));
+        assert!(matches("F?O", false, Some("foo")));
+        assert!(!matches("*", true, None));
+        assert!(!matches("a", true, None));
+        isar.close();
+    }
+
+    #[test]
+    fn test_bytes() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::ByteList));
+        let property = col.get_properties().get(1).unwrap().1;
+        let matches = |filter: &Filter, value: Option<&[u8]>| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_byte_list(value);
+            filter.evaluate(builder.finish(), None).unwrap()
+        };
+
+        let filter = BytesEqualCond::filter(property, Some(&[1, 2])).unwrap();
+        assert!(matches(&filter, Some(&[1, 2])));
+        assert!(!matches(&filter, Some(&[1])));
+        assert!(!matches(&filter, Some(&[1, 2, 3])));
+        assert!(!matches(&filter, Some(&[])));
+        assert!(!matches(&filter, None));
+
+        let filter = BytesEqualCond::filter(property, Some(&[])).unwrap();
+        assert!(matches(&filter, Some(&[])));
+        assert!(!matches(&filter, Some(&[0])));
+        assert!(!matches(&filter, None));
+
+        let filter = BytesEqualCond::filter(property, None).unwrap();
+        assert!(matches(&filter, None));
+        assert!(!matches(&filter, Some(&[])));
+
+        let filter = BytesStartsWithCond::filter(property, Some(&[1, 2])).unwrap();
+        assert!(matches(&filter, Some(&[1, 2])));
+        assert!(matches(&filter, Some(&[1, 2, 3])));
+        assert!(!matches(&filter, Some(&[1])));
+        assert!(!matches(&filter, Some(&[2, 1, 2])));
+        assert!(!matches(&filter, None));
+
+        let filter = BytesStartsWithCond::filter(property, Some(&[])).unwrap();
+        assert!(matches(&filter, Some(&[])));
+        assert!(matches(&filter, Some(&[5])));
+        assert!(!matches(&filter, None));
+
+        let oid_property = col.get_oid_property();
+        assert!(BytesEqualCond::filter(oid_property, None).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_int_long_in() {
+        isar!(isar, col => col!(oid => DataType::Long, i => DataType::Int, l => DataType::Long));
+        let int_property = col.get_properties().get(1).unwrap().1;
+        let long_property = col.get_properties().get(2).unwrap().1;
+        let object = |value: i32| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_int(value);
+            builder.write_long(value as i64);
+            builder.finish().as_bytes().to_vec()
+        };
+        let matches = |filter: &Filter, value: i32| {
+            let bytes = object(value);
+            filter
+                .evaluate(IsarObject::from_bytes(&bytes), None)
+                .unwrap()
+        };
+
+        let filter = IntInCond::filter(int_property, &[7, 1, 3, 1, 7]).unwrap();
+        for value in &[1, 3, 7] {
+            assert!(matches(&filter, *value));
+        }
+        for value in &[0, 2, 8, IsarObject::NULL_INT] {
+            assert!(!matches(&filter, *value));
+        }
+
+        let filter = IntInCond::filter(int_property, &[]).unwrap();
+        assert!(!matches(&filter, 0));
+        assert!(!matches(&filter, IsarObject::NULL_INT));
+
+        let filter = IntInCond::filter(int_property, &[IsarObject::NULL_INT]).unwrap();
+        assert!(matches(&filter, IsarObject::NULL_INT));
+
+        let values: Vec<i64> = (0..10_000).rev().map(|i| i * 3).collect();
+        let filter = LongInCond::filter(long_property, &values).unwrap();
+        assert!(matches(&filter, 0));
+        assert!(matches(&filter, 29_997));
+        assert!(matches(&filter, 3_333));
+        assert!(!matches(&filter, 3_334));
+        assert!(!matches(&filter, 30_000));
+
+        assert!(IntInCond::filter(long_property, &[1]).is_err());
+        assert!(LongInCond::filter(int_property, &[1]).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_string_in() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::String));
+        let property = col.get_properties().get(1).unwrap().1;
+        let matches = |filter: &Filter, value: Option<&str>| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_string(value);
+            filter.evaluate(builder.finish(), None).unwrap()
+        };
+
+        let values = [Some("b"), Some("Ä"), Some("b"), Some("")];
+        let filter = StringInCond::filter(property, &values, true).unwrap();
+        assert!(matches(&filter, Some("b")));
+        assert!(matches(&filter, Some("Ä")));
+        assert!(matches(&filter, Some("")));
+        assert!(!matches(&filter, Some("B")));
+        assert!(!matches(&filter, Some("ä")));
+        assert!(!matches(&filter, None));
+
+        let filter = StringInCond::filter(property, &values, false).unwrap();
+        assert!(matches(&filter, Some("B")));
+        assert!(matches(&filter, Some("ä")));
+        assert!(!matches(&filter, Some("c")));
+
+        let filter = StringInCond::filter(property, &[None, Some("a")], true).unwrap();
+        assert!(matches(&filter, None));
+        assert!(matches(&filter, Some("a")));
+
+        let filter = StringInCond::filter(property, &[], false).unwrap();
+        assert!(!matches(&filter, Some("")));
+        assert!(!matches(&filter, None));
+
+        assert!(StringInCond::filter(col.get_oid_property(), &[], true).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_string_similar() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::String));
+        let property = col.get_properties().get(1).unwrap().1;
+        let matches =
+            |value: &str, max_distance: u32, case_sensitive: bool, other: Option<&str>| {
+                let filter =
+                    StringSimilarCond::filter(property, value, max_distance, case_sensitive)
+                        .unwrap();
+                let mut builder = col.new_object_builder(None);
+                builder.write_long(1);
+                builder.write_string(other);
+                filter.evaluate(builder.finish(), None).unwrap()
+            };
+
+        assert!(matches("kitten", 0, true, Some("kitten")));
+        assert!(!matches("kitten", 0, true, Some("kittens")));
+        // insertions and deletions
+        assert!(matches("kitten", 1, true, Some("kittens")));
+        assert!(matches("kitten", 1, true, Some("kiten")));
+        assert!(matches("", 3, true, Some("abc")));
+        assert!(!matches("", 2, true, Some("abc")));
+        // transpositions count as two edits
+        assert!(!matches("form", 1, true, Some("from")));
+        assert!(matches("form", 2, true, Some("from")));
+        // threshold boundary
+        assert!(matches("kitten", 3, true, Some("sitting")));
+        assert!(!matches("kitten", 2, true, Some("sitting")));
+        assert!(!matches("a", 5, true, Some(&"a".repeat(7))));
+
+        assert!(!matches("Straße", 0, true, Some("STRASSE")));
+        assert!(matches("Straße", 0, false, Some("STRASSE")));
+        assert!(matches("äpfel", 1, false, Some("ÄPFL")));
+        assert!(!matches("", 10, true, None));
+
+        assert!(StringSimilarCond::filter(col.get_oid_property(), "a", 1, true).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_string_word() {
+        fn test(case_sensitive: bool) {
+            isar!(isar, col => col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Words, Some(case_sensitive))));
+            let property = col.get_properties().get(1).unwrap().1;
+            let mut txn = isar.begin_txn(true, false).unwrap();
+            let values = [
+                Some("The quick brown fox"),
+                Some("the lazy dog"),
+                Some("THE END"),
+                Some("foxes"),
+                None,
+            ];
+            for (i, value) in values.iter().enumerate() {
+                let mut builder = col.new_object_builder(None);
+                builder.write_long(i as i64 + 1);
+                builder.write_string(*value);
+                col.put(&mut txn, builder.finish()).unwrap();
+            }
+
+            for word in &["The", "the", "fox", "FOX", "dog", "quick brown", "missing"] {
+                let filter = StringWordCond::filter(property, Some(word), case_sensitive).unwrap();
+                let mut qb = col.new_query_builder();
+                qb.set_filter(filter.clone());
+                let filtered = qb.build().find_all_vec(&mut txn).unwrap();
+
+                let mut qb = col.new_query_builder();
+                let plan = qb.where_optimized(filter).unwrap();
+                assert_eq!(plan, WherePlan::IndexScan { index_index: 0 });
+                let mut indexed = qb.build().find_all_vec(&mut txn).unwrap();
+
+                let oid = col.get_oid_property();
+                indexed.sort_by_key(|o| o.read_long(oid));
+                assert_eq!(filtered, indexed);
+            }
+
+            let mut matches = |word: &str| {
+                let filter = StringWordCond::filter(property, Some(word), case_sensitive).unwrap();
+                let mut qb = col.new_query_builder();
+                qb.set_filter(filter);
+                qb.build().count(&mut txn).unwrap()
+            };
+            if case_sensitive {
+                assert_eq!(matches("the"), 1);
+                assert_eq!(matches("FOX"), 0);
+            } else {
+                assert_eq!(matches("the"), 3);
+                assert_eq!(matches("FOX"), 1);
+            }
+            assert_eq!(matches("quick brown"), 0);
+
+            let filter = StringWordCond::filter(property, Some("fox"), !case_sensitive).unwrap();
+            let mut qb = col.new_query_builder();
+            assert_eq!(qb.where_optimized(filter).unwrap(), WherePlan::FilterOnly);
+            txn.abort();
+            isar.close();
+        }
+
+        test(true);
+        test(false);
+    }
+
+    #[test]
+    fn test_list_length() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::IntList));
+        let property = col.get_properties().get(1).unwrap().1;
+        let matches = |filter: &Filter, value: Option<&[i32]>| {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(1);
+            builder.write_int_list(value);
+            filter.evaluate(builder.finish(), None).unwrap()
+        };
+
+        let filter = ListLengthCond::filter(property, 0, 0, false).unwrap();
+        assert!(matches(&filter, Some(&[])));
+        assert!(!matches(&filter, Some(&[1])));
+        assert!(!matches(&filter, None));
+
+        let filter = ListLengthCond::filter(property, 0, 0, true).unwrap();
+        assert!(matches(&filter, None));
+
+        let filter = ListLengthCond::filter(property, 2, 3, true).unwrap();
+        assert!(!matches(&filter, Some(&[1])));
+        assert!(matches(&filter, Some(&[1, 2])));
+        assert!(matches(&filter, Some(&[1, 2, 3])));
+        assert!(!matches(&filter, Some(&[1, 2, 3, 4])));
+        assert!(!matches(&filter, None));
+
+        let oid_property = col.get_oid_property();
+        assert!(ListLengthCond::filter(oid_property, 0, 1, false).is_err());
+        isar.close();
+    }
+
+    #[test]
+    fn test_case_insensitive_folding() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(false))));
+        let property = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let values = ["straße", "i", "ı", "İ"];
+        for (i, value) in values.iter().enumerate() {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(i as i64 + 1);
+            builder.write_string(Some(value));
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+
+        let mut check = |value: &str, expected: Vec<i64>| {
+            let filter =
+                StringBetweenCond::filter(property, Some(value), true, Some(value), true, false);
+            let mut qb = col.new_query_builder();
+            qb.set_filter(filter.unwrap());
+            let filtered = qb.build().find_all_vec(&mut txn).unwrap();
+
+            let mut key = col.new_index_key(0).unwrap();
+            key.add_string_value(Some(value), false);
+            let mut qb = col.new_query_builder();
+            qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)
+                .unwrap();
+            let indexed = qb.build().find_all_vec(&mut txn).unwrap();
+
+            let oid = col.get_oid_property();
+            let filtered: Vec<i64> = filtered.iter().map(|o| o.read_long(oid)).collect();
+            let indexed: Vec<i64> = indexed.iter().map(|o| o.read_long(oid)).collect();
+            assert_eq!(filtered, expected);
+            assert_eq!(indexed, expected);
+        };
+
+        check("STRASSE", vec![1]);
+        check("STRAẞE", vec![1]);
+        check("I", vec![2]);
+        check("ı", vec![3]);
+        check("i\u{307}", vec![4]);
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_string_between() {
+        fn test(case_sensitive: bool) {
+            isar!(isar, col => col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(case_sensitive))));
+            let property = col.get_properties().get(1).unwrap().1;
+            let mut txn = isar.begin_txn(true, false).unwrap();
+            let values = [
+                Some("a"),
+                Some("B"),
+                Some("c"),
+                Some("m"),
+                Some("Mz"),
+                Some("Z"),
+                None,
+            ];
+            for (i, value) in values.iter().enumerate() {
+                let mut builder = col.new_object_builder(None);
+                builder.write_long(i as i64 + 1);
+                builder.write_string(*value);
+                col.put(&mut txn, builder.finish()).unwrap();
+            }
+
+            let bounds = [None, Some("a"), Some("B"), Some("m"), Some("Z"), Some("zz")];
+            for lower in bounds.iter() {
+                for upper in bounds.iter() {
+                    for (include_lower, include_upper) in
+                        [(true, true), (true, false), (false, true), (false, false)].iter()
+                    {
+                        let filter = StringBetweenCond::filter(
+                            property,
+                            *lower,
+                            *include_lower,
+                            *upper,
+                            *include_upper,
+                            case_sensitive,
+                        )
+                        .unwrap();
+                        let mut qb = col.new_query_builder();
+                        qb.set_filter(filter);
+                        let mut filtered = qb.build().find_all_vec(&mut txn).unwrap();
+
+                        let mut lower_key = col.new_index_key(0).unwrap();
+                        lower_key.add_string_value(*lower, case_sensitive);
+                        let mut upper_key = col.new_index_key(0).unwrap();
+                        upper_key.add_string_value(*upper, case_sensitive);
+                        let mut qb = col.new_query_builder();
+                        qb.add_index_where_clause(
+                            lower_key,
+                            *include_lower,
+                            upper_key,
+                            *include_upper,
+                            false,
+                            Sort::Ascending,
+                        )
+                        .unwrap();
+                        let mut indexed = qb.build().find_all_vec(&mut txn).unwrap();
+
+                        let oid = col.get_oid_property();
+                        filtered.sort_by_key(|o| o.read_long(oid));
+                        indexed.sort_by_key(|o| o.read_long(oid));
+                        assert_eq!(filtered, indexed);
+                    }
+                }
+            }
+            txn.abort();
+            isar.close();
+        }
+
+        test(true);
+        test(false);
+    }
+}
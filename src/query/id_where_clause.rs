@@ -1,10 +1,13 @@
 use crate::lmdb::cursor::Cursor;
 use crate::lmdb::IntKey;
 use crate::object::isar_object::IsarObject;
+use crate::query::query_plan::WhereClausePlan;
 use crate::query::Sort;
 use crate::{collection::IsarCollection, error::Result};
 use hashbrown::HashSet;
 
+/// A range of object ids. Object ids are always `Long` values (the schema rejects other id
+/// types) so the range is scanned directly on the `IntKey`s of the data db.
 #[derive(Clone)]
 pub(crate) struct IdWhereClause {
     prefix: u16,
@@ -27,6 +30,45 @@ impl IdWhereClause {
         self.upper < self.lower
     }
 
+    pub(crate) fn reversed(&self) -> IdWhereClause {
+        IdWhereClause {
+            sort: self.sort.reverse(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn plan(&self) -> WhereClausePlan {
+        WhereClausePlan::IdRange {
+            lower: self.lower,
+            upper: self.upper,
+            sort: self.sort,
+        }
+    }
+
+    pub(crate) fn is_overlapping(&self, other: &IdWhereClause) -> bool {
+        self.lower <= other.upper && other.lower <= self.upper
+    }
+
+    /// Sweeps the where clauses ordered by their lower bound so only each clause and the one
+    /// reaching furthest so far need to be compared.
+    pub(crate) fn any_overlapping(mut where_clauses: Vec<&IdWhereClause>) -> bool {
+        where_clauses.sort_unstable_by_key(|wc| wc.lower);
+        let mut furthest: Option<&IdWhereClause> = None;
+        for wc in where_clauses {
+            if let Some(furthest) = furthest.as_mut() {
+                if furthest.is_overlapping(wc) {
+                    return true;
+                }
+                if wc.upper > furthest.upper {
+                    *furthest = wc;
+                }
+            } else {
+                furthest = Some(wc);
+            }
+        }
+        false
+    }
+
     pub(crate) fn id_matches(&self, oid: i64) -> bool {
         self.lower <= oid && self.upper >= oid
     }
@@ -42,7 +84,9 @@ impl IdWhereClause {
     {
         data.iter_between(
             IntKey::new(self.prefix, self.lower),
+            true,
             IntKey::new(self.prefix, self.upper),
+            true,
             false,
             self.sort == Sort::Ascending,
             |cursor, id, object| {
@@ -57,4 +101,31 @@ impl IdWhereClause {
             },
         )
     }
+
+    /// Returns the next object of the range that is not part of `result_ids` yet.
+    pub(crate) fn next<'txn>(
+        &self,
+        data: &mut Cursor<'txn>,
+        mut first: bool,
+        mut result_ids: Option<&mut HashSet<i64>>,
+    ) -> Result<Option<IsarObject<'txn>>> {
+        while let Some((id, object)) = data.next_between(
+            IntKey::new(self.prefix, self.lower),
+            true,
+            IntKey::new(self.prefix, self.upper),
+            true,
+            false,
+            self.sort == Sort::Ascending,
+            first,
+        )? {
+            first = false;
+            if let Some(result_ids) = result_ids.as_deref_mut() {
+                if !result_ids.insert(IntKey::from_bytes(id).get_id()) {
+                    continue;
+                }
+            }
+            return Ok(Some(IsarObject::from_bytes(object)));
+        }
+        Ok(None)
+    }
 }
@@ -3,25 +3,30 @@ use crate::index::index_key::IndexKey;
 use crate::index::Index;
 use crate::lmdb::cursor::Cursor;
 use crate::lmdb::{ByteKey, IntKey};
-use crate::object::isar_object::IsarObject;
+use crate::object::data_type::DataType;
+use crate::object::isar_object::{IsarObject, Property};
+use crate::query::query_plan::WhereClausePlan;
 use crate::query::Sort;
+use crate::schema::collection_schema::IndexType;
 use hashbrown::HashSet;
 
 #[derive(Clone)]
 pub(crate) struct IndexWhereClause {
     lower_key: Vec<u8>,
+    include_lower: bool,
     upper_key: Vec<u8>,
+    include_upper: bool,
     index: Index,
     skip_duplicates: bool,
     sort: Sort,
 }
 
 impl IndexWhereClause {
-    const PREFIX_LEN: usize = 2;
-
     pub(crate) fn new(
         lower: IndexKey,
+        include_lower: bool,
         upper: IndexKey,
+        include_upper: bool,
         skip_duplicates: bool,
         sort: Sort,
     ) -> Result<Self> {
@@ -31,7 +36,9 @@ impl IndexWhereClause {
         let index = lower.index.clone();
         Ok(IndexWhereClause {
             lower_key: lower.bytes,
+            include_lower,
             upper_key: upper.bytes,
+            include_upper,
             index,
             skip_duplicates,
             sort,
@@ -39,7 +46,83 @@ impl IndexWhereClause {
     }
 
     pub fn is_empty(&self) -> bool {
-        ByteKey::new(&self.lower_key) > ByteKey::new(&self.upper_key)
+        let lower = ByteKey::new(&self.lower_key);
+        let upper = ByteKey::new(&self.upper_key);
+        lower > upper || (lower == upper && !(self.include_lower && self.include_upper))
+    }
+
+    pub(crate) fn reversed(&self) -> IndexWhereClause {
+        IndexWhereClause {
+            sort: self.sort.reverse(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn plan(&self) -> WhereClausePlan {
+        WhereClausePlan::IndexRange {
+            index_id: self.index.id,
+            skip_duplicates: self.skip_duplicates,
+            sort: self.sort,
+        }
+    }
+
+    pub(crate) fn may_contain_duplicates(&self) -> bool {
+        self.index.multiple()
+    }
+
+    pub(crate) fn is_overlapping(&self, other: &IndexWhereClause) -> bool {
+        if self.index.id != other.index.id {
+            return true;
+        }
+        ByteKey::new(&self.lower_key) <= ByteKey::new(&other.upper_key)
+            && ByteKey::new(&other.lower_key) <= ByteKey::new(&self.upper_key)
+    }
+
+    pub(crate) fn any_overlapping(mut where_clauses: Vec<&IndexWhereClause>) -> bool {
+        where_clauses.sort_unstable_by(|wc1, wc2| {
+            wc1.index
+                .id
+                .cmp(&wc2.index.id)
+                .then_with(|| ByteKey::new(&wc1.lower_key).cmp(&ByteKey::new(&wc2.lower_key)))
+        });
+        let mut furthest: Option<&IndexWhereClause> = None;
+        for wc in where_clauses {
+            if let Some(furthest) = furthest.as_mut() {
+                if furthest.is_overlapping(wc) {
+                    return true;
+                }
+                if ByteKey::new(&wc.upper_key) > ByteKey::new(&furthest.upper_key) {
+                    *furthest = wc;
+                }
+            } else {
+                furthest = Some(wc);
+            }
+        }
+        false
+    }
+
+    /// Returns whether the objects are returned in the order of `sort` because the sort
+    /// properties are a prefix of the index properties.
+    pub(crate) fn is_sorted_by(&self, sort: &[(Property, Sort, bool)]) -> bool {
+        if sort.len() > self.index.properties.len() {
+            return false;
+        }
+        let wc_ascending = self.sort == Sort::Ascending;
+        sort.iter()
+            .zip(&self.index.properties)
+            .all(|((property, sort, case_sensitive), ip)| {
+                let data_type = property.data_type;
+                // list indexes contain an entry for each element and long strings are ordered by
+                // the hash after their prefix unless the index rejects them
+                let same_order = data_type.is_static()
+                    || (data_type == DataType::String
+                        && ip.case_sensitive == Some(*case_sensitive)
+                        && self.index.reject_long_strings);
+                ip.property == *property
+                    && ip.index_type == IndexType::Value
+                    && same_order
+                    && (wc_ascending != ip.descending) == (*sort == Sort::Ascending)
+            })
     }
 
     pub(crate) fn object_matches(&self, object: IsarObject) -> bool {
@@ -47,8 +130,10 @@ impl IndexWhereClause {
         self.index
             .create_keys(object, |key| {
                 let key = ByteKey::new(key);
-                key_matches =
-                    key >= ByteKey::new(&self.lower_key) && key <= ByteKey::new(&self.upper_key);
+                let lower = ByteKey::new(&self.lower_key);
+                let upper = ByteKey::new(&self.upper_key);
+                key_matches = (key > lower || (self.include_lower && key == lower))
+                    && (key < upper || (self.include_upper && key == upper));
                 Ok(!key_matches)
             })
             .unwrap();
@@ -65,7 +150,9 @@ impl IndexWhereClause {
     {
         index.iter_between(
             ByteKey::new(&self.lower_key),
+            self.include_lower,
             ByteKey::new(&self.upper_key),
+            self.include_upper,
             self.skip_duplicates,
             self.sort == Sort::Ascending,
             |cursor, _, id| {
@@ -102,34 +189,38 @@ impl IndexWhereClause {
         })
     }
 
-    pub(crate) fn try_exclude(&mut self, include_lower: bool, include_upper: bool) -> bool {
-        if !include_lower {
-            let mut increased = false;
-            for i in (Self::PREFIX_LEN..self.lower_key.len()).rev() {
-                if let Some(added) = self.lower_key[i].checked_add(1) {
-                    self.lower_key[i] = added;
-                    increased = true;
-                    break;
-                }
-            }
-            if !increased {
-                return false;
-            }
-        }
-        if !include_upper {
-            let mut decreased = false;
-            for i in (Self::PREFIX_LEN..self.upper_key.len()).rev() {
-                if let Some(subtracted) = self.upper_key[i].checked_sub(1) {
-                    self.upper_key[i] = subtracted;
-                    decreased = true;
-                    break;
+    /// Returns the next object of the index range that is not part of `result_ids` yet.
+    pub(crate) fn next<'txn>(
+        &self,
+        data: &mut Cursor<'txn>,
+        index: &mut Cursor<'txn>,
+        mut first: bool,
+        mut result_ids: Option<&mut HashSet<i64>>,
+    ) -> Result<Option<IsarObject<'txn>>> {
+        while let Some((_, id)) = index.next_between(
+            ByteKey::new(&self.lower_key),
+            self.include_lower,
+            ByteKey::new(&self.upper_key),
+            self.include_upper,
+            self.skip_duplicates,
+            self.sort == Sort::Ascending,
+            first,
+        )? {
+            first = false;
+            let id = IntKey::from_bytes(id);
+            if let Some(result_ids) = result_ids.as_deref_mut() {
+                if !result_ids.insert(id.get_id()) {
+                    continue;
                 }
             }
-            if !decreased {
-                return false;
-            }
+
+            let entry = data.move_to(id)?;
+            let (_, object) = entry.ok_or(IsarError::DbCorrupted {
+                message: "Could not find object specified in index.".to_string(),
+            })?;
+            return Ok(Some(IsarObject::from_bytes(object)));
         }
-        true
+        Ok(None)
     }
 }
 
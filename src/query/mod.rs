@@ -1,45 +1,95 @@
 use std::cmp::Ordering;
 use std::hash::Hasher;
+use std::io::Write;
+use std::thread;
 
 use hashbrown::HashSet;
 use serde_json::{json, Value};
-use wyhash::WyHash;
+use wyhash::{wyhash, WyHash};
 
 use crate::collection::IsarCollection;
-use crate::error::Result;
-use crate::object::isar_object::{IsarObject, Property};
+use crate::error::{IsarError, Result};
+use crate::instance::IsarInstance;
+use crate::lmdb::IntKey;
+use crate::object::csv_encode::CsvEncode;
+use crate::object::from_isar_object::FromIsarObject;
+use crate::object::isar_object::{IsarObject, IsarValue, Property};
 use crate::object::json_encode_decode::JsonEncodeDecode;
+use crate::query::external_sort::{SortRun, SortSpill};
 use crate::query::filter::{Condition, Filter, StaticCond};
+use crate::query::id_where_clause::IdWhereClause;
+use crate::query::index_where_clause::IndexWhereClause;
+use crate::query::query_iter::QueryIter;
+use crate::query::query_plan::{DistinctPlan, QueryPlan};
 use crate::query::where_clause::WhereClause;
 use crate::txn::{Cursors, IsarTxn};
 
+pub mod aggregation;
+mod external_sort;
 mod fast_wild_match;
 pub mod filter;
 pub mod id_where_clause;
 pub mod index_where_clause;
 pub mod query_builder;
+pub mod query_iter;
+pub mod query_plan;
 mod where_clause;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Sort {
     Ascending,
     Descending,
 }
 
+impl Sort {
+    pub fn reverse(self) -> Sort {
+        match self {
+            Sort::Ascending => Sort::Descending,
+            Sort::Descending => Sort::Ascending,
+        }
+    }
+}
+
+/// Which object of a group with equal distinct properties is part of the result.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DistinctKeep {
+    First,
+    Last,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Case {
     Sensitive,
     Insensitive,
 }
 
+#[derive(Default)]
+struct DistinctKey(Vec<u8>);
+
+impl Hasher for DistinctKey {
+    fn finish(&self) -> u64 {
+        wyhash(&self.0, 0)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+}
+
 #[derive(Clone)]
 pub struct Query {
     where_clauses: Vec<WhereClause>,
     where_clauses_overlapping: bool,
     filter: Option<Filter>,
-    sort: Vec<(Property, Sort)>,
-    distinct: Vec<(Property, bool)>,
+    sort_by_index: bool,
+    sort: Vec<(Property, Sort, bool)>,
+    distinct: Vec<(Property, bool, bool)>,
+    distinct_keep: DistinctKeep,
     offset: usize,
     limit: usize,
+    stable_sort: Option<(Property, Sort)>,
+    sort_spill: Option<SortSpill>,
+    oid_property: Property,
 }
 
 impl<'txn> Query {
@@ -47,19 +97,100 @@ impl<'txn> Query {
     pub(crate) fn new(
         where_clauses: Vec<WhereClause>,
         filter: Option<Filter>,
-        sort: Vec<(Property, Sort)>,
-        distinct: Vec<(Property, bool)>,
+        sort: Vec<(Property, Sort, bool)>,
+        distinct: Vec<(Property, bool, bool)>,
+        distinct_keep: DistinctKeep,
         offset: usize,
         limit: usize,
+        stable_sort: Option<(Property, Sort)>,
+        sort_spill: Option<SortSpill>,
+        oid_property: Property,
     ) -> Self {
+        let where_clauses_overlapping = Self::check_where_clauses_overlapping(&where_clauses);
+        let sort_by_index = Self::check_sort_by_index(&where_clauses, &sort, stable_sort);
         Query {
             where_clauses,
-            where_clauses_overlapping: true,
+            where_clauses_overlapping,
             filter,
+            sort_by_index,
             sort,
             distinct,
+            distinct_keep,
             offset,
             limit,
+            stable_sort,
+            sort_spill,
+            oid_property,
+        }
+    }
+
+    /// Returns a copy of the query that returns the results in reverse order. The where clauses
+    /// are scanned in reverse order and direction and all sorts are reversed, so offset and
+    /// limit apply to the end of the original results. If the where clauses overlap or skip
+    /// duplicates, the first of multiple equal objects is kept instead of the last one.
+    pub fn reversed(&self) -> Query {
+        let where_clauses = self
+            .where_clauses
+            .iter()
+            .rev()
+            .map(|wc| wc.reversed())
+            .collect();
+        let sort = self
+            .sort
+            .iter()
+            .map(|(p, sort, case_sensitive)| (*p, sort.reverse(), *case_sensitive))
+            .collect();
+        let distinct_keep = match self.distinct_keep {
+            DistinctKeep::First => DistinctKeep::Last,
+            DistinctKeep::Last => DistinctKeep::First,
+        };
+        Query::new(
+            where_clauses,
+            self.filter.clone(),
+            sort,
+            self.distinct.clone(),
+            distinct_keep,
+            self.offset,
+            self.limit,
+            self.stable_sort.map(|(p, sort)| (p, sort.reverse())),
+            self.sort_spill,
+            self.oid_property,
+        )
+    }
+
+    fn check_where_clauses_overlapping(where_clauses: &[WhereClause]) -> bool {
+        let mut id_wcs = vec![];
+        let mut index_wcs = vec![];
+        for wc in where_clauses {
+            if wc.may_contain_duplicates() {
+                return true;
+            }
+            match wc {
+                WhereClause::Id(wc) => id_wcs.push(wc),
+                WhereClause::Index(wc) => index_wcs.push(wc),
+            }
+        }
+        // id and index where clauses can always return the same object
+        if !id_wcs.is_empty() && !index_wcs.is_empty() {
+            return true;
+        }
+        IdWhereClause::any_overlapping(id_wcs) || IndexWhereClause::any_overlapping(index_wcs)
+    }
+
+    /// A single index where clause already returns the objects sorted if the sort properties
+    /// are a prefix of the index. The order of equal objects is not defined by the index so
+    /// stable sorts are always sorted.
+    fn check_sort_by_index(
+        where_clauses: &[WhereClause],
+        sort: &[(Property, Sort, bool)],
+        stable_sort: Option<(Property, Sort)>,
+    ) -> bool {
+        if sort.is_empty() || stable_sort.is_some() {
+            return false;
+        }
+        match where_clauses {
+            [WhereClause::Index(wc)] => wc.is_sorted_by(sort),
+            _ => false,
         }
     }
 
@@ -97,9 +228,28 @@ impl<'txn> Query {
     where
         F: FnMut(IsarObject<'txn>) -> Result<bool>,
     {
-        if !self.distinct.is_empty() {
-            let callback = self.add_distinct_unsorted(callback);
+        if self.limit == 0 {
+            return Ok(());
+        }
+        if !self.distinct.is_empty() && self.distinct_keep == DistinctKeep::Last {
+            // the last object of a group is only known after the scan, so all matching objects
+            // are buffered
+            let mut results = vec![];
+            self.execute_raw(cursors, |object| {
+                results.push(object);
+                Ok(true)
+            })?;
+            let mut callback = self.add_offset_limit_unsorted(callback);
+            for object in self.add_distinct_sorted(results, |o| *o) {
+                if !callback(object)? {
+                    break;
+                }
+            }
+            Ok(())
+        } else if !self.distinct.is_empty() {
+            // offset and limit refer to the distinct results
             let callback = self.add_offset_limit_unsorted(callback);
+            let callback = self.add_distinct_unsorted(callback);
             self.execute_raw(cursors, callback)
         } else {
             let callback = self.add_offset_limit_unsorted(callback);
@@ -115,14 +265,13 @@ impl<'txn> Query {
         F: FnMut(IsarObject<'txn>) -> Result<bool>,
     {
         let properties = self.distinct.clone();
-        let mut hashes = HashSet::new();
+        let mut keys = HashSet::new();
         move |object| {
-            let mut hasher = WyHash::default();
-            for (property, case_sensitive) in &properties {
-                object.hash_property(*property, *case_sensitive, &mut hasher);
-            }
-            let hash = hasher.finish();
-            if hashes.insert(hash) {
+            let is_distinct = match Self::distinct_key(&properties, object) {
+                Some(key) => keys.insert(key),
+                None => true,
+            };
+            if is_distinct {
                 callback(object)
             } else {
                 Ok(true)
@@ -138,60 +287,127 @@ impl<'txn> Query {
         F: FnMut(IsarObject<'txn>) -> Result<bool>,
     {
         let offset = self.offset;
-        let max_count = self.limit.saturating_add(offset);
-        let mut count = 0;
+        let limit = self.limit;
+        let mut skipped = 0;
+        let mut taken = 0;
         move |value| {
-            count += 1;
-            if count > max_count || (count > offset && !callback(value)?) {
-                Ok(false)
-            } else {
-                Ok(true)
+            if skipped < offset {
+                skipped += 1;
+                return Ok(true);
             }
+            taken += 1;
+            // stop right after the last result instead of reading one more object
+            Ok(taken <= limit && callback(value)? && taken < limit)
         }
     }
 
-    fn execute_sorted(&self, cursors: &mut Cursors<'txn>) -> Result<Vec<IsarObject<'txn>>> {
+    /// If the objects use more memory than allowed, sorted runs are written to `runs` and no
+    /// objects are returned.
+    fn execute_sorted(
+        &self,
+        cursors: &mut Cursors<'txn>,
+        runs: &mut Vec<SortRun>,
+    ) -> Result<Vec<IsarObject<'txn>>> {
         let mut results = vec![];
+        let mut memory = 0;
         self.execute_raw(cursors, |object| {
             results.push(object);
+            if let Some(spill) = self.sort_spill {
+                memory += object.as_bytes().len() + std::mem::size_of::<IsarObject>();
+                if memory > spill.max_memory {
+                    self.sort_objects(&mut results, |o| *o);
+                    runs.push(SortRun::write(&results)?);
+                    results.clear();
+                    memory = 0;
+                }
+            }
             Ok(true)
         })?;
 
-        results.sort_unstable_by(|o1, o2| {
-            for (p, sort) in &self.sort {
-                let ord = o1.compare_property(o2, *p);
-                if ord != Ordering::Equal {
-                    return if *sort == Sort::Ascending {
-                        ord
-                    } else {
-                        ord.reverse()
-                    };
-                }
+        self.sort_objects(&mut results, |o| *o);
+        if !runs.is_empty() {
+            if !results.is_empty() {
+                runs.push(SortRun::write(&results)?);
             }
-            Ordering::Equal
-        });
+            return Ok(vec![]);
+        }
 
         if !self.distinct.is_empty() {
-            Ok(self.add_distinct_sorted(results))
+            Ok(self.add_distinct_sorted(results, |o| *o))
         } else {
             Ok(results)
         }
     }
 
-    fn add_distinct_sorted(&self, results: Vec<IsarObject<'txn>>) -> Vec<IsarObject<'txn>> {
-        let properties = self.distinct.clone();
-        let mut hashes = HashSet::new();
-        results
+    fn sort_objects<T, F>(&self, results: &mut [T], object: F)
+    where
+        F: Fn(&T) -> IsarObject,
+    {
+        let compare = |o1: &T, o2: &T| self.compare_sorted(&object(o1), &object(o2));
+        if self.stable_sort.is_some() {
+            results.sort_by(compare);
+        } else {
+            results.sort_unstable_by(compare);
+        }
+    }
+
+    fn compare_sorted(&self, o1: &IsarObject, o2: &IsarObject) -> Ordering {
+        for (p, sort, case_sensitive) in &self.sort {
+            let ord = o1.compare_property(o2, *p, *case_sensitive);
+            if ord != Ordering::Equal {
+                return if *sort == Sort::Ascending {
+                    ord
+                } else {
+                    ord.reverse()
+                };
+            }
+        }
+        if let Some((oid_property, sort)) = self.stable_sort {
+            let ord = o1.read_long(oid_property).cmp(&o2.read_long(oid_property));
+            if sort == Sort::Ascending {
+                ord
+            } else {
+                ord.reverse()
+            }
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    fn add_distinct_sorted<T, F>(&self, mut results: Vec<T>, object: F) -> Vec<T>
+    where
+        F: Fn(&T) -> IsarObject,
+    {
+        let keep_last = self.distinct_keep == DistinctKeep::Last;
+        if keep_last {
+            results.reverse();
+        }
+        let mut keys = HashSet::new();
+        let mut results: Vec<T> = results
             .into_iter()
-            .filter(|object| {
-                let mut hasher = WyHash::default();
-                for (property, case_sensitive) in &properties {
-                    object.hash_property(*property, *case_sensitive, &mut hasher);
-                }
-                let hash = hasher.finish();
-                hashes.insert(hash)
+            .filter(|o| match Self::distinct_key(&self.distinct, object(o)) {
+                Some(key) => keys.insert(key),
+                None => true,
             })
-            .collect()
+            .collect();
+        if keep_last {
+            results.reverse();
+        }
+        results
+    }
+
+    /// The key contains the raw property values so different values never collide.
+    fn distinct_key(properties: &[(Property, bool, bool)], object: IsarObject) -> Option<Vec<u8>> {
+        let mut key = DistinctKey::default();
+        for (property, case_sensitive, nulls_distinct) in properties {
+            let is_null = object.is_null(*property);
+            if *nulls_distinct && is_null {
+                return None;
+            }
+            key.write_u8(is_null as u8);
+            object.hash_property(*property, *case_sensitive, &mut key);
+        }
+        Some(key.0)
     }
 
     fn add_offset_limit_sorted(
@@ -216,6 +432,38 @@ impl<'txn> Query {
         }
     }
 
+    pub fn explain(&self) -> QueryPlan {
+        let materialize_for_sort = !self.sort.is_empty() && !self.sort_by_index;
+        let distinct = if self.distinct.is_empty() {
+            DistinctPlan::None
+        } else if materialize_for_sort {
+            DistinctPlan::AfterSort
+        } else if self.distinct_keep == DistinctKeep::Last {
+            DistinctPlan::AfterScan
+        } else {
+            DistinctPlan::WhileScanning
+        };
+        QueryPlan {
+            where_clauses: self.where_clauses.iter().map(|wc| wc.plan()).collect(),
+            deduplicate_ids: self.where_clauses_overlapping,
+            filter: self.filter.is_some(),
+            materialize_for_sort,
+            sort_by_index: self.sort_by_index,
+            distinct,
+            offset: self.offset,
+            limit: if self.limit == usize::MAX {
+                None
+            } else {
+                Some(self.limit)
+            },
+        }
+    }
+
+    /// Sorting can only be skipped if it does not change which objects are part of the result.
+    pub(crate) fn can_skip_sorting(&self) -> bool {
+        self.offset == 0 && self.limit == usize::MAX && self.distinct.is_empty()
+    }
+
     pub(crate) fn find_all_internal<F>(
         &self,
         cursors: &mut Cursors<'txn>,
@@ -225,10 +473,14 @@ impl<'txn> Query {
     where
         F: FnMut(IsarObject<'txn>) -> Result<bool>,
     {
-        if self.sort.is_empty() || skip_sorting {
+        if self.sort.is_empty() || self.sort_by_index || skip_sorting {
             self.execute_unsorted(cursors, callback)?;
         } else {
-            let results = self.execute_sorted(cursors)?;
+            let mut runs = vec![];
+            let results = self.execute_sorted(cursors, &mut runs)?;
+            if !runs.is_empty() {
+                return self.merge_sorted_runs(cursors, runs, callback);
+            }
             let results_iter = self.add_offset_limit_sorted(results);
             for object in results_iter {
                 if !callback(object)? {
@@ -239,6 +491,52 @@ impl<'txn> Query {
         Ok(())
     }
 
+    fn merge_sorted_runs<F>(
+        &self,
+        cursors: &mut Cursors<'txn>,
+        runs: Vec<SortRun>,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(IsarObject<'txn>) -> Result<bool>,
+    {
+        let spill = self.sort_spill.unwrap();
+        let data = &mut cursors.data;
+        let mut read_object = |copy: IsarObject| -> Result<IsarObject<'txn>> {
+            let oid = copy.read_long(spill.oid_property);
+            let (_, bytes) =
+                data.move_to(IntKey::new(spill.col_id, oid))?
+                    .ok_or(IsarError::DbCorrupted {
+                        message: "Sorted object does not exist anymore.".to_string(),
+                    })?;
+            Ok(IsarObject::from_bytes(bytes))
+        };
+        let compare = |o1: &IsarObject, o2: &IsarObject| self.compare_sorted(o1, o2);
+
+        if !self.distinct.is_empty() && self.distinct_keep == DistinctKeep::Last {
+            // the last object of a group is only known after merging
+            let mut results = vec![];
+            SortRun::merge(runs, compare, |copy| {
+                results.push(read_object(copy)?);
+                Ok(true)
+            })?;
+            let results = self.add_distinct_sorted(results, |o| *o);
+            for object in self.add_offset_limit_sorted(results) {
+                if !callback(object)? {
+                    break;
+                }
+            }
+            Ok(())
+        } else if !self.distinct.is_empty() {
+            let callback = self.add_offset_limit_unsorted(callback);
+            let mut callback = self.add_distinct_unsorted(callback);
+            SortRun::merge(runs, compare, |copy| callback(read_object(copy)?))
+        } else {
+            let mut callback = self.add_offset_limit_unsorted(callback);
+            SortRun::merge(runs, compare, |copy| callback(read_object(copy)?))
+        }
+    }
+
     pub fn find_while<F>(&self, txn: &mut IsarTxn<'txn>, mut callback: F) -> Result<()>
     where
         F: FnMut(IsarObject<'txn>) -> bool,
@@ -255,6 +553,212 @@ impl<'txn> Query {
         Ok(results)
     }
 
+    /// Returns only the values of `properties` for each result. Sort, distinct, offset and limit
+    /// are applied to the whole objects.
+    pub fn find_projected(
+        &self,
+        txn: &mut IsarTxn<'txn>,
+        properties: &[Property],
+    ) -> Result<Vec<Vec<IsarValue<'txn>>>> {
+        let mut results = vec![];
+        self.find_while(txn, |object| {
+            let values = properties.iter().map(|p| object.read_value(*p)).collect();
+            results.push(values);
+            true
+        })?;
+        Ok(results)
+    }
+
+    pub fn find_all_typed<T: FromIsarObject<'txn>>(
+        &self,
+        txn: &mut IsarTxn<'txn>,
+    ) -> Result<Vec<T>> {
+        let mut results = vec![];
+        txn.read(|cursors| {
+            self.find_all_internal(cursors, false, |object| {
+                results.push(T::from_isar_object(object)?);
+                Ok(true)
+            })
+        })?;
+        Ok(results)
+    }
+
+    /// Returns an iterator over the results. If the results have to be sorted or the last object
+    /// of each distinct group is kept, all results are collected first. Otherwise they are read
+    /// lazily from the where clauses.
+    pub fn iter<'a>(&'a self, txn: &'a mut IsarTxn<'txn>) -> Result<QueryIter<'a, 'txn>> {
+        let sorted = !self.sort.is_empty() && !self.sort_by_index;
+        if sorted || (!self.distinct.is_empty() && self.distinct_keep == DistinctKeep::Last) {
+            let objects = self.find_all_vec(txn)?;
+            Ok(QueryIter::buffered(objects))
+        } else {
+            Ok(QueryIter::streaming(self, txn.cursors()))
+        }
+    }
+
+    /// Scans chunks of the where clauses on up to `threads` workers, each with its own read
+    /// transaction. Sort, distinct, offset and limit are applied after merging, so the result
+    /// matches `find_all_vec`. Overlapping where clauses are scanned by a single worker.
+    pub fn find_all_parallel(&self, isar: &IsarInstance, threads: usize) -> Result<Vec<Vec<u8>>> {
+        let workers = if self.where_clauses_overlapping {
+            1
+        } else {
+            threads.clamp(1, self.where_clauses.len().max(1))
+        };
+        let chunk_size = ((self.where_clauses.len() + workers - 1) / workers).max(1);
+        let chunks = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .where_clauses
+                .chunks(chunk_size)
+                .map(|where_clauses| scope.spawn(move || self.scan_bytes(isar, where_clauses)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        let mut results: Vec<Vec<u8>> = chunks.into_iter().flatten().collect();
+        if !self.sort.is_empty() {
+            self.sort_objects(&mut results, |bytes| IsarObject::from_bytes(bytes));
+        }
+        if !self.distinct.is_empty() {
+            results = self.add_distinct_sorted(results, |bytes| IsarObject::from_bytes(bytes));
+        }
+        let results = results
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect();
+        Ok(results)
+    }
+
+    fn scan_bytes(
+        &self,
+        isar: &IsarInstance,
+        where_clauses: &[WhereClause],
+    ) -> Result<Vec<Vec<u8>>> {
+        let mut txn = isar.begin_txn(false, true)?;
+        let mut result_ids = if self.where_clauses_overlapping {
+            Some(HashSet::<i64>::new())
+        } else {
+            None
+        };
+        let static_filter = StaticCond::filter(true);
+        let filter = self.filter.as_ref().unwrap_or(&static_filter);
+
+        let mut results = vec![];
+        txn.read(|cursors| {
+            for where_clause in where_clauses {
+                where_clause.iter(cursors, result_ids.as_mut(), |filter_cursors, object| {
+                    if filter.evaluate(object, Some(filter_cursors))? {
+                        results.push(object.as_bytes().to_vec());
+                    }
+                    Ok(true)
+                })?;
+            }
+            Ok(())
+        })?;
+        txn.abort();
+        Ok(results)
+    }
+
+    pub fn find_first(&self, txn: &mut IsarTxn<'txn>) -> Result<Option<IsarObject<'txn>>> {
+        if self.limit == 0 {
+            return Ok(None);
+        }
+        if self.sort.is_empty() || self.sort_by_index || !self.distinct.is_empty() {
+            let mut first = None;
+            self.find_while(txn, |object| {
+                first = Some(object);
+                false
+            })?;
+            return Ok(first);
+        }
+
+        // only the object at the offset position needs to be in its sorted place
+        txn.read(|cursors| {
+            let mut results = vec![];
+            self.execute_raw(cursors, |object| {
+                results.push(object);
+                Ok(true)
+            })?;
+            if results.len() > self.offset {
+                let (_, first, _) = results
+                    .select_nth_unstable_by(self.offset, |o1, o2| self.compare_sorted(o1, o2));
+                Ok(Some(*first))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Returns the ids of the results. Queries without filter, distinct and sorting (or sorted by
+    /// the index) read the ids directly from the where clauses without looking up the objects.
+    pub fn find_ids(&self, txn: &mut IsarTxn) -> Result<Vec<i64>> {
+        let mut ids = vec![];
+        if self.limit == 0 {
+            return Ok(ids);
+        }
+        let ids_only = self.filter.is_none()
+            && self.distinct.is_empty()
+            && (self.sort.is_empty() || self.sort_by_index);
+        txn.read(|cursors| {
+            if ids_only {
+                let mut result_ids = if self.where_clauses_overlapping {
+                    Some(HashSet::<i64>::new())
+                } else {
+                    None
+                };
+                let mut skipped = 0;
+                for where_clause in &self.where_clauses {
+                    let result = where_clause.iter_ids(cursors, result_ids.as_mut(), |id| {
+                        if skipped < self.offset {
+                            skipped += 1;
+                        } else {
+                            ids.push(id);
+                        }
+                        Ok(ids.len() < self.limit)
+                    })?;
+                    if !result {
+                        break;
+                    }
+                }
+                Ok(())
+            } else {
+                self.find_all_internal(cursors, false, |object| {
+                    ids.push(object.read_long(self.oid_property));
+                    Ok(true)
+                })
+            }
+        })?;
+        Ok(ids)
+    }
+
+    /// Returns a hash of the result that changes if any result object changes. Sorted queries
+    /// hash the objects in order so the hash also changes if the order changes. Without sort,
+    /// the object hashes are combined independent of their order.
+    pub fn result_hash(&self, txn: &mut IsarTxn) -> Result<u64> {
+        let sorted = !self.sort.is_empty();
+        let mut hasher = WyHash::default();
+        let mut combined = 0;
+        self.find_while(txn, |object| {
+            let bytes = object.as_bytes();
+            if sorted {
+                hasher.write_usize(bytes.len());
+                hasher.write(bytes);
+            } else {
+                combined ^= wyhash(bytes, 0);
+            }
+            true
+        })?;
+        if sorted {
+            Ok(hasher.finish())
+        } else {
+            Ok(combined)
+        }
+    }
+
     pub fn count(&self, txn: &mut IsarTxn) -> Result<u32> {
         let mut counter = 0;
         self.find_while(txn, |_| {
@@ -264,6 +768,42 @@ impl<'txn> Query {
         Ok(counter)
     }
 
+    /// Counts the results but stops scanning once `max` results were found. The offset and limit
+    /// of the query are applied before counting.
+    pub fn count_up_to(&self, txn: &mut IsarTxn, max: u32) -> Result<u32> {
+        if max == 0 {
+            return Ok(0);
+        }
+        let mut counter = 0;
+        txn.read(|cursors| {
+            self.find_all_internal(cursors, self.can_skip_sorting(), |_| {
+                counter += 1;
+                Ok(counter < max)
+            })
+        })?;
+        Ok(counter)
+    }
+
+    pub fn delete_all(&self, txn: &mut IsarTxn, collection: &IsarCollection) -> Result<u32> {
+        let oid_property = collection.get_oid_property();
+        let mut oids = vec![];
+        txn.read(|cursors| {
+            self.find_all_internal(cursors, self.can_skip_sorting(), |object| {
+                oids.push(object.read_long(oid_property));
+                Ok(true)
+            })
+        })?;
+        let mut count = 0;
+        for oid in oids {
+            if collection.delete(txn, oid)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// `byte_as_bool` encodes `Byte` properties as booleans. `Bool` properties are always encoded
+    /// as `true`, `false` or `null`.
     pub fn export_json(
         &self,
         txn: &mut IsarTxn,
@@ -279,15 +819,65 @@ impl<'txn> Query {
         })?;
         Ok(json!(items))
     }
+
+    /// Writes the results as a JSON array without collecting them first.
+    pub fn export_json_to<W: Write>(
+        &self,
+        txn: &mut IsarTxn,
+        collection: &IsarCollection,
+        mut writer: W,
+        primitive_null: bool,
+        byte_as_bool: bool,
+    ) -> Result<()> {
+        writer.write_all(b"[")?;
+        let mut first = true;
+        txn.read(|cursors| {
+            self.find_all_internal(cursors, false, |object| {
+                if !first {
+                    writer.write_all(b",")?;
+                }
+                first = false;
+                let json =
+                    JsonEncodeDecode::encode(collection, object, primitive_null, byte_as_bool);
+                serde_json::to_writer(&mut writer, &json)?;
+                Ok(true)
+            })
+        })?;
+        writer.write_all(b"]")?;
+        Ok(())
+    }
+
+    /// Writes a header row with the property names and one row per result. Lists are encoded
+    /// as JSON and null values are written as empty cells.
+    pub fn export_csv<W: Write>(
+        &self,
+        txn: &mut IsarTxn,
+        collection: &IsarCollection,
+        mut writer: W,
+        properties: &[Property],
+    ) -> Result<()> {
+        CsvEncode::write_header(collection, properties, &mut writer)?;
+        txn.read(|cursors| {
+            self.find_all_internal(cursors, false, |object| {
+                CsvEncode::write_object(object, properties, &mut writer)?;
+                Ok(true)
+            })
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
-    use crate::instance::IsarInstance;
+    use crate::lmdb::{MAX_ID, MIN_ID};
     use crate::object::data_type::DataType;
-    use crate::query::filter::{IntBetweenCond, NotCond, OrCond};
+    use crate::query::filter::{
+        AndCond, DoubleBetweenCond, IdBetweenCond, IntBetweenCond, NotCond, OrCond,
+    };
+    use crate::query::query_builder::{QueryBuilder, WherePlan};
+    use crate::query::query_plan::WhereClausePlan;
+    use crate::schema::collection_schema::IndexType;
     use crate::{col, ind, isar, set};
 
     use super::*;
@@ -339,18 +929,50 @@ mod tests {
         Ok(())
     }
 
+    #[derive(Debug, PartialEq)]
+    struct IntRow {
+        id: i64,
+        field: Option<i32>,
+    }
+
+    impl<'a> FromIsarObject<'a> for IntRow {
+        fn from_isar_object(object: IsarObject<'a>) -> Result<Self> {
+            let id_property = Property::new(DataType::Long, 2);
+            let field_property = Property::new(DataType::Int, 10);
+            let field = if object.is_null(field_property) {
+                None
+            } else {
+                Some(object.read_int(field_property))
+            };
+            Ok(IntRow {
+                id: object.read_long(id_property),
+                field,
+            })
+        }
+    }
+
     #[test]
-    fn test_single_primary_where_clause() -> Result<()> {
-        let isar = fill_int_col(vec![1, 2, 3, 4, 5], true);
+    fn test_find_all_typed() -> Result<()> {
+        let isar = fill_int_col(vec![5, IsarObject::NULL_INT, 7], true);
         let col = isar.get_collection(0).unwrap();
         let mut txn = isar.begin_txn(false, false)?;
 
-        let wc = col
-            .new_id_where_clause(Some(2), Some(4), Sort::Ascending)
-            .unwrap();
-        let mut qb = col.new_query_builder();
-        qb.add_id_where_clause(wc)?;
-        assert_eq!(find(&mut txn, qb.build()), vec![(2, 2), (3, 3), (4, 4)]);
+        let q = col.new_query_builder().build();
+        let rows: Vec<IntRow> = q.find_all_typed(&mut txn)?;
+        assert_eq!(
+            rows,
+            vec![
+                IntRow {
+                    id: 1,
+                    field: Some(5)
+                },
+                IntRow { id: 2, field: None },
+                IntRow {
+                    id: 3,
+                    field: Some(7)
+                },
+            ]
+        );
 
         txn.abort();
         isar.close();
@@ -358,17 +980,29 @@ mod tests {
     }
 
     #[test]
-    fn test_single_secondary_where_clause() -> Result<()> {
-        let isar = fill_int_col(vec![1, 2, 3, 4], true);
+    fn test_where_optimized() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3, 2, 1], false);
         let col = isar.get_collection(0).unwrap();
         let mut txn = isar.begin_txn(false, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
 
-        let mut wc = col
-            .new_index_where_clause(0, false, Sort::Ascending)
-            .unwrap();
-        wc.add_int(2, 3).unwrap();
         let mut qb = col.new_query_builder();
-        qb.add_index_where_clause(wc, true, true)?;
+        let plan = qb.where_optimized(IntBetweenCond::filter(field, 2, 2)?)?;
+        assert_eq!(plan, WherePlan::IndexScan { index_index: 0 });
+        assert_eq!(find(&mut txn, qb.build()), vec![(2, 2), (4, 2)]);
+
+        let mut qb = col.new_query_builder();
+        let filter = AndCond::filter(vec![
+            IdBetweenCond::filter(col, 3, 5),
+            IntBetweenCond::filter(field, 1, 2)?,
+        ]);
+        let plan = qb.where_optimized(filter)?;
+        assert_eq!(plan, WherePlan::IndexScan { index_index: 0 });
+        assert_eq!(find(&mut txn, qb.build()), vec![(5, 1), (4, 2)]);
+
+        let mut qb = col.new_query_builder();
+        let plan = qb.where_optimized(IdBetweenCond::filter(col, 2, 3))?;
+        assert_eq!(plan, WherePlan::FilterOnly);
         assert_eq!(find(&mut txn, qb.build()), vec![(2, 2), (3, 3)]);
 
         txn.abort();
@@ -377,17 +1011,853 @@ mod tests {
     }
 
     #[test]
-    fn test_single_secondary_where_clause_dup() -> Result<()> {
-        let isar = fill_int_col(vec![1, 2, 2, 3, 3, 3, 4], false);
+    fn test_find_all_parallel() -> Result<()> {
+        let isar = fill_int_col((0..100).map(|i| i % 7).collect(), false);
         let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
         let mut txn = isar.begin_txn(false, false)?;
 
-        let mut wc = col
-            .new_index_where_clause(0, false, Sort::Ascending)
-            .unwrap();
-        wc.add_int(2, 3).unwrap();
-        let mut qb = col.new_query_builder();
-        qb.add_index_where_clause(wc, true, true)?;
+        let build = |overlapping: bool| -> Result<Query> {
+            let mut qb = col.new_query_builder();
+            for i in 0..10 {
+                qb.add_id_where_clause(i * 10 + 1, i * 10 + 10, Sort::Ascending)?;
+            }
+            if overlapping {
+                qb.add_id_where_clause(5, 15, Sort::Ascending)?;
+            }
+            qb.set_filter(IntBetweenCond::filter(field, 1, 5)?);
+            qb.add_sort(field, Sort::Descending, Case::Sensitive);
+            qb.add_distinct(field, false, false);
+            qb.set_offset(1);
+            Ok(qb.build())
+        };
+
+        for overlapping in [false, true] {
+            let q = build(overlapping)?;
+            let values = |objects: Vec<Vec<u8>>| -> Vec<i32> {
+                objects
+                    .iter()
+                    .map(|bytes| IsarObject::from_bytes(bytes).read_int(field))
+                    .collect()
+            };
+            let expected: Vec<i32> = q
+                .find_all_vec(&mut txn)?
+                .iter()
+                .map(|o| o.read_int(field))
+                .collect();
+            assert_eq!(expected, vec![4, 3, 2, 1]);
+            assert_eq!(values(q.find_all_parallel(&isar, 4)?), expected);
+            assert_eq!(values(q.find_all_parallel(&isar, 1)?), expected);
+        }
+
+        let q = col.new_query_builder().build();
+        assert_eq!(q.find_all_parallel(&isar, 8)?.len(), 100);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_stable_sort() -> Result<()> {
+        let isar = fill_int_col((0..500).map(|i| i % 3).collect(), false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(250, MAX_ID, Sort::Descending)?;
+        qb.add_id_where_clause(MIN_ID, 249, Sort::Descending)?;
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        qb.stable_sort();
+        let results = find(&mut txn, qb.build());
+
+        let mut expected: Vec<(i64, i32)> = (0..500).map(|i| (i as i64 + 1, i % 3)).collect();
+        expected.sort_by_key(|(id, value)| (-value, *id));
+        assert_eq!(results, expected);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_sort() -> Result<()> {
+        let mut schema = col!(oid => DataType::Long, field => DataType::Int);
+        schema.add_default_sort("field", Sort::Descending, Case::Sensitive);
+        isar!(isar, col => schema.clone());
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false)?;
+        for (i, int) in [3, 1, 2].iter().enumerate() {
+            let mut o = col.new_object_builder(None);
+            o.write_long(i as i64 + 1);
+            o.write_int(*int);
+            col.put(&mut txn, o.finish())?;
+        }
+
+        let results = find(&mut txn, col.new_query_builder().build());
+        assert_eq!(results, vec![(1, 3), (3, 2), (2, 1)]);
+
+        let mut qb = col.new_query_builder();
+        qb.skip_default_sort();
+        let results = find(&mut txn, qb.build());
+        assert_eq!(results, vec![(1, 3), (2, 1), (3, 2)]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        let results = find(&mut txn, qb.build());
+        assert_eq!(results, vec![(2, 1), (3, 2), (1, 3)]);
+
+        txn.abort();
+        isar.close();
+
+        let mut schema = col!(oid => DataType::Long, field => DataType::Int);
+        schema.add_default_sort("unknown", Sort::Ascending, Case::Sensitive);
+        assert!(crate::schema::Schema::new(vec![schema]).is_err());
+
+        let mut schema = col!(oid => DataType::Long, list => DataType::IntList);
+        schema.add_default_sort("list", Sort::Ascending, Case::Sensitive);
+        assert!(crate::schema::Schema::new(vec![schema]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_sort_memory() -> Result<()> {
+        let isar = fill_int_col((0..200).map(|i| (i * 7) % 50).collect(), false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let query = |max_sort_memory: Option<usize>, distinct: Option<DistinctKeep>| {
+            let mut qb = col.new_query_builder();
+            qb.add_sort(field, Sort::Descending, Case::Sensitive);
+            qb.stable_sort();
+            if let Some(keep) = distinct {
+                qb.add_distinct(field, true, false);
+                qb.set_distinct_keep(keep);
+            }
+            if let Some(max_sort_memory) = max_sort_memory {
+                qb.max_sort_memory(max_sort_memory);
+            }
+            qb.set_offset(3);
+            qb.set_limit(120);
+            qb.build()
+        };
+
+        for distinct in &[None, Some(DistinctKeep::First), Some(DistinctKeep::Last)] {
+            let in_memory = find(&mut txn, query(None, *distinct));
+            assert!(!in_memory.is_empty());
+            for max_sort_memory in &[1, 500, 100000] {
+                let spilled = find(&mut txn, query(Some(*max_sort_memory), *distinct));
+                assert_eq!(spilled, in_memory);
+            }
+        }
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_json_to() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let q = col.new_query_builder().build();
+        let mut bytes = vec![];
+        q.export_json_to(&mut txn, col, &mut bytes, true, false)?;
+        let json: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json, q.export_json(&mut txn, col, true, false)?);
+        assert_eq!(json.as_array().unwrap().len(), 3);
+
+        let mut qb = col.new_query_builder();
+        qb.set_limit(0);
+        let mut bytes = vec![];
+        qb.build()
+            .export_json_to(&mut txn, col, &mut bytes, true, false)?;
+        assert_eq!(bytes, b"[]");
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_csv() -> Result<()> {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String, values => DataType::IntList, score => DataType::Double));
+        let mut txn = isar.begin_txn(true, false)?;
+        let mut o = col.new_object_builder(None);
+        o.write_long(1);
+        o.write_string(Some("plain"));
+        o.write_int_list(Some(&[1, 2]));
+        o.write_double(1.5);
+        col.put(&mut txn, o.finish())?;
+        let mut o = col.new_object_builder(None);
+        o.write_long(2);
+        o.write_string(Some("comma, \"quote\"\nnewline"));
+        o.write_int_list(None);
+        o.write_double(f64::NAN);
+        col.put(&mut txn, o.finish())?;
+
+        let properties: Vec<Property> = col.get_properties().iter().map(|(_, p)| *p).collect();
+        let mut bytes = vec![];
+        let q = col.new_query_builder().build();
+        q.export_csv(&mut txn, col, &mut bytes, &properties)?;
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "oid,name,values,score\n\
+             1,plain,\"[1,2]\",1.5\n\
+             2,\"comma, \"\"quote\"\"\nnewline\",,\n"
+        );
+
+        let unknown = Property::new(DataType::Int, 100);
+        assert!(q.export_csv(&mut txn, col, vec![], &[unknown]).is_err());
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_first() -> Result<()> {
+        let isar = fill_int_col(vec![3, 1, 4, 1, 5], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
+        let first = |txn: &mut IsarTxn, q: Query| {
+            q.find_first(txn)
+                .unwrap()
+                .map(|o| o.read_long(col.get_oid_property()))
+        };
+
+        let q = col.new_query_builder().build();
+        assert_eq!(first(&mut txn, q), Some(1));
+
+        let mut qb = col.new_query_builder();
+        qb.set_offset(2);
+        assert_eq!(first(&mut txn, qb.build()), Some(3));
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        assert_eq!(first(&mut txn, qb.build()), Some(5));
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        qb.set_offset(2);
+        assert_eq!(first(&mut txn, qb.build()), Some(1));
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        qb.set_offset(5);
+        assert_eq!(first(&mut txn, qb.build()), None);
+
+        let mut qb = col.new_query_builder();
+        qb.set_limit(0);
+        assert_eq!(first(&mut txn, qb.build()), None);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_up_to() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3, 2, 1], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
+
+        let q = col.new_query_builder().build();
+        assert_eq!(q.count_up_to(&mut txn, 0)?, 0);
+        assert_eq!(q.count_up_to(&mut txn, 3)?, 3);
+        assert_eq!(q.count_up_to(&mut txn, 100)?, 5);
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(IntBetweenCond::filter(field, 2, 3)?);
+        qb.set_offset(1);
+        assert_eq!(qb.build().count_up_to(&mut txn, 100)?, 2);
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        qb.set_limit(2);
+        assert_eq!(qb.build().count_up_to(&mut txn, 100)?, 2);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_all() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3, 2, 1], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(true, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        qb.set_limit(1);
+        assert_eq!(qb.build().delete_all(&mut txn, col)?, 1);
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(IntBetweenCond::filter(field, 2, 3)?);
+        assert_eq!(qb.build().delete_all(&mut txn, col)?, 2);
+
+        let q = col.new_query_builder().build();
+        assert_eq!(find(&mut txn, q), vec![(1, 1), (5, 1)]);
+        assert_eq!(col.count_by_index(&mut txn, 0)?.len(), 1);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter() -> Result<()> {
+        let isar = fill_int_col(vec![3, 1, 4, 1, 5], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        qb.set_offset(1);
+        qb.set_limit(3);
+        let q = qb.build();
+        let values = q
+            .iter(&mut txn)?
+            .map(|object| object.map(|o| o.read_int(field)))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(values, vec![4, 3, 1]);
+
+        let lower = col.new_index_key(0).unwrap();
+        let mut upper = col.new_index_key(0).unwrap();
+        upper.add_int(i32::MAX);
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(lower, true, upper, true, false, Sort::Descending)?;
+        qb.add_id_where_clause(2, 4, Sort::Ascending)?;
+        qb.set_filter(IntBetweenCond::filter(field, 1, 4)?);
+        qb.add_distinct(field, true, false);
+        qb.set_offset(1);
+        let q = qb.build();
+        let mut iter = q.iter(&mut txn)?;
+        assert_eq!(iter.next().unwrap()?.read_int(field), 3);
+        assert_eq!(iter.next().unwrap()?.read_int(field), 1);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2], false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+
+        let plan = col.new_query_builder().build().explain();
+        assert_eq!(
+            plan.where_clauses,
+            vec![WhereClausePlan::IdRange {
+                lower: MIN_ID,
+                upper: MAX_ID,
+                sort: Sort::Ascending
+            }]
+        );
+        assert!(!plan.deduplicate_ids && !plan.filter && !plan.materialize_for_sort);
+        assert_eq!(plan.distinct, DistinctPlan::None);
+        assert_eq!(plan.limit, None);
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(1, 5, Sort::Ascending)?;
+        qb.add_id_where_clause(3, 8, Sort::Descending)?;
+        qb.set_filter(IntBetweenCond::filter(field, 1, 2)?);
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        qb.add_distinct(field, false, false);
+        qb.set_limit(3);
+        let plan = qb.build().explain();
+        assert_eq!(plan.where_clauses.len(), 2);
+        assert!(plan.deduplicate_ids && plan.filter && plan.materialize_for_sort);
+        assert_eq!(plan.distinct, DistinctPlan::AfterSort);
+        assert_eq!(plan.limit, Some(3));
+        assert!(plan.to_string().contains("id range 3..=8 Descending"));
+
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_by_index() -> Result<()> {
+        let isar = fill_int_col(vec![5, 3, 8, 1, 3, 9, 2], false);
+        let col = isar.get_collection(0).unwrap();
+        let oid = col.get_oid_property();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let query = |wc_sort: Sort, sort: Sort, offset: usize| {
+            let lower = col.new_index_key(0).unwrap();
+            let mut upper = col.new_index_key(0).unwrap();
+            upper.add_int(i32::MAX);
+            let mut qb = col.new_query_builder();
+            qb.add_index_where_clause(lower, true, upper, true, false, wc_sort)
+                .unwrap();
+            qb.add_sort(field, sort, Case::Sensitive);
+            qb.set_offset(offset);
+            qb.build()
+        };
+        let values = |txn: &mut IsarTxn, query: Query| -> Vec<i32> {
+            query
+                .find_all_vec(txn)
+                .unwrap()
+                .iter()
+                .map(|o| o.read_int(field))
+                .collect()
+        };
+
+        let q = query(Sort::Ascending, Sort::Ascending, 0);
+        let plan = q.explain();
+        assert!(plan.sort_by_index && !plan.materialize_for_sort);
+        assert_eq!(values(&mut txn, q), vec![1, 2, 3, 3, 5, 8, 9]);
+
+        let q = query(Sort::Descending, Sort::Descending, 2);
+        assert!(q.explain().sort_by_index);
+        assert_eq!(values(&mut txn, q), vec![5, 3, 3, 2, 1]);
+
+        let q = query(Sort::Descending, Sort::Ascending, 0);
+        let plan = q.explain();
+        assert!(!plan.sort_by_index && plan.materialize_for_sort);
+        assert_eq!(values(&mut txn, q), vec![1, 2, 3, 3, 5, 8, 9]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(oid, Sort::Ascending, Case::Sensitive);
+        assert!(!qb.build().explain().sort_by_index);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_by_string_index() -> Result<()> {
+        let schema = |reject_long_strings: bool| {
+            let mut col = col!(oid => DataType::Long, field => DataType::String; ind!(str field, IndexType::Value, Some(true)));
+            col.indexes[0].set_max_string_size(4);
+            col.indexes[0].set_reject_long_strings(reject_long_strings);
+            col
+        };
+        let query = |col: &IsarCollection| {
+            let lower = col.new_index_key(0).unwrap();
+            let mut upper = col.new_index_key(0).unwrap();
+            upper.add_string_value(Some("\u{10FFFF}"), true);
+            let mut qb = col.new_query_builder();
+            qb.add_index_where_clause(lower, true, upper, true, false, Sort::Ascending)
+                .unwrap();
+            let field = col.get_properties().get(1).unwrap().1;
+            qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+            qb.build()
+        };
+
+        // long strings with a common prefix are ordered by their hash in the index
+        isar!(isar, col => schema(false));
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false)?;
+        let values: Vec<String> = (0..10).map(|i| format!("aaaa{}", i)).collect();
+        for (i, value) in values.iter().enumerate().rev() {
+            let mut o = col.new_object_builder(None);
+            o.write_long(i as i64 + 1);
+            o.write_string(Some(value));
+            col.put(&mut txn, o.finish())?;
+        }
+        let q = query(col);
+        assert!(!q.explain().sort_by_index);
+        let result: Vec<String> = q
+            .find_all_vec(&mut txn)?
+            .iter()
+            .map(|o| o.read_string(field).unwrap().to_string())
+            .collect();
+        assert_eq!(result, values);
+        txn.abort();
+        isar.close();
+
+        isar!(isar, col => schema(true));
+        assert!(query(col).explain().sort_by_index);
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_ids() -> Result<()> {
+        let isar = fill_int_col(vec![5, 3, 8, 1, 3, 9, 2], false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let index_wc = |qb: &mut QueryBuilder, lower: i32, upper: i32| {
+            let mut lower_key = col.new_index_key(0).unwrap();
+            lower_key.add_int(lower);
+            let mut upper_key = col.new_index_key(0).unwrap();
+            upper_key.add_int(upper);
+            qb.add_index_where_clause(lower_key, true, upper_key, true, false, Sort::Ascending)
+                .unwrap();
+        };
+        let check = |txn: &mut IsarTxn, query: Query| -> Vec<i64> {
+            let ids = query.find_ids(txn).unwrap();
+            let object_ids: Vec<i64> = find(txn, query).iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids, object_ids);
+            ids
+        };
+
+        let mut qb = col.new_query_builder();
+        index_wc(&mut qb, 2, 5);
+        assert_eq!(check(&mut txn, qb.build()), vec![7, 2, 5, 1]);
+
+        let mut qb = col.new_query_builder();
+        index_wc(&mut qb, 2, 5);
+        index_wc(&mut qb, 3, 9);
+        qb.set_offset(1);
+        qb.set_limit(4);
+        assert_eq!(check(&mut txn, qb.build()), vec![2, 5, 1, 3]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(2, 4, Sort::Descending)?;
+        assert_eq!(check(&mut txn, qb.build()), vec![4, 3, 2]);
+
+        let mut qb = col.new_query_builder();
+        index_wc(&mut qb, 0, 10);
+        qb.set_filter(IntBetweenCond::filter(field, 3, 8)?);
+        assert_eq!(check(&mut txn, qb.build()), vec![2, 5, 1, 3]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        qb.stable_sort();
+        assert_eq!(check(&mut txn, qb.build()), vec![6, 3, 1, 2, 5, 7, 4]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_projected() -> Result<()> {
+        let isar = fill_int_col(vec![5, 3, 8, 1, 3, 9, 2], false);
+        let col = isar.get_collection(0).unwrap();
+        let oid = col.get_oid_property();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        qb.add_distinct(field, true, false);
+        qb.set_offset(1);
+        qb.set_limit(3);
+        let q = qb.build();
+
+        let values = q.find_projected(&mut txn, &[field])?;
+        let expected: Vec<Vec<IsarValue>> = find(&mut txn, q.clone())
+            .iter()
+            .map(|(_, value)| vec![IsarValue::Int(*value)])
+            .collect();
+        assert_eq!(values, expected);
+        assert_eq!(
+            values,
+            vec![
+                vec![IsarValue::Int(8)],
+                vec![IsarValue::Int(5)],
+                vec![IsarValue::Int(3)]
+            ]
+        );
+
+        let values = q.find_projected(&mut txn, &[field, oid])?;
+        assert_eq!(values[0], vec![IsarValue::Int(8), IsarValue::Long(3)]);
+        assert!(q
+            .find_projected(&mut txn, &[])?
+            .iter()
+            .all(|v| v.is_empty()));
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_result_hash() -> Result<()> {
+        let isar = fill_int_col(vec![5, 3, 8, 1], false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+
+        let unsorted = col.new_query_builder().build();
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        let sorted = qb.build();
+
+        let mut txn = isar.begin_txn(true, false)?;
+        let unsorted_hash = unsorted.result_hash(&mut txn)?;
+        let sorted_hash = sorted.result_hash(&mut txn)?;
+        assert_eq!(unsorted.result_hash(&mut txn)?, unsorted_hash);
+        assert_eq!(sorted.result_hash(&mut txn)?, sorted_hash);
+
+        let put = |txn: &mut IsarTxn, oid: i64, value: i32| {
+            let mut o = col.new_object_builder(None);
+            o.write_long(oid);
+            o.write_int(value);
+            col.put(txn, o.finish()).unwrap();
+        };
+
+        // swapping the values of two objects changes the sort order
+        put(&mut txn, 1, 3);
+        put(&mut txn, 2, 5);
+        assert_ne!(unsorted.result_hash(&mut txn)?, unsorted_hash);
+        assert_ne!(sorted.result_hash(&mut txn)?, sorted_hash);
+
+        put(&mut txn, 1, 5);
+        put(&mut txn, 2, 3);
+        assert_eq!(unsorted.result_hash(&mut txn)?, unsorted_hash);
+        assert_eq!(sorted.result_hash(&mut txn)?, sorted_hash);
+
+        col.delete(&mut txn, 4)?;
+        assert_ne!(unsorted.result_hash(&mut txn)?, unsorted_hash);
+        assert_ne!(sorted.result_hash(&mut txn)?, sorted_hash);
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_where_clause_exclusive() -> Result<()> {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String; ind!(str name, IndexType::Value, Some(true))));
+        let name = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(true, false)?;
+        for (i, value) in ["abd", "abb", "abc", "abbz"].iter().enumerate() {
+            let mut o = col.new_object_builder(None);
+            o.write_long(i as i64 + 1);
+            o.write_string(Some(value));
+            col.put(&mut txn, o.finish())?;
+        }
+
+        let mut find =
+            |lower: &str, include_lower: bool, upper: &str, include_upper: bool, sort| {
+                let mut lower_key = col.new_index_key(0).unwrap();
+                lower_key.add_string_value(Some(lower), true);
+                let mut upper_key = col.new_index_key(0).unwrap();
+                upper_key.add_string_value(Some(upper), true);
+                let mut qb = col.new_query_builder();
+                qb.add_index_where_clause(
+                    lower_key,
+                    include_lower,
+                    upper_key,
+                    include_upper,
+                    false,
+                    sort,
+                )
+                .unwrap();
+                qb.build()
+                    .find_all_vec(&mut txn)
+                    .unwrap()
+                    .iter()
+                    .map(|o| o.read_string(name).unwrap().to_string())
+                    .collect::<Vec<_>>()
+            };
+        assert_eq!(
+            find("abb", true, "abc", true, Sort::Ascending),
+            vec!["abb", "abbz", "abc"]
+        );
+        assert_eq!(
+            find("abb", false, "abc", false, Sort::Ascending),
+            vec!["abbz"]
+        );
+        assert_eq!(
+            find("abb", false, "abc", false, Sort::Descending),
+            vec!["abbz"]
+        );
+        assert_eq!(
+            find("abb", false, "abd", true, Sort::Descending),
+            vec!["abd", "abc", "abbz"]
+        );
+        assert!(find("abc", true, "abc", false, Sort::Ascending).is_empty());
+        txn.abort();
+        isar.close();
+
+        let isar = fill_int_col(vec![5, 3, 8, 1, 3, 9, 2], false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+        let mut find = |include_lower: bool, include_upper: bool, sort| {
+            let mut lower = col.new_index_key(0).unwrap();
+            lower.add_int(1);
+            let mut upper = col.new_index_key(0).unwrap();
+            upper.add_int(3);
+            let mut qb = col.new_query_builder();
+            qb.add_index_where_clause(lower, include_lower, upper, include_upper, false, sort)
+                .unwrap();
+            qb.build()
+                .find_all_vec(&mut txn)
+                .unwrap()
+                .iter()
+                .map(|o| o.read_int(field))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(find(true, true, Sort::Descending), vec![3, 3, 2, 1]);
+        assert_eq!(find(false, true, Sort::Ascending), vec![2, 3, 3]);
+        assert_eq!(find(true, false, Sort::Descending), vec![2, 1]);
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_id_where_clause_bounds() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3], true);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut qb = col.new_query_builder();
+        assert!(qb
+            .add_id_where_clause(MIN_ID - 1, 2, Sort::Ascending)
+            .is_err());
+        assert!(qb
+            .add_id_where_clause(1, MAX_ID + 1, Sort::Ascending)
+            .is_err());
+        assert!(qb
+            .add_id_where_clause(i64::MIN, i64::MAX, Sort::Ascending)
+            .is_err());
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(3, 1, Sort::Ascending)?;
+        assert_eq!(find(&mut txn, qb.build()), vec![]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(MIN_ID, MAX_ID, Sort::Descending)?;
+        assert_eq!(find(&mut txn, qb.build()), vec![(3, 3), (2, 2), (1, 1)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_n_by_index() -> Result<()> {
+        let isar = fill_int_col(vec![5, 1, 9, 3, 7, 9], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut qb = col.new_query_builder();
+        qb.top_n_by_index(0, 3, Sort::Descending)?;
+        assert_eq!(find(&mut txn, qb.build()), vec![(6, 9), (3, 9), (5, 7)]);
+
+        let mut qb = col.new_query_builder();
+        qb.top_n_by_index(0, 2, Sort::Ascending)?;
+        assert_eq!(find(&mut txn, qb.build()), vec![(2, 1), (4, 3)]);
+
+        let mut qb = col.new_query_builder();
+        qb.top_n_by_index(0, 10, Sort::Ascending)?;
+        assert_eq!(find(&mut txn, qb.build()).len(), 6);
+
+        let mut qb = col.new_query_builder();
+        assert!(qb.top_n_by_index(1, 3, Sort::Ascending).is_err());
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_reversed() -> Result<()> {
+        let isar = fill_int_col(vec![5, 1, 9, 3, 7, 9, 1, 4], false);
+        let col = isar.get_collection(0).unwrap();
+        let field = col.get_properties().get(1).unwrap().1;
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut queries = vec![];
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(6, 8, Sort::Descending)?;
+        qb.add_id_where_clause(1, 4, Sort::Ascending)?;
+        qb.set_filter(IntBetweenCond::filter(field, 1, 8)?);
+        queries.push(qb.build());
+
+        let mut qb = col.new_query_builder();
+        qb.add_index_null_where_clause(0, false, Sort::Ascending)?;
+        queries.push(qb.build());
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Descending, Case::Sensitive);
+        qb.stable_sort();
+        queries.push(qb.build());
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        qb.stable_sort();
+        qb.add_distinct(field, true, false);
+        queries.push(qb.build());
+
+        for query in queries {
+            let mut results = find(&mut txn, query.clone());
+            assert!(!results.is_empty());
+            results.reverse();
+            assert_eq!(find(&mut txn, query.reversed()), results);
+        }
+
+        let mut qb = col.new_query_builder();
+        qb.set_limit(2);
+        let query = qb.build().reversed();
+        assert_eq!(find(&mut txn, query), vec![(8, 4), (7, 1)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_primary_where_clause() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3, 4, 5], true);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let wc = col
+            .new_id_where_clause(Some(2), Some(4), Sort::Ascending)
+            .unwrap();
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(wc)?;
+        assert_eq!(find(&mut txn, qb.build()), vec![(2, 2), (3, 3), (4, 4)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_secondary_where_clause() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3, 4], true);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut wc = col
+            .new_index_where_clause(0, false, Sort::Ascending)
+            .unwrap();
+        wc.add_int(2, 3).unwrap();
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(wc, true, true)?;
+        assert_eq!(find(&mut txn, qb.build()), vec![(2, 2), (3, 3)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_secondary_where_clause_dup() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 2, 3, 3, 3, 4], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let mut wc = col
+            .new_index_where_clause(0, false, Sort::Ascending)
+            .unwrap();
+        wc.add_int(2, 3).unwrap();
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(wc, true, true)?;
         assert_eq!(
             find(&mut txn, qb.build()),
             vec![(2, 2), (3, 2), (4, 3), (5, 3), (6, 3)]
@@ -434,6 +1904,88 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_many_overlapping_id_where_clauses() -> Result<()> {
+        let isar = fill_int_col(vec![], false);
+        let col = isar.get_collection(0).unwrap();
+
+        let mut qb = col.new_query_builder();
+        for i in (0..10_000).rev() {
+            qb.add_id_where_clause(i * 10, i * 10 + 9, Sort::Ascending)?;
+        }
+        assert!(!qb.build().where_clauses_overlapping);
+
+        let mut qb = col.new_query_builder();
+        for i in 0..10_000 {
+            qb.add_id_where_clause(i * 10, i * 10 + 9, Sort::Ascending)?;
+        }
+        qb.add_id_where_clause(0, 99_990, Sort::Descending)?;
+        assert!(qb.build().where_clauses_overlapping);
+
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_overlapping_id_and_index_where_clauses() -> Result<()> {
+        let isar = fill_int_col(vec![1, 2, 3, 4, 5], true);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let index_key = |value: i32| {
+            let mut key = col.new_index_key(0).unwrap();
+            key.add_int(value);
+            key
+        };
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(1, 3, Sort::Ascending)?;
+        qb.add_index_where_clause(
+            index_key(2),
+            true,
+            index_key(4),
+            true,
+            false,
+            Sort::Ascending,
+        )?;
+        let q = qb.build();
+        assert!(q.where_clauses_overlapping);
+        let results: HashSet<(i64, i32)> = find(&mut txn, q).into_iter().collect();
+        assert_eq!(results, set![(1, 1), (2, 2), (3, 3), (4, 4)]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_id_where_clause(1, 2, Sort::Ascending)?;
+        qb.add_id_where_clause(4, 5, Sort::Ascending)?;
+        let q = qb.build();
+        assert!(!q.where_clauses_overlapping);
+        assert_eq!(find(&mut txn, q), vec![(1, 1), (2, 2), (4, 4), (5, 5)]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(
+            index_key(1),
+            true,
+            index_key(2),
+            true,
+            false,
+            Sort::Ascending,
+        )?;
+        qb.add_index_where_clause(
+            index_key(2),
+            true,
+            index_key(3),
+            true,
+            false,
+            Sort::Ascending,
+        )?;
+        let q = qb.build();
+        assert!(q.where_clauses_overlapping);
+        assert_eq!(find(&mut txn, q), vec![(1, 1), (2, 2), (3, 3)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
     #[test]
     fn test_filter_unsorted() -> Result<()> {
         let isar = fill_int_col(vec![5, 4, 4, 3, 2, 2, 1], false);
@@ -457,6 +2009,275 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_filter_id() -> Result<()> {
+        let isar = fill_int_col(vec![5, 4, 4, 3, 2, 2, 1], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let int_property = col.get_properties().get(1).unwrap().1;
+        let mut qb = col.new_query_builder();
+        qb.set_filter(OrCond::filter(vec![
+            IdBetweenCond::filter(col, 2, 3),
+            IntBetweenCond::filter(int_property, 1, 2)?,
+        ]));
+        assert_eq!(
+            find(&mut txn, qb.build()),
+            vec![(2, 4), (3, 4), (5, 2), (6, 2), (7, 1)]
+        );
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(AndCond::filter(vec![
+            IdBetweenCond::filter(col, 3, 6),
+            NotCond::filter(IntBetweenCond::filter(int_property, 3, 3)?),
+        ]));
+        assert_eq!(find(&mut txn, qb.build()), vec![(3, 4), (5, 2), (6, 2)]);
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(OrCond::filter(vec![
+            IdBetweenCond::filter(col, 100, 200),
+            IntBetweenCond::filter(int_property, 5, 5)?,
+        ]));
+        assert_eq!(find(&mut txn, qb.build()), vec![(1, 5)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_offset_limit() -> Result<()> {
+        let isar = fill_int_col(vec![1, 1, 2, 2, 3], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
+        let all = find(&mut txn, col.new_query_builder().build());
+
+        let cases = [
+            (0, 0),
+            (0, 2),
+            (2, 2),
+            (5, 1),
+            (10, 1),
+            (3, usize::MAX),
+            (usize::MAX, 2),
+            (2, usize::MAX - 1),
+        ];
+        for (offset, limit) in cases.iter() {
+            let expected: Vec<_> = all.iter().copied().skip(*offset).take(*limit).collect();
+            for sorted in [false, true].iter() {
+                let mut qb = col.new_query_builder();
+                if *sorted {
+                    qb.add_sort(col.get_oid_property(), Sort::Ascending, Case::Sensitive);
+                }
+                qb.set_offset(*offset);
+                qb.set_limit(*limit);
+                assert_eq!(find(&mut txn, qb.build()), expected);
+            }
+        }
+
+        for sorted in [false, true].iter() {
+            let mut qb = col.new_query_builder();
+            if *sorted {
+                qb.add_sort(col.get_oid_property(), Sort::Ascending, Case::Sensitive);
+            }
+            qb.add_distinct(field, false, false);
+            qb.set_offset(1);
+            qb.set_limit(1);
+            assert_eq!(find(&mut txn, qb.build()), vec![(3, 2)]);
+        }
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_keep() -> Result<()> {
+        let isar = fill_int_col(vec![5, 4, 4, 3, 2, 2, 1], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+        let field = col.get_properties().get(1).unwrap().1;
+
+        let cases = [
+            (DistinctKeep::First, false, vec![1, 2, 4, 5, 7]),
+            (DistinctKeep::Last, false, vec![1, 3, 4, 6, 7]),
+            (DistinctKeep::First, true, vec![7, 5, 4, 2, 1]),
+            (DistinctKeep::Last, true, vec![7, 6, 4, 3, 1]),
+        ];
+        for (keep, sorted, expected) in cases.iter() {
+            let mut qb = col.new_query_builder();
+            if *sorted {
+                qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+                qb.stable_sort();
+            }
+            qb.add_distinct(field, false, false);
+            qb.set_distinct_keep(*keep);
+            let ids: Vec<i64> = find(&mut txn, qb.build())
+                .iter()
+                .map(|(id, _)| *id)
+                .collect();
+            assert_eq!(&ids, expected);
+        }
+
+        let mut qb = col.new_query_builder();
+        qb.add_distinct(field, false, false);
+        qb.set_distinct_keep(DistinctKeep::Last);
+        qb.set_offset(1);
+        qb.set_limit(2);
+        let query = qb.build();
+        assert_eq!(query.explain().distinct, DistinctPlan::AfterScan);
+        assert_eq!(find(&mut txn, query), vec![(3, 4), (4, 3)]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_string_and_list() -> Result<()> {
+        isar!(isar, col => col!(oid => DataType::Long, name => DataType::String, tags => DataType::StringList));
+        let mut txn = isar.begin_txn(true, false)?;
+        let data: [(Option<&str>, Option<&[Option<&str>]>); 4] = [
+            (Some("b"), Some(&[Some("x")])),
+            (None, None),
+            (Some("C"), Some(&[Some("x"), Some("a")])),
+            (Some("a"), Some(&[])),
+        ];
+        for (i, (name, tags)) in data.iter().enumerate() {
+            let mut o = col.new_object_builder(None);
+            o.write_long(i as i64 + 1);
+            o.write_string(*name);
+            o.write_string_list(*tags);
+            col.put(&mut txn, o.finish())?;
+        }
+        let name = col.get_properties().get(1).unwrap().1;
+        let tags = col.get_properties().get(2).unwrap().1;
+
+        let cases = [
+            (name, Sort::Ascending, Case::Insensitive, vec![2, 4, 1, 3]),
+            (name, Sort::Ascending, Case::Sensitive, vec![2, 3, 4, 1]),
+            (name, Sort::Descending, Case::Insensitive, vec![3, 1, 4, 2]),
+            (tags, Sort::Ascending, Case::Sensitive, vec![2, 4, 1, 3]),
+        ];
+        for (property, sort, case, expected) in cases.iter() {
+            let mut qb = col.new_query_builder();
+            qb.add_sort(*property, *sort, *case);
+            let ids: Vec<i64> = qb
+                .build()
+                .find_all_vec(&mut txn)?
+                .iter()
+                .map(|o| o.read_long(col.get_oid_property()))
+                .collect();
+            assert_eq!(&ids, expected);
+        }
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_and_distinct_bool() -> Result<()> {
+        isar!(isar, col => col!(oid => DataType::Long, flag => DataType::Bool));
+        let mut txn = isar.begin_txn(true, false)?;
+        for (i, flag) in [Some(true), None, Some(false), Some(true), Some(false)]
+            .iter()
+            .enumerate()
+        {
+            let mut o = col.new_object_builder(None);
+            o.write_long(i as i64 + 1);
+            match flag {
+                Some(flag) => o.write_bool(*flag),
+                None => o.write_null(),
+            }
+            col.put(&mut txn, o.finish())?;
+        }
+        let flag = col.get_properties().get(1).unwrap().1;
+        let ids = |query: Query, txn: &mut IsarTxn| -> Result<Vec<i64>> {
+            Ok(query
+                .find_all_vec(txn)?
+                .iter()
+                .map(|o| o.read_long(col.get_oid_property()))
+                .collect())
+        };
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(flag, Sort::Ascending, Case::Sensitive);
+        qb.stable_sort();
+        assert_eq!(ids(qb.build(), &mut txn)?, vec![2, 3, 5, 1, 4]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(flag, Sort::Descending, Case::Sensitive);
+        qb.stable_sort();
+        qb.add_distinct(flag, true, false);
+        assert_eq!(ids(qb.build(), &mut txn)?, vec![1, 3, 2]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_order() -> Result<()> {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Double; ind!(field)));
+        let mut txn = isar.begin_txn(true, false)?;
+        let values = [
+            1.0,
+            f64::NAN,
+            -0.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            0.0,
+            -1.0,
+            -f64::NAN,
+        ];
+        for (i, value) in values.iter().enumerate() {
+            let mut o = col.new_object_builder(None);
+            o.write_long(i as i64 + 1);
+            o.write_double(*value);
+            col.put(&mut txn, o.finish())?;
+        }
+        let field = col.get_properties().get(1).unwrap().1;
+        let ids = |txn: &mut IsarTxn, query: Query| -> Result<Vec<i64>> {
+            Ok(query
+                .find_all_vec(txn)?
+                .iter()
+                .map(|o| o.read_long(col.get_oid_property()))
+                .collect())
+        };
+
+        // NaN < -inf < -1.0 < -0.0 == 0.0 < 1.0 < inf
+        let expected = vec![2, 8, 5, 7, 3, 6, 1, 4];
+
+        let mut qb = col.new_query_builder();
+        qb.top_n_by_index(0, 10, Sort::Ascending)?;
+        assert_eq!(ids(&mut txn, qb.build())?, expected);
+
+        let mut qb = col.new_query_builder();
+        qb.add_sort(field, Sort::Ascending, Case::Sensitive);
+        qb.stable_sort();
+        assert_eq!(ids(&mut txn, qb.build())?, expected);
+
+        let mut qb = col.new_query_builder();
+        qb.set_filter(DoubleBetweenCond::filter(field, 0.0, 0.0)?);
+        assert_eq!(ids(&mut txn, qb.build())?, vec![3, 6]);
+
+        let mut key = col.new_index_key(0).unwrap();
+        key.add_double(0.0);
+        let mut qb = col.new_query_builder();
+        qb.add_index_where_clause(key.clone(), true, key, true, false, Sort::Ascending)?;
+        assert_eq!(ids(&mut txn, qb.build())?, vec![3, 6]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_distinct(field, false, false);
+        assert_eq!(ids(&mut txn, qb.build())?, vec![1, 2, 3, 4, 5, 7]);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
     #[test]
     fn test_filter_sorted() -> Result<()> {
         let isar = fill_int_col(vec![5, 4, 4, 3, 2, 2, 1], false);
@@ -469,7 +2290,7 @@ mod tests {
             IntBetweenCond::filter(int_property, 2, 3)?,
             NotCond::filter(IntBetweenCond::filter(int_property, 0, 4)?),
         ]));
-        qb.add_sort(int_property, Sort::Ascending);
+        qb.add_sort(int_property, Sort::Ascending, Case::Sensitive);
 
         assert_eq!(
             find(&mut txn, qb.build()),
@@ -489,7 +2310,7 @@ mod tests {
 
         let int_property = col.get_properties().get(1).unwrap().1;
         let mut qb = col.new_query_builder();
-        qb.add_distinct(int_property, false);
+        qb.add_distinct(int_property, false, false);
 
         assert_eq!(
             find(&mut txn, qb.build()),
@@ -501,6 +2322,76 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_distinct_nulls() -> Result<()> {
+        let null = IsarObject::NULL_INT;
+        let isar = fill_int_col(vec![null, 1, null, 1, null], false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let int_property = col.get_properties().get(1).unwrap().1;
+        let mut qb = col.new_query_builder();
+        qb.add_distinct(int_property, false, false);
+        assert_eq!(find(&mut txn, qb.build()), vec![(1, null), (2, 1)]);
+
+        let mut qb = col.new_query_builder();
+        qb.add_distinct(int_property, false, true);
+        assert_eq!(
+            find(&mut txn, qb.build()),
+            vec![(1, null), (2, 1), (3, null), (5, null)]
+        );
+
+        let mut qb = col.new_query_builder();
+        qb.add_distinct(int_property, false, true);
+        qb.add_sort(int_property, Sort::Descending, Case::Sensitive);
+        let results = find(&mut txn, qb.build());
+        assert_eq!(results[0], (2, 1));
+        assert_eq!(results.len(), 4);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_no_collisions() -> Result<()> {
+        let isar = fill_int_col((0..20000).collect(), false);
+        let col = isar.get_collection(0).unwrap();
+        let mut txn = isar.begin_txn(false, false)?;
+
+        let int_property = col.get_properties().get(1).unwrap().1;
+        let mut qb = col.new_query_builder();
+        qb.add_distinct(int_property, false, false);
+        assert_eq!(qb.build().count(&mut txn)?, 20000);
+
+        txn.abort();
+        isar.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_key_strings() {
+        isar!(isar, col => col!(oid => DataType::Long, s1 => DataType::String, s2 => DataType::String));
+        let properties: Vec<_> = col.get_properties()[1..]
+            .iter()
+            .map(|(_, p)| (*p, true, false))
+            .collect();
+        let object = |s1: Option<&str>, s2: Option<&str>| {
+            let mut o = col.new_object_builder(None);
+            o.write_long(1);
+            o.write_string(s1);
+            o.write_string(s2);
+            o.finish().as_bytes().to_vec()
+        };
+        let o1 = object(None, Some("ab"));
+        let o2 = object(Some("ab"), None);
+        assert_ne!(
+            Query::distinct_key(&properties, IsarObject::from_bytes(&o1)),
+            Query::distinct_key(&properties, IsarObject::from_bytes(&o2))
+        );
+        isar.close();
+    }
+
     #[test]
     fn test_distinct_sorted() -> Result<()> {
         let isar = fill_int_col(vec![5, 4, 4, 3, 2, 2, 1], false);
@@ -509,8 +2400,8 @@ mod tests {
 
         let int_property = col.get_properties().get(1).unwrap().1;
         let mut qb = col.new_query_builder();
-        qb.add_distinct(int_property, false);
-        qb.add_sort(int_property, Sort::Ascending);
+        qb.add_distinct(int_property, false, false);
+        qb.add_sort(int_property, Sort::Ascending, Case::Sensitive);
 
         assert_eq!(
             find(&mut txn, qb.build()),
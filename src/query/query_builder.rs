@@ -1,23 +1,36 @@
 use crate::error::{illegal_arg, Result};
-use crate::lmdb::{MAX_ID, MIN_ID};
+use crate::lmdb::{verify_id, MAX_ID, MIN_ID};
+use crate::object::data_type::DataType;
 use crate::object::isar_object::Property;
-use crate::query::filter::Filter;
+use crate::query::external_sort::SortSpill;
+use crate::query::filter::{AndCond, Filter};
 use crate::query::id_where_clause::IdWhereClause;
 use crate::query::where_clause::WhereClause;
-use crate::query::{Query, Sort};
+use crate::query::{Case, DistinctKeep, Query, Sort};
+use crate::schema::collection_schema::IndexType;
 use crate::{collection::IsarCollection, index::index_key::IndexKey};
 use itertools::Itertools;
 
 use super::index_where_clause::IndexWhereClause;
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum WherePlan {
+    IndexScan { index_index: usize },
+    FilterOnly,
+}
+
 pub struct QueryBuilder<'a> {
     collection: &'a IsarCollection,
     where_clauses: Option<Vec<WhereClause>>,
     filter: Option<Filter>,
-    sort: Vec<(Property, Sort)>,
-    distinct: Vec<(Property, bool)>,
+    sort: Vec<(Property, Sort, bool)>,
+    distinct: Vec<(Property, bool, bool)>,
+    distinct_keep: DistinctKeep,
     offset: usize,
     limit: usize,
+    stable_sort: bool,
+    use_default_sort: bool,
+    max_sort_memory: Option<usize>,
 }
 
 impl<'a> QueryBuilder<'a> {
@@ -28,12 +41,19 @@ impl<'a> QueryBuilder<'a> {
             filter: None,
             sort: vec![],
             distinct: vec![],
+            distinct_keep: DistinctKeep::First,
             offset: 0,
             limit: usize::MAX,
+            stable_sort: false,
+            use_default_sort: true,
+            max_sort_memory: None,
         }
     }
 
     pub fn add_id_where_clause(&mut self, lower_id: i64, upper_id: i64, sort: Sort) -> Result<()> {
+        if verify_id(lower_id).is_err() || verify_id(upper_id).is_err() {
+            return illegal_arg("Id where clause bounds are out of range.");
+        }
         if self.where_clauses.is_none() {
             self.where_clauses = Some(vec![]);
         }
@@ -59,11 +79,18 @@ impl<'a> QueryBuilder<'a> {
         if lower_key.index.get_col_id() != self.collection.get_id() {
             return illegal_arg("Invalid IndexKey for this collection");
         }
-        let mut wc = IndexWhereClause::new(lower_key, upper_key, skip_duplicates, sort)?;
+        let wc = IndexWhereClause::new(
+            lower_key,
+            include_lower,
+            upper_key,
+            include_upper,
+            skip_duplicates,
+            sort,
+        )?;
         if self.where_clauses.is_none() {
             self.where_clauses = Some(vec![]);
         }
-        if wc.try_exclude(include_lower, include_upper) && !wc.is_empty() {
+        if !wc.is_empty() {
             self.where_clauses
                 .as_mut()
                 .unwrap()
@@ -72,16 +99,153 @@ impl<'a> QueryBuilder<'a> {
         Ok(())
     }
 
+    /// Adds a where clause for all objects whose string starts with `prefix`. The string has to
+    /// be the first property of the index.
+    pub fn add_index_prefix_where_clause(
+        &mut self,
+        index_index: usize,
+        prefix: &str,
+        case_sensitive: bool,
+        sort: Sort,
+    ) -> Result<()> {
+        let wc = self.collection.new_index_prefix_where_clause(
+            index_index,
+            prefix,
+            case_sensitive,
+            sort,
+        )?;
+        if self.where_clauses.is_none() {
+            self.where_clauses = Some(vec![]);
+        }
+        self.where_clauses
+            .as_mut()
+            .unwrap()
+            .push(WhereClause::Index(wc));
+        Ok(())
+    }
+
+    /// Adds a where clause for all objects whose indexed property is null or not null.
+    pub fn add_index_null_where_clause(
+        &mut self,
+        index_index: usize,
+        is_null: bool,
+        sort: Sort,
+    ) -> Result<()> {
+        let wc = self
+            .collection
+            .new_index_null_where_clause(index_index, is_null, sort)?;
+        if self.where_clauses.is_none() {
+            self.where_clauses = Some(vec![]);
+        }
+        self.where_clauses
+            .as_mut()
+            .unwrap()
+            .push(WhereClause::Index(wc));
+        Ok(())
+    }
+
+    pub fn top_n_by_index(&mut self, index_index: usize, n: usize, sort: Sort) -> Result<()> {
+        let index = if let Some(index) = self.collection.get_indexes().get(index_index) {
+            index
+        } else {
+            return illegal_arg("Index does not exist");
+        };
+        if index.properties.len() != 1 || index.properties[0].index_type != IndexType::Value {
+            return illegal_arg("Index needs to be a single property value index.");
+        }
+
+        // all 0xFF bytes are larger than every ascending or descending key of the property
+        let max_key_len = match index.properties[0].property.data_type {
+            DataType::Byte | DataType::Bool => 1,
+            DataType::Int | DataType::Float => 4,
+            DataType::Long | DataType::Double | DataType::DateTime => 8,
+            // valid UTF-8 never contains 0xFF so two bytes are enough
+            DataType::String => 2,
+            _ => return illegal_arg("Index property is not orderable."),
+        };
+        let lower_key = IndexKey::new(index);
+        let mut upper_key = IndexKey::new(index);
+        upper_key.bytes.extend(vec![u8::MAX; max_key_len]);
+        self.add_index_where_clause(lower_key, true, upper_key, true, false, sort)?;
+        self.set_limit(n);
+        Ok(())
+    }
+
     pub fn set_filter(&mut self, filter: Filter) {
         self.filter = Some(filter);
     }
 
-    pub fn add_sort(&mut self, property: Property, sort: Sort) {
-        self.sort.push((property, sort))
+    /// Uses an index where clause for the first part of the filter that can be answered by an
+    /// index. The remaining conditions are used as filter.
+    pub fn where_optimized(&mut self, filter: Filter) -> Result<WherePlan> {
+        let mut filters = filter.into_conjunction();
+        let mut plan = WherePlan::FilterOnly;
+        if self.where_clauses.is_none() {
+            let collection = self.collection;
+            'indexes: for (index_index, index) in collection.get_indexes().iter().enumerate() {
+                for i in 0..filters.len() {
+                    if let Some((lower, upper)) = filters[i].index_bounds(index) {
+                        self.add_index_where_clause(
+                            lower,
+                            true,
+                            upper,
+                            true,
+                            false,
+                            Sort::Ascending,
+                        )?;
+                        filters.remove(i);
+                        plan = WherePlan::IndexScan { index_index };
+                        break 'indexes;
+                    }
+                }
+            }
+        }
+        self.filter = match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(AndCond::filter(filters)),
+        };
+        Ok(plan)
+    }
+
+    /// `case` only affects `String` and `StringList` properties.
+    pub fn add_sort(&mut self, property: Property, sort: Sort, case: Case) {
+        self.sort.push((property, sort, case == Case::Sensitive))
+    }
+
+    /// Breaks ties between objects with equal sort properties by ascending id. This uses a
+    /// stable sort which allocates a buffer and is slower than the default unstable sort.
+    pub fn stable_sort(&mut self) {
+        self.stable_sort = true;
     }
 
-    pub fn add_distinct(&mut self, property: Property, case_sensitive: bool) {
-        self.distinct.push((property, case_sensitive));
+    /// Returns the results in id order instead of the default sort of the collection schema if
+    /// no sort has been added.
+    pub fn skip_default_sort(&mut self) {
+        self.use_default_sort = false;
+    }
+
+    /// Limits the memory used to buffer the objects of a sorted query. Once exceeded, sorted runs
+    /// of object copies are written to temporary files and merged afterwards.
+    pub fn max_sort_memory(&mut self, bytes: usize) {
+        self.max_sort_memory = Some(bytes);
+    }
+
+    pub fn add_distinct(
+        &mut self,
+        property: Property,
+        case_sensitive: bool,
+        treat_nulls_distinct: bool,
+    ) {
+        self.distinct
+            .push((property, case_sensitive, treat_nulls_distinct));
+    }
+
+    /// Selects which object of each group of the whole distinct key is returned. Defaults to
+    /// `DistinctKeep::First`. Keeping the last object without sorting buffers all matching
+    /// objects until the scan is complete.
+    pub fn set_distinct_keep(&mut self, keep: DistinctKeep) {
+        self.distinct_keep = keep;
     }
 
     pub fn set_offset(&mut self, offset: usize) {
@@ -97,19 +261,41 @@ impl<'a> QueryBuilder<'a> {
             self.add_id_where_clause(MIN_ID, MAX_ID, Sort::Ascending)
                 .unwrap();
         }
-        let sort_unique = self.sort.into_iter().unique_by(|(p, _)| p.offset).collect();
+        if self.use_default_sort && self.sort.is_empty() {
+            self.sort = self.collection.get_default_sort().to_vec();
+        }
+        let sort_unique = self
+            .sort
+            .into_iter()
+            .unique_by(|(p, _, _)| p.offset)
+            .collect();
         let distinct_unique = self
             .distinct
             .into_iter()
-            .unique_by(|(p, _)| p.offset)
+            .unique_by(|(p, _, _)| p.offset)
             .collect();
+        let stable_sort = if self.stable_sort {
+            Some((self.collection.get_oid_property(), Sort::Ascending))
+        } else {
+            None
+        };
+        let collection = self.collection;
+        let sort_spill = self.max_sort_memory.map(|max_memory| SortSpill {
+            max_memory,
+            col_id: collection.get_id(),
+            oid_property: collection.get_oid_property(),
+        });
         Query::new(
             self.where_clauses.unwrap(),
             self.filter,
             sort_unique,
             distinct_unique,
+            self.distinct_keep,
             self.offset,
             self.limit,
+            stable_sort,
+            sort_spill,
+            collection.get_oid_property(),
         )
     }
 }
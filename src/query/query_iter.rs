@@ -0,0 +1,143 @@
+use crate::error::Result;
+use crate::object::isar_object::IsarObject;
+use crate::query::filter::{Condition, FilterCursors};
+use crate::query::Query;
+use crate::txn::Cursors;
+use hashbrown::HashSet;
+
+/// Iterator over the results of a query. The objects borrow from the transaction and are only
+/// valid as long as the transaction is open.
+pub struct QueryIter<'a, 'txn> {
+    state: QueryIterState<'a, 'txn>,
+}
+
+enum QueryIterState<'a, 'txn> {
+    Buffered(std::vec::IntoIter<IsarObject<'txn>>),
+    Streaming(QueryStream<'a, 'txn>),
+    Done,
+}
+
+/// Reads the results directly from the where clause cursors. Only used if the results do not
+/// have to be sorted and the first object of each distinct group is kept.
+struct QueryStream<'a, 'txn> {
+    query: &'a Query,
+    cursors: &'a mut Cursors<'txn>,
+    where_clause: usize,
+    first: bool,
+    result_ids: Option<HashSet<i64>>,
+    distinct_keys: HashSet<Vec<u8>>,
+    skipped: usize,
+    taken: usize,
+}
+
+impl<'a, 'txn> QueryIter<'a, 'txn> {
+    pub(crate) fn buffered(objects: Vec<IsarObject<'txn>>) -> Self {
+        QueryIter {
+            state: QueryIterState::Buffered(objects.into_iter()),
+        }
+    }
+
+    pub(crate) fn streaming(query: &'a Query, cursors: &'a mut Cursors<'txn>) -> Self {
+        let result_ids = if query.where_clauses_overlapping {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        QueryIter {
+            state: QueryIterState::Streaming(QueryStream {
+                query,
+                cursors,
+                where_clause: 0,
+                first: true,
+                result_ids,
+                distinct_keys: HashSet::new(),
+                skipped: 0,
+                taken: 0,
+            }),
+        }
+    }
+}
+
+impl<'a, 'txn> QueryStream<'a, 'txn> {
+    /// Returns the next object that matches the where clauses, the filter and is distinct.
+    fn next_match(&mut self) -> Result<Option<IsarObject<'txn>>> {
+        let query = self.query;
+        while let Some(where_clause) = query.where_clauses.get(self.where_clause) {
+            let object = where_clause.next(self.cursors, self.first, self.result_ids.as_mut())?;
+            self.first = false;
+            if let Some(object) = object {
+                if self.matches(object)? {
+                    return Ok(Some(object));
+                }
+            } else {
+                self.where_clause += 1;
+                self.first = true;
+            }
+        }
+        Ok(None)
+    }
+
+    fn matches(&mut self, object: IsarObject<'txn>) -> Result<bool> {
+        if let Some(filter) = &self.query.filter {
+            let mut filter_cursors =
+                FilterCursors::new(&mut self.cursors.data2, &mut self.cursors.links);
+            if !filter.evaluate(object, Some(&mut filter_cursors))? {
+                return Ok(false);
+            }
+        }
+        if self.query.distinct.is_empty() {
+            return Ok(true);
+        }
+        match Query::distinct_key(&self.query.distinct, object) {
+            Some(key) => Ok(self.distinct_keys.insert(key)),
+            None => Ok(true),
+        }
+    }
+
+    /// Applies offset and limit to the matching objects.
+    fn next(&mut self) -> Result<Option<IsarObject<'txn>>> {
+        while self.taken < self.query.limit {
+            match self.next_match()? {
+                Some(_) if self.skipped < self.query.offset => self.skipped += 1,
+                Some(object) => {
+                    self.taken += 1;
+                    return Ok(Some(object));
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, 'txn> Iterator for QueryIter<'a, 'txn> {
+    type Item = Result<IsarObject<'txn>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match &mut self.state {
+            QueryIterState::Buffered(objects) => return objects.next().map(Ok),
+            QueryIterState::Streaming(stream) => stream.next(),
+            QueryIterState::Done => return None,
+        };
+        match result {
+            Ok(Some(object)) => Some(Ok(object)),
+            Ok(None) => {
+                self.state = QueryIterState::Done;
+                None
+            }
+            Err(e) => {
+                // the cursors may not be positioned correctly after an error
+                self.state = QueryIterState::Done;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.state {
+            QueryIterState::Buffered(objects) => objects.size_hint(),
+            QueryIterState::Streaming(_) => (0, None),
+            QueryIterState::Done => (0, Some(0)),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::query::Sort;
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WhereClausePlan {
+    IdRange {
+        lower: i64,
+        upper: i64,
+        sort: Sort,
+    },
+    IndexRange {
+        index_id: u16,
+        skip_duplicates: bool,
+        sort: Sort,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DistinctPlan {
+    None,
+    WhileScanning,
+    AfterScan,
+    AfterSort,
+}
+
+/// Describes how a query will be executed without touching the database.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryPlan {
+    pub where_clauses: Vec<WhereClausePlan>,
+    pub deduplicate_ids: bool,
+    pub filter: bool,
+    pub materialize_for_sort: bool,
+    pub sort_by_index: bool,
+    pub distinct: DistinctPlan,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "where clauses:")?;
+        for wc in &self.where_clauses {
+            match wc {
+                WhereClausePlan::IdRange { lower, upper, sort } => {
+                    writeln!(f, "  id range {}..={} {:?}", lower, upper, sort)?
+                }
+                WhereClausePlan::IndexRange {
+                    index_id,
+                    skip_duplicates,
+                    sort,
+                } => writeln!(
+                    f,
+                    "  index {} range {:?}{}",
+                    index_id,
+                    sort,
+                    if *skip_duplicates {
+                        " skipping duplicates"
+                    } else {
+                        ""
+                    }
+                )?,
+            }
+        }
+        writeln!(f, "deduplicate ids: {}", self.deduplicate_ids)?;
+        writeln!(f, "filter: {}", self.filter)?;
+        writeln!(f, "materialize for sort: {}", self.materialize_for_sort)?;
+        writeln!(f, "sort by index: {}", self.sort_by_index)?;
+        writeln!(f, "distinct: {:?}", self.distinct)?;
+        match self.limit {
+            Some(limit) => write!(f, "offset: {}, limit: {}", self.offset, limit),
+            None => write!(f, "offset: {}, no limit", self.offset),
+        }
+    }
+}
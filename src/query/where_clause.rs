@@ -3,6 +3,7 @@ use crate::object::isar_object::IsarObject;
 use crate::query::filter::FilterCursors;
 use crate::query::id_where_clause::IdWhereClause;
 use crate::query::index_where_clause::IndexWhereClause;
+use crate::query::query_plan::WhereClausePlan;
 use crate::txn::Cursors;
 use hashbrown::HashSet;
 
@@ -20,6 +21,27 @@ impl WhereClause {
         }
     }
 
+    pub fn reversed(&self) -> WhereClause {
+        match self {
+            WhereClause::Id(wc) => WhereClause::Id(wc.reversed()),
+            WhereClause::Index(wc) => WhereClause::Index(wc.reversed()),
+        }
+    }
+
+    pub fn plan(&self) -> WhereClausePlan {
+        match self {
+            WhereClause::Id(wc) => wc.plan(),
+            WhereClause::Index(wc) => wc.plan(),
+        }
+    }
+
+    pub fn may_contain_duplicates(&self) -> bool {
+        match self {
+            WhereClause::Id(_) => false,
+            WhereClause::Index(wc) => wc.may_contain_duplicates(),
+        }
+    }
+
     pub fn iter<'txn, 'a, F>(
         &self,
         cursors: &'a mut Cursors<'txn>,
@@ -42,4 +64,50 @@ impl WhereClause {
             ),
         }
     }
+
+    /// Returns the next matching object or `None` once the where clause is exhausted. `first`
+    /// has to be set for the first call.
+    pub fn next<'txn>(
+        &self,
+        cursors: &mut Cursors<'txn>,
+        first: bool,
+        result_ids: Option<&mut HashSet<i64>>,
+    ) -> Result<Option<IsarObject<'txn>>> {
+        match self {
+            WhereClause::Id(wc) => wc.next(&mut cursors.data, first, result_ids),
+            WhereClause::Index(wc) => {
+                wc.next(&mut cursors.data, &mut cursors.index, first, result_ids)
+            }
+        }
+    }
+
+    /// Returns the ids of the matching objects without reading the objects from index where
+    /// clauses.
+    pub fn iter_ids<F>(
+        &self,
+        cursors: &mut Cursors,
+        result_ids: Option<&mut HashSet<i64>>,
+        mut callback: F,
+    ) -> Result<bool>
+    where
+        F: FnMut(i64) -> Result<bool>,
+    {
+        match self {
+            WhereClause::Id(wc) => wc.iter(&mut cursors.data, result_ids, |_, id, _| {
+                callback(id.get_id())
+            }),
+            WhereClause::Index(wc) => {
+                let mut result_ids = result_ids;
+                wc.iter_ids(&mut cursors.index, |_, id| {
+                    let id = id.get_id();
+                    if let Some(result_ids) = result_ids.as_deref_mut() {
+                        if !result_ids.insert(id) {
+                            return Ok(true);
+                        }
+                    }
+                    callback(id)
+                })
+            }
+        }
+    }
 }
@@ -0,0 +1,179 @@
+use crate::error::{illegal_arg, Result};
+use crate::lmdb::cursor::Cursor;
+use crate::lmdb::{ByteKey, IntKey, KeyVal, MIN_ID};
+
+pub(crate) enum RawCursorScope {
+    Data(u16),
+    Index(Vec<u8>),
+}
+
+impl RawCursorScope {
+    fn contains(&self, key: &[u8]) -> bool {
+        match self {
+            RawCursorScope::Data(col_id) => {
+                key.len() == 8 && IntKey::from_bytes(key).get_prefix() == *col_id
+            }
+            RawCursorScope::Index(prefix) => key.starts_with(prefix),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum RawCursorState {
+    Unpositioned,
+    Positioned,
+    Exhausted,
+}
+
+/// Read-only cursor over the raw entries of a single collection or index. It borrows the read
+/// transaction it was created from and the returned slices must not be used after it is closed.
+pub struct RawCursor<'a, 'txn: 'a> {
+    cursor: &'a mut Cursor<'txn>,
+    scope: RawCursorScope,
+    state: RawCursorState,
+}
+
+impl<'a, 'txn: 'a> RawCursor<'a, 'txn> {
+    pub(crate) fn new(cursor: &'a mut Cursor<'txn>, scope: RawCursorScope) -> Self {
+        RawCursor {
+            cursor,
+            scope,
+            state: RawCursorState::Unpositioned,
+        }
+    }
+
+    fn check_scope(&mut self, entry: Option<KeyVal<'txn>>) -> Option<KeyVal<'a>> {
+        match entry {
+            Some((key, val)) if self.scope.contains(key) => {
+                self.state = RawCursorState::Positioned;
+                Some((key, val))
+            }
+            _ => {
+                self.state = RawCursorState::Exhausted;
+                None
+            }
+        }
+    }
+
+    pub fn seek_first(&mut self) -> Result<Option<KeyVal<'a>>> {
+        let entry = match &self.scope {
+            RawCursorScope::Data(col_id) => {
+                self.cursor.move_to_gte(IntKey::new(*col_id, MIN_ID))?
+            }
+            RawCursorScope::Index(prefix) => self.cursor.move_to_gte(ByteKey::new(prefix))?,
+        };
+        Ok(self.check_scope(entry))
+    }
+
+    pub fn seek(&mut self, key: &[u8]) -> Result<Option<KeyVal<'a>>> {
+        if !self.scope.contains(key) {
+            return illegal_arg("Key is outside of the cursor scope.");
+        }
+        let entry = self.cursor.move_to_gte(ByteKey::new(key))?;
+        Ok(self.check_scope(entry))
+    }
+
+    pub fn current(&mut self) -> Result<Option<KeyVal<'a>>> {
+        if self.state != RawCursorState::Positioned {
+            return Ok(None);
+        }
+        let entry = self.cursor.current()?;
+        Ok(self.check_scope(entry))
+    }
+
+    /// Moves to the next entry. An unpositioned cursor starts at the first entry and once the
+    /// cursor left its scope it keeps returning `None` until it is repositioned with a seek.
+    pub fn next(&mut self) -> Result<Option<KeyVal<'a>>> {
+        match self.state {
+            RawCursorState::Unpositioned => return self.seek_first(),
+            RawCursorState::Exhausted => return Ok(None),
+            RawCursorState::Positioned => {}
+        }
+        let entry = self.cursor.move_to_next()?;
+        Ok(self.check_scope(entry))
+    }
+
+    pub fn prev(&mut self) -> Result<Option<KeyVal<'a>>> {
+        if self.state != RawCursorState::Positioned {
+            return Ok(None);
+        }
+        let entry = self.cursor.move_to_prev()?;
+        Ok(self.check_scope(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object::data_type::DataType;
+    use crate::object::isar_object::IsarObject;
+    use crate::{col, ind, isar};
+
+    #[test]
+    fn test_every_other_row() {
+        isar!(isar, col => col!(oid => DataType::Long, field => DataType::Int; ind!(field)));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 1..=7 {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            builder.write_int(oid as i32 * 10);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+        assert!(col.raw_cursor(&mut txn).is_err());
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let oid_property = col.get_oid_property();
+        let mut oids = vec![];
+        {
+            let mut cursor = col.raw_cursor(&mut txn).unwrap();
+            let mut entry = cursor.seek_first().unwrap();
+            while let Some((_, val)) = entry {
+                oids.push(IsarObject::from_bytes(val).read_long(oid_property));
+                cursor.next().unwrap();
+                entry = cursor.next().unwrap();
+            }
+            assert_eq!(cursor.current().unwrap(), None);
+            assert_eq!(cursor.prev().unwrap(), None);
+        }
+        assert_eq!(oids, vec![1, 3, 5, 7]);
+
+        let mut cursor = col.raw_index_cursor(&mut txn, 0).unwrap();
+        let (first_key, _) = cursor.seek_first().unwrap().unwrap();
+        let (second_key, _) = cursor.next().unwrap().unwrap();
+        assert!(first_key < second_key);
+        assert_eq!(cursor.prev().unwrap().unwrap().0, first_key);
+        assert_eq!(cursor.prev().unwrap(), None);
+        assert_eq!(cursor.seek(second_key).unwrap().unwrap().0, second_key);
+        assert!(cursor.seek(&[]).is_err());
+
+        txn.abort();
+        isar.close();
+    }
+
+    #[test]
+    fn test_drain() {
+        isar!(isar, col => col!(oid => DataType::Long));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for oid in 1..=3 {
+            let mut builder = col.new_object_builder(None);
+            builder.write_long(oid);
+            col.put(&mut txn, builder.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let mut cursor = col.raw_cursor(&mut txn).unwrap();
+        let mut count = 0;
+        while cursor.next().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert_eq!(cursor.next().unwrap(), None);
+        assert_eq!(cursor.next().unwrap(), None);
+        assert_eq!(cursor.current().unwrap(), None);
+        assert!(cursor.seek_first().unwrap().is_some());
+
+        txn.abort();
+        isar.close();
+    }
+}
@@ -32,25 +32,28 @@ impl<'a> CollectionMigrator<'a> {
         diff_indexes
     }
 
+    pub fn adds_indexes(&self) -> bool {
+        !self.added_indexes.is_empty()
+    }
+
     pub fn migrate<'b>(self, cursors: &mut Cursors<'b>, cursors2: &mut Cursors<'b>) -> Result<()> {
         for removed_index in &self.removed_indexes {
             removed_index.clear(cursors)?;
         }
 
         if !self.added_indexes.is_empty() {
-            self.collection
-                .new_query_builder()
-                .build()
-                .find_all_internal(cursors, false, |object| {
-                    let oid = object.read_long(self.collection.get_oid_property());
-                    for index in &self.added_indexes {
-                        index.create_for_object(cursors2, oid, object, |cursors, id| {
-                            self.collection.delete_internal(cursors, true, None, id)?;
-                            Ok(())
-                        })?;
-                    }
-                    Ok(true)
-                })?;
+            let mut qb = self.collection.new_query_builder();
+            qb.skip_default_sort();
+            qb.build().find_all_internal(cursors, false, |object| {
+                let oid = object.read_long(self.collection.get_oid_property());
+                for index in &self.added_indexes {
+                    index.create_for_object(cursors2, oid, object, |cursors, id| {
+                        self.collection.delete_internal(cursors, true, None, id)?;
+                        Ok(())
+                    })?;
+                }
+                Ok(true)
+            })?;
         }
 
         Ok(())
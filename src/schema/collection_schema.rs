@@ -1,14 +1,17 @@
 use crate::collection::IsarCollection;
-use crate::error::{schema_error, Result};
-use crate::index::{Index, IndexProperty};
+use crate::error::{schema_error, schema_error_for, IsarError, Result};
+use crate::index::{Index, IndexProperty, MAX_KEY_SIZE};
 use crate::link::Link;
 use crate::object::data_type::DataType;
 use crate::object::isar_object::Property;
 use crate::object::object_info::ObjectInfo;
+use crate::query::{Case, Sort};
 use enum_ordinalize::Ordinalize;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::iter::once;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(PartialEq, Serialize, Deserialize, Clone, Debug)]
 pub struct PropertySchema {
@@ -36,6 +39,50 @@ pub enum IndexType {
     Words,
 }
 
+/// Splits the strings of `IndexType::Words` indexes into the words that are indexed.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum WordTokenizer {
+    /// Words according to the Unicode word boundaries without punctuation.
+    UnicodeWords,
+    /// Parts of the string separated by whitespace.
+    Whitespace,
+    /// All substrings of `n` characters. Shorter strings are indexed as a whole.
+    NGrams { n: usize },
+}
+
+impl Default for WordTokenizer {
+    fn default() -> Self {
+        WordTokenizer::UnicodeWords
+    }
+}
+
+impl WordTokenizer {
+    pub(crate) fn tokenize<'a>(&self, value: &'a str) -> Vec<&'a str> {
+        match self {
+            WordTokenizer::UnicodeWords => value.unicode_words().collect(),
+            WordTokenizer::Whitespace => value.split_whitespace().collect(),
+            WordTokenizer::NGrams { n } => {
+                let bounds = value
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain(once(value.len()))
+                    .collect_vec();
+                if value.is_empty() {
+                    vec![]
+                } else if bounds.len() <= *n {
+                    vec![value]
+                } else {
+                    bounds
+                        .windows(n + 1)
+                        .map(|window| &value[window[0]..window[*n]])
+                        .collect()
+                }
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct IndexPropertySchema {
     #[serde(rename = "name")]
@@ -44,6 +91,8 @@ pub struct IndexPropertySchema {
     pub(crate) index_type: IndexType,
     #[serde(rename = "caseSensitive")]
     pub(crate) case_sensitive: Option<bool>,
+    #[serde(default)]
+    pub(crate) descending: bool,
 }
 
 impl IndexPropertySchema {
@@ -51,11 +100,21 @@ impl IndexPropertySchema {
         name: &str,
         index_type: IndexType,
         case_sensitive: Option<bool>,
+    ) -> IndexPropertySchema {
+        Self::with_sort(name, index_type, case_sensitive, Sort::Ascending)
+    }
+
+    pub fn with_sort(
+        name: &str,
+        index_type: IndexType,
+        case_sensitive: Option<bool>,
+        sort: Sort,
     ) -> IndexPropertySchema {
         IndexPropertySchema {
             name: name.to_string(),
             index_type,
             case_sensitive,
+            descending: sort == Sort::Descending,
         }
     }
 }
@@ -66,6 +125,18 @@ pub struct IndexSchema {
     pub(crate) properties: Vec<IndexPropertySchema>,
     pub(crate) unique: bool,
     pub(crate) replace: bool,
+    #[serde(rename = "maxStringSize", default)]
+    pub(crate) max_string_size: Option<usize>,
+    #[serde(default)]
+    pub(crate) sparse: bool,
+    #[serde(rename = "wordTokenizer", default)]
+    pub(crate) word_tokenizer: WordTokenizer,
+    #[serde(rename = "rejectLongStrings", default)]
+    pub(crate) reject_long_strings: bool,
+    /// The version of the key encoding the index was written with. Indexes stored before the
+    /// version was recorded have version 0.
+    #[serde(rename = "keyVersion", default)]
+    pub(crate) key_version: u8,
 }
 
 impl IndexSchema {
@@ -75,6 +146,101 @@ impl IndexSchema {
             properties,
             unique,
             replace,
+            max_string_size: None,
+            sparse: false,
+            word_tokenizer: WordTokenizer::default(),
+            reject_long_strings: false,
+            key_version: INDEX_KEY_VERSION,
+        }
+    }
+
+    /// Whether the keys of the stored index were created with an encoding that has changed
+    /// since. Such indexes are rebuilt when the instance is opened.
+    fn has_outdated_keys(&self, properties: &[PropertySchema]) -> bool {
+        self.properties.iter().any(|ip| {
+            let property = properties.iter().find(|p| p.name == ip.name).unwrap();
+            // 1: case insensitive strings are case folded and hashed with their folded length
+            let folded_strings = ip.case_sensitive == Some(false)
+                && matches!(property.data_type, DataType::String | DataType::StringList);
+            // 2: -0.0 has the key of 0.0
+            let floats = matches!(
+                property.data_type,
+                DataType::Float | DataType::Double | DataType::FloatList | DataType::DoubleList
+            );
+            (self.key_version < 1 && folded_strings) || (self.key_version < 2 && floats)
+        })
+    }
+
+    /// Strings longer than `max_string_size` bytes are indexed by their prefix followed by a hash
+    /// of the full string so distinct strings with a common prefix keep distinct keys. Defaults to
+    /// the largest size that keeps the keys within `MAX_KEY_SIZE`.
+    pub fn set_max_string_size(&mut self, max_string_size: usize) {
+        self.max_string_size = Some(max_string_size);
+    }
+
+    /// The largest `max_string_size` that keeps every key of the index within `MAX_KEY_SIZE`.
+    /// Only the last index property may be a string value or words property so all other
+    /// properties have a fixed size.
+    fn get_string_size_limit(&self, properties: &[PropertySchema]) -> usize {
+        let data_type = |name: &str| {
+            properties
+                .iter()
+                .find(|p| p.name == name)
+                .unwrap()
+                .data_type
+        };
+        let (last, others) = self.properties.split_last().unwrap();
+        let others_size: usize = others
+            .iter()
+            .map(|ip| data_type(&ip.name).get_static_size())
+            .sum();
+        // the null flag and the null byte or hash of value strings and the hash of long words
+        let string_overhead = match last.index_type {
+            IndexType::Words => 1 + 8,
+            IndexType::Value if last.case_sensitive.is_some() => 2 + 8,
+            _ => 0,
+        };
+        MAX_KEY_SIZE.saturating_sub(2 + others_size + string_overhead)
+    }
+
+    /// Sparse indexes skip objects with a null value so multiple objects with null values don't
+    /// violate a unique index. Null list elements are skipped as well.
+    pub fn set_sparse(&mut self, sparse: bool) {
+        self.sparse = sparse;
+    }
+
+    /// Puts of objects with string values of at least `max_string_size` bytes fail with
+    /// `IsarError::IndexKeyTooLarge` instead of indexing the prefix followed by a hash. Only
+    /// applies to `IndexType::Value` properties.
+    pub fn set_reject_long_strings(&mut self, reject_long_strings: bool) {
+        self.reject_long_strings = reject_long_strings;
+    }
+
+    /// Only used by indexes ending with an `IndexType::Words` property.
+    pub fn set_word_tokenizer(&mut self, word_tokenizer: WordTokenizer) {
+        self.word_tokenizer = word_tokenizer;
+    }
+}
+
+fn default_case_sensitive() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct SortPropertySchema {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) descending: bool,
+    #[serde(rename = "caseSensitive", default = "default_case_sensitive")]
+    pub(crate) case_sensitive: bool,
+}
+
+impl SortPropertySchema {
+    pub fn new(name: &str, sort: Sort, case: Case) -> SortPropertySchema {
+        SortPropertySchema {
+            name: name.to_string(),
+            descending: sort == Sort::Descending,
+            case_sensitive: case == Case::Sensitive,
         }
     }
 }
@@ -100,6 +266,16 @@ impl LinkSchema {
     }
 }
 
+pub const DEFAULT_MAX_INDEXES: usize = 64;
+
+/// The current version of the index key encoding. It has to be increased whenever the keys
+/// created for existing values change.
+const INDEX_KEY_VERSION: u8 = 2;
+
+fn default_max_indexes() -> usize {
+    DEFAULT_MAX_INDEXES
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CollectionSchema {
     pub(crate) id: Option<u16>,
@@ -109,6 +285,12 @@ pub struct CollectionSchema {
     pub(crate) properties: Vec<PropertySchema>,
     pub(crate) indexes: Vec<IndexSchema>,
     pub(crate) links: Vec<LinkSchema>,
+    #[serde(rename = "defaultSort", default)]
+    pub(crate) default_sort: Vec<SortPropertySchema>,
+    #[serde(skip, default = "default_max_indexes")]
+    pub(crate) max_indexes: usize,
+    #[serde(skip)]
+    pub(crate) property_renames: Vec<(String, String)>,
 }
 
 impl CollectionSchema {
@@ -126,13 +308,35 @@ impl CollectionSchema {
             properties,
             indexes,
             links,
+            default_sort: vec![],
+            max_indexes: DEFAULT_MAX_INDEXES,
+            property_renames: vec![],
         }
     }
 
+    pub fn set_max_indexes(&mut self, max_indexes: usize) {
+        self.max_indexes = max_indexes;
+    }
+
+    /// Sorts the results of queries without an explicit sort by `property`. Multiple default
+    /// sorts are applied in the order they were added.
+    pub fn add_default_sort(&mut self, property: &str, sort: Sort, case: Case) {
+        self.default_sort
+            .push(SortPropertySchema::new(property, sort, case));
+    }
+
+    /// Treats the property `new_name` as the existing property `old_name` when the schema is
+    /// updated, so its data and indexes are kept if the data type did not change.
+    pub fn rename_property(&mut self, old_name: &str, new_name: &str) {
+        self.property_renames
+            .push((old_name.to_string(), new_name.to_string()));
+    }
+
     pub(crate) fn verify(&mut self) -> Result<()> {
         if self.name.is_empty() {
             schema_error("Empty collection names are not allowed")?;
         }
+        let name = &self.name;
 
         let properties_link_names = self
             .properties
@@ -140,63 +344,110 @@ impl CollectionSchema {
             .map(|p| &p.name)
             .chain(self.links.iter().map(|l| &l.name));
         if properties_link_names.unique().count() < self.properties.len() + self.links.len() {
-            schema_error("Duplicate property or link name")?;
+            schema_error_for(name, None, "Duplicate property or link name")?;
         }
 
         let mut has_oid = false;
-        for property in &mut self.properties {
+        for property in &self.properties {
             if property.name.is_empty() {
-                schema_error("Empty property names are not allowed")?;
+                schema_error_for(name, None, "Empty property names are not allowed")?;
             }
             if property.name == self.id_property {
                 if property.data_type != DataType::Long {
-                    schema_error("Illegal ObjectId type")?;
+                    schema_error_for(name, Some(&property.name), "Illegal ObjectId type")?;
                 }
                 has_oid = true;
             }
-            property.offset = None
         }
         if !has_oid {
-            schema_error("Unknown ObjectId property")?;
+            schema_error_for(name, Some(&self.id_property), "Unknown ObjectId property")?;
+        }
+        for (_, new_name) in &self.property_renames {
+            if !self.properties.iter().any(|p| &p.name == new_name) {
+                schema_error_for(name, Some(new_name), "Renamed property does not exist")?;
+            }
+        }
+
+        for sort_property in &self.default_sort {
+            let property = self
+                .properties
+                .iter()
+                .find(|p| p.name == sort_property.name);
+            if let Some(property) = property {
+                if property.data_type.is_list() {
+                    schema_error_for(
+                        name,
+                        Some(&sort_property.name),
+                        "Lists cannot be used as default sort property",
+                    )?;
+                }
+            } else {
+                schema_error_for(
+                    name,
+                    Some(&sort_property.name),
+                    "Default sort property does not exist",
+                )?;
+            }
+        }
+
+        if self.indexes.len() > self.max_indexes {
+            return Err(IsarError::TooManyIndexes {});
         }
 
         for index in &self.indexes {
             if index.properties.is_empty() {
-                schema_error("At least one property needs to be added to a valid index")?;
+                schema_error_for(
+                    name,
+                    None,
+                    "At least one property needs to be added to a valid index",
+                )?;
             } else if index.properties.len() > 3 {
-                schema_error("No more than three properties may be used as a composite index")?;
+                schema_error_for(
+                    name,
+                    None,
+                    "No more than three properties may be used as a composite index",
+                )?;
             }
 
             for (i, index_property) in index.properties.iter().enumerate() {
+                let property_error =
+                    |msg: &str| schema_error_for(name, Some(&index_property.name), msg);
                 let property = self
                     .properties
                     .iter()
                     .find(|p| p.name == index_property.name)
                     .cloned();
                 if property.is_none() {
-                    schema_error("Index property does not exist")?;
+                    property_error("Index property does not exist")?;
                 }
                 let property = property.unwrap();
 
-                if property.data_type.is_dynamic() && property.data_type != DataType::String {
-                    schema_error("Illegal index data type")?;
+                let is_list = property.data_type.is_list();
+                if is_list && i != index.properties.len() - 1 {
+                    property_error("List indexes must only be at the end of a composite index.")?;
                 }
 
-                if property.data_type != DataType::String
-                    && index_property.index_type != IndexType::Value
-                {
-                    schema_error("Non string indexes must use IndexType::Value")?;
+                let is_string =
+                    matches!(property.data_type, DataType::String | DataType::StringList);
+                if !is_string && index_property.index_type != IndexType::Value {
+                    property_error("Non string indexes must use IndexType::Value")?;
+                }
+                if is_list && index_property.index_type == IndexType::Words {
+                    property_error("List indexes must not use IndexType::Words")?;
                 }
-                if (property.data_type == DataType::String)
-                    != index_property.case_sensitive.is_some()
-                {
-                    schema_error("Only String indexes must have case sensitivity.")?;
+                if is_string != index_property.case_sensitive.is_some() {
+                    property_error("Only String indexes must have case sensitivity.")?;
+                }
+                if index_property.descending && index_property.index_type != IndexType::Value {
+                    property_error("Only value indexes may be descending.")?;
                 }
 
                 match index_property.index_type {
-                    IndexType::Value | IndexType::Words => {
+                    IndexType::Value | IndexType::Words
+                        if property.data_type == DataType::String =>
+                    {
                         if i != index.properties.len() - 1 {
-                            schema_error(
+                            property_error(
                                 "Value and word string indexes must only be at the end of a composite index.",
                             )?;
                         }
@@ -204,11 +455,49 @@ impl CollectionSchema {
                     _ => {}
                 }
             }
+
+            if let Some(max_string_size) = index.max_string_size {
+                if max_string_size > index.get_string_size_limit(&self.properties) {
+                    schema_error_for(
+                        name,
+                        None,
+                        "The maximum string size exceeds the maximum index key size.",
+                    )?;
+                }
+            }
+
+            if index.word_tokenizer != WordTokenizer::default() {
+                let words =
+                    index.properties.last().map(|ip| ip.index_type) == Some(IndexType::Words);
+                if !words {
+                    schema_error_for(name, None, "Only word indexes may use a word tokenizer.")?;
+                }
+            }
+            if index.reject_long_strings {
+                let string_value = index
+                    .properties
+                    .iter()
+                    .any(|ip| ip.index_type == IndexType::Value && ip.case_sensitive.is_some());
+                if !string_value {
+                    schema_error_for(
+                        name,
+                        None,
+                        "Only indexes with string value properties may reject long strings.",
+                    )?;
+                }
+            }
+            if index.word_tokenizer == (WordTokenizer::NGrams { n: 0 }) {
+                schema_error_for(
+                    name,
+                    None,
+                    "N-grams need to contain at least one character.",
+                )?;
+            }
         }
 
         for link in &self.links {
             if link.name.is_empty() {
-                schema_error("Empty link names are not allowed")?;
+                schema_error_for(name, None, "Empty link names are not allowed")?;
             }
         }
 
@@ -226,6 +515,23 @@ impl CollectionSchema {
             .find(|(name, _)| name == &self.id_property)
             .unwrap();
 
+        let default_sort = self
+            .default_sort
+            .iter()
+            .map(|sps| {
+                let (_, property) = properties
+                    .iter()
+                    .find(|(name, _)| name == &sps.name)
+                    .unwrap();
+                let sort = if sps.descending {
+                    Sort::Descending
+                } else {
+                    Sort::Ascending
+                };
+                (*property, sort, sps.case_sensitive)
+            })
+            .collect();
+
         let oi = ObjectInfo::new(*id_property, properties);
         IsarCollection::new(
             self.id.unwrap(),
@@ -234,6 +540,7 @@ impl CollectionSchema {
             indexes,
             links,
             backlinks,
+            default_sort,
         )
     }
 
@@ -259,7 +566,12 @@ impl CollectionSchema {
                             .iter()
                             .find(|(name, _)| name == &ips.name)
                             .unwrap();
-                        IndexProperty::new(*property, ips.index_type, ips.case_sensitive)
+                        IndexProperty::new(
+                            *property,
+                            ips.index_type,
+                            ips.case_sensitive,
+                            ips.descending,
+                        )
                     })
                     .collect_vec();
 
@@ -269,6 +581,12 @@ impl CollectionSchema {
                     properties,
                     index.unique,
                     index.replace,
+                    index
+                        .max_string_size
+                        .unwrap_or_else(|| index.get_string_size_limit(&self.properties)),
+                    index.sparse,
+                    index.word_tokenizer,
+                    index.reject_long_strings,
                 )
             })
             .collect()
@@ -323,22 +641,40 @@ impl CollectionSchema {
             .unwrap_or(2)
     }
 
+    /// `renames` maps the names of renamed properties in `properties1` to their names in
+    /// `properties2`.
     fn check_indexes_equal(
         index1: &IndexSchema,
         properties1: &[PropertySchema],
         index2: &IndexSchema,
         properties2: &[PropertySchema],
+        renames: &[(String, String)],
     ) -> bool {
-        if index1.unique != index2.unique || index1.properties.len() != index2.properties.len() {
+        if index1.unique != index2.unique
+            || index2.has_outdated_keys(properties2)
+            || index1.max_string_size != index2.max_string_size
+            || index1.sparse != index2.sparse
+            || index1.word_tokenizer != index2.word_tokenizer
+            || index1.reject_long_strings != index2.reject_long_strings
+            || index1.properties.len() != index2.properties.len()
+        {
             return false;
         }
         for (ip1, ip2) in index1.properties.iter().zip(index2.properties.iter()) {
-            if ip1 != ip2 {
+            let p1 = properties1.iter().find(|p| p.name == ip1.name).unwrap();
+            let previous_name = renames
+                .iter()
+                .find(|(new_name, _)| *new_name == ip1.name)
+                .map_or(&ip1.name, |(_, old_name)| old_name);
+            let ip1 = IndexPropertySchema {
+                name: previous_name.clone(),
+                ..ip1.clone()
+            };
+            if &ip1 != ip2 {
                 return false;
             }
-            let p1 = properties1.iter().find(|p| p.name == ip1.name).unwrap();
             let p2 = properties2.iter().find(|p| p.name == ip2.name).unwrap();
-            if p1 != p2 {
+            if p1.data_type != p2.data_type || p1.offset != p2.offset {
                 return false;
             }
         }
@@ -353,7 +689,11 @@ impl CollectionSchema {
         if let Some(existing_col) = existing_col {
             self.id = existing_col.id;
             if existing_col.id_property != self.id_property {
-                return schema_error("The id property must not change between versions.");
+                return schema_error_for(
+                    &self.name,
+                    Some(&self.id_property),
+                    "The id property must not change between versions.",
+                );
             }
         } else {
             self.id = Some(get_id());
@@ -361,9 +701,49 @@ impl CollectionSchema {
 
         let existing_properties: &[PropertySchema] = existing_col.map_or(&[], |e| &e.properties);
         let mut next_offset = Self::find_next_offset(existing_properties);
+        // the old name of a renamed property may be reused for a new property
+        let renamed_names = self
+            .property_renames
+            .iter()
+            .filter(|(old_name, new_name)| {
+                self.properties.iter().any(|p| {
+                    &p.name == new_name
+                        && existing_properties
+                            .iter()
+                            .any(|e| &e.name == old_name && e.data_type == p.data_type)
+                })
+            })
+            .map(|(old_name, _)| old_name.clone())
+            .collect_vec();
+        let mut renames = vec![];
         for property in &mut self.properties {
-            let existing_property = existing_properties.iter().find(|i| i.name == property.name);
+            let renamed_property = self
+                .property_renames
+                .iter()
+                .filter(|(_, new_name)| *new_name == property.name)
+                .find_map(|(old_name, _)| {
+                    existing_properties
+                        .iter()
+                        .find(|p| &p.name == old_name && p.data_type == property.data_type)
+                });
+            let existing_property = if let Some(renamed_property) = renamed_property {
+                renames.push((property.name.clone(), renamed_property.name.clone()));
+                Some(renamed_property)
+            } else {
+                existing_properties
+                    .iter()
+                    .find(|i| i.name == property.name && !renamed_names.contains(&i.name))
+            };
             if let Some(existing_property) = existing_property {
+                // the stored values would be read as the new type
+                if existing_property.data_type.storage_type() != property.data_type.storage_type() {
+                    let message = format!(
+                        "The type changed from {:?} to {:?}. Existing objects have to be \
+                        migrated to a new property.",
+                        existing_property.data_type, property.data_type
+                    );
+                    return schema_error_for(&self.name, Some(&property.name), &message);
+                }
                 property.offset = existing_property.offset;
             } else {
                 property.offset = Some(next_offset);
@@ -374,9 +754,10 @@ impl CollectionSchema {
         let existing_indexes: &[IndexSchema] = existing_col.map_or(&[], |e| &e.indexes);
         let properties = &self.properties;
         for index in &mut self.indexes {
-            let existing_index = existing_indexes
-                .iter()
-                .find(|i| Self::check_indexes_equal(index, properties, i, existing_properties));
+            index.key_version = INDEX_KEY_VERSION;
+            let existing_index = existing_indexes.iter().find(|i| {
+                Self::check_indexes_equal(index, properties, i, existing_properties, &renames)
+            });
             if let Some(existing_index) = existing_index {
                 index.id = existing_index.id;
             } else {
@@ -601,3 +982,164 @@ mod tests {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use crate::error::IsarError;
+    use crate::index::MAX_KEY_SIZE;
+    use crate::object::data_type::DataType;
+    use crate::schema::collection_schema::{IndexType, WordTokenizer, INDEX_KEY_VERSION};
+    use crate::schema::Schema;
+    use crate::{col, ind};
+
+    #[test]
+    fn test_too_many_indexes() {
+        let mut col = col!(oid => DataType::Long, f1 => DataType::Int, f2 => DataType::Int; ind!(f1), ind!(f2), ind!(f1, f2));
+        col.set_max_indexes(3);
+        assert!(Schema::new(vec![col.clone()]).is_ok());
+
+        col.set_max_indexes(2);
+        match Schema::new(vec![col]) {
+            Err(IsarError::TooManyIndexes {}) => {}
+            _ => panic!("wrong error"),
+        }
+    }
+
+    #[test]
+    fn test_max_string_size_limit() {
+        let schema = |max_string_size: usize| {
+            let mut col = col!(oid => DataType::Long, f1 => DataType::Long, f2 => DataType::String; ind!(str f1, IndexType::Value, None, f2, IndexType::Value, Some(true)));
+            col.indexes[0].set_max_string_size(max_string_size);
+            col
+        };
+        let limit = MAX_KEY_SIZE - 2 - 8 - 10;
+        assert!(Schema::new(vec![schema(limit)]).is_ok());
+        assert!(Schema::new(vec![schema(limit + 1)]).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_outdated_index_keys() {
+        let schema = || {
+            let col = col!(oid => DataType::Long, s => DataType::String, i => DataType::Int, f => DataType::Double; ind!(str s, IndexType::Value, Some(false)), ind!(i), ind!(f));
+            Schema::new(vec![col]).unwrap()
+        };
+        let mut counter = 0;
+        let mut get_id = || {
+            counter += 1;
+            counter
+        };
+        let mut existing = schema();
+        existing
+            .update_with_existing_schema_and_ids(None, &mut get_id)
+            .unwrap();
+        let index_ids = |schema: &Schema| {
+            let indexes = &schema.collections[0].indexes;
+            (indexes[0].id.unwrap(), indexes[1].id.unwrap())
+        };
+        let (string_id, int_id) = index_ids(&existing);
+        let float_id = existing.collections[0].indexes[2].id.unwrap();
+
+        let mut current = schema();
+        current
+            .update_with_existing_schema_and_ids(Some(&existing), &mut get_id)
+            .unwrap();
+        assert_eq!(index_ids(&current), (string_id, int_id));
+
+        let mut outdated = existing.clone();
+        for index in &mut outdated.collections[0].indexes {
+            index.key_version = 0;
+        }
+        let mut current = schema();
+        current
+            .update_with_existing_schema_and_ids(Some(&outdated), &mut get_id)
+            .unwrap();
+        let (new_string_id, new_int_id) = index_ids(&current);
+        assert_ne!(new_string_id, string_id);
+        assert_eq!(new_int_id, int_id);
+
+        // float keys changed in version 2, string keys did not
+        for index in &mut outdated.collections[0].indexes {
+            index.key_version = 1;
+        }
+        let mut current = schema();
+        current
+            .update_with_existing_schema_and_ids(Some(&outdated), &mut get_id)
+            .unwrap();
+        let (new_string_id, _) = index_ids(&current);
+        let new_float_id = current.collections[0].indexes[2].id.unwrap();
+        assert_eq!(new_string_id, string_id);
+        assert_ne!(new_float_id, float_id);
+        assert!(current.collections[0]
+            .indexes
+            .iter()
+            .all(|index| index.key_version == INDEX_KEY_VERSION));
+    }
+
+    #[test]
+    fn test_word_tokenizer() {
+        let value = "Hello, wörld  it's";
+        assert_eq!(
+            WordTokenizer::UnicodeWords.tokenize(value),
+            vec!["Hello", "wörld", "it's"]
+        );
+        assert_eq!(
+            WordTokenizer::Whitespace.tokenize(value),
+            vec!["Hello,", "wörld", "it's"]
+        );
+        assert_eq!(
+            WordTokenizer::NGrams { n: 3 }.tokenize("wörld"),
+            vec!["wör", "örl", "rld"]
+        );
+        assert_eq!(WordTokenizer::NGrams { n: 3 }.tokenize("wö"), vec!["wö"]);
+        assert!(WordTokenizer::NGrams { n: 3 }.tokenize("").is_empty());
+        assert!(WordTokenizer::Whitespace.tokenize(" ").is_empty());
+    }
+
+    #[test]
+    fn test_word_tokenizer_schema() {
+        let mut col = col!(oid => DataType::Long, f1 => DataType::String; ind!(str f1, IndexType::Words, Some(false)));
+        col.indexes[0].set_word_tokenizer(WordTokenizer::NGrams { n: 2 });
+        assert!(Schema::new(vec![col.clone()]).is_ok());
+
+        col.indexes[0].set_word_tokenizer(WordTokenizer::NGrams { n: 0 });
+        assert!(Schema::new(vec![col]).is_err());
+
+        let mut col = col!(oid => DataType::Long, f1 => DataType::String; ind!(str f1, IndexType::Value, Some(false)));
+        col.indexes[0].set_word_tokenizer(WordTokenizer::Whitespace);
+        assert!(Schema::new(vec![col]).is_err());
+    }
+
+    #[test]
+    fn test_id_generator() {
+        fn build() -> Schema {
+            let col1 = col!("col1", oid => DataType::Long, f1 => DataType::Int; ind!(f1));
+            let col2 = col!("col2", oid => DataType::Long, f1 => DataType::Long; ind!(f1));
+            let mut schema = Schema::new(vec![col1, col2]).unwrap();
+            let mut counter = 0;
+            let mut get_id = || {
+                counter += 1;
+                counter
+            };
+            schema
+                .update_with_existing_schema_and_ids(None, &mut get_id)
+                .unwrap();
+            schema
+        }
+
+        let schema = build();
+        assert_eq!(schema.to_json().unwrap(), build().to_json().unwrap());
+        assert_eq!(schema.collections[0].id, Some(1));
+        assert_eq!(schema.collections[0].indexes[0].id, Some(2));
+        assert_eq!(schema.collections[1].id, Some(3));
+        assert_eq!(schema.collections[1].indexes[0].id, Some(4));
+
+        let mut schema2 = Schema::new(vec![col!("col3", oid => DataType::Long)]).unwrap();
+        let mut ids = vec![1, 3, 5].into_iter();
+        let mut next_id = || ids.next().unwrap();
+        schema2
+            .update_with_existing_schema_and_ids(Some(&schema), &mut next_id)
+            .unwrap();
+        // ids used by the existing schema are skipped
+        assert_eq!(schema2.collections[0].id, Some(5));
+    }
+}
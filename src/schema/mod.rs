@@ -10,6 +10,18 @@ use itertools::Itertools;
 use rand::random;
 use serde::{Deserialize, Serialize};
 
+/// Source of the ids assigned to new collections, indexes and links. Closures returning a `u16`
+/// can be used as generators.
+pub trait IdGenerator {
+    fn next_id(&mut self) -> u16;
+}
+
+impl<F: FnMut() -> u16> IdGenerator for F {
+    fn next_id(&mut self) -> u16 {
+        self()
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Schema {
     collections: Vec<CollectionSchema>,
@@ -22,20 +34,22 @@ impl Schema {
         Ok(schema)
     }
 
+    /// Ids and property offsets are kept so a schema exported with `to_json()` round-trips.
+    /// They are reassigned when the schema is used to open an instance.
     pub fn from_json(json: &[u8]) -> Result<Schema> {
-        if let Ok(mut collections) = serde_json::from_slice::<Vec<CollectionSchema>>(json) {
-            for col in &mut collections {
-                col.id = None;
-                for index in &mut col.indexes {
-                    index.id = None;
-                }
-            }
+        if let Ok(collections) = serde_json::from_slice::<Vec<CollectionSchema>>(json) {
             Schema::new(collections)
         } else {
             schema_error("Could not deserialize schema JSON")
         }
     }
 
+    /// Returns the collections as JSON in the format accepted by `from_json()`, including the
+    /// ids and property offsets assigned to an existing schema.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.collections)?)
+    }
+
     fn verify(&mut self) -> Result<()> {
         if self.collections.iter().unique_by(|c| &c.name).count() != self.collections.len() {
             return schema_error("Duplicate collections");
@@ -68,10 +82,12 @@ impl Schema {
         ids
     }
 
-    fn update_with_existing_schema_internal(
+    /// Like `update_with_existing_schema()` but new ids are taken from `id_generator`. Ids that
+    /// are already in use are skipped.
+    pub fn update_with_existing_schema_and_ids(
         &mut self,
         existing_schema: Option<&Schema>,
-        mut random: impl FnMut() -> u16,
+        id_generator: &mut impl IdGenerator,
     ) -> Result<()> {
         let mut ids = if let Some(existing_schema) = existing_schema {
             existing_schema.collect_ids()
@@ -80,7 +96,7 @@ impl Schema {
         };
 
         let mut get_id = || loop {
-            let id = random();
+            let id = id_generator.next_id();
             if ids.insert(id) {
                 return id;
             }
@@ -96,7 +112,7 @@ impl Schema {
     }
 
     pub fn update_with_existing_schema(&mut self, existing_schema: Option<&Schema>) -> Result<()> {
-        self.update_with_existing_schema_internal(existing_schema, random)
+        self.update_with_existing_schema_and_ids(existing_schema, &mut random)
     }
 }
 
@@ -1,4 +1,4 @@
-use crate::error::{IsarError, Result};
+use crate::error::{schema_error, schema_error_for, IsarError, Result};
 use crate::lmdb::cursor::Cursor;
 use crate::lmdb::{ByteKey, IntKey, MIN_ID};
 use crate::query::Sort;
@@ -16,14 +16,21 @@ pub(crate) struct SchemaManger<'env> {
     info_cursor: Cursor<'env>,
     cursors: Cursors<'env>,
     cursors2: Cursors<'env>,
+    read_only: bool,
 }
 
 impl<'env> SchemaManger<'env> {
-    pub fn new(info_cursor: Cursor<'env>, cursors: Cursors<'env>, cursors2: Cursors<'env>) -> Self {
+    pub fn new(
+        info_cursor: Cursor<'env>,
+        cursors: Cursors<'env>,
+        cursors2: Cursors<'env>,
+        read_only: bool,
+    ) -> Self {
         SchemaManger {
             info_cursor,
             cursors,
             cursors2,
+            read_only,
         }
     }
 
@@ -34,6 +41,8 @@ impl<'env> SchemaManger<'env> {
             if version_num != ISAR_VERSION {
                 return Err(IsarError::VersionError {});
             }
+        } else if self.read_only {
+            return schema_error("Read-only instances require an existing database.");
         } else {
             let version_bytes = &ISAR_VERSION.to_le_bytes();
             self.info_cursor.put(INFO_VERSION_KEY, version_bytes)?;
@@ -41,15 +50,24 @@ impl<'env> SchemaManger<'env> {
         Ok(())
     }
 
-    pub fn get_collections(mut self, mut schema: Schema) -> Result<Vec<IsarCollection>> {
-        let existing_schema_bytes = self.info_cursor.move_to(INFO_SCHEMA_KEY)?;
-
-        let existing_collections = if let Some((_, existing_schema_bytes)) = existing_schema_bytes {
-            let existing_schema = serde_json::from_slice(existing_schema_bytes).map_err(|e| {
-                IsarError::DbCorrupted {
+    pub fn read_schema(info_cursor: &mut Cursor) -> Result<Option<Schema>> {
+        if let Some((_, schema_bytes)) = info_cursor.move_to(INFO_SCHEMA_KEY)? {
+            let schema =
+                serde_json::from_slice(schema_bytes).map_err(|e| IsarError::DbCorrupted {
                     message: format!("Could not deserialize existing schema: {}", e),
-                }
-            })?;
+                })?;
+            Ok(Some(schema))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn get_collections(mut self, mut schema: Schema) -> Result<Vec<IsarCollection>> {
+        let existing_schema = Self::read_schema(&mut self.info_cursor)?;
+        if self.read_only {
+            return self.get_collections_read_only(schema, existing_schema);
+        }
+        let existing_collections = if let Some(existing_schema) = existing_schema {
             schema.update_with_existing_schema(Some(&existing_schema))?;
             existing_schema.build_collections()
         } else {
@@ -67,6 +85,39 @@ impl<'env> SchemaManger<'env> {
         Ok(collections)
     }
 
+    /// The schema is not saved and existing collections must not require new indexes. Data of
+    /// removed collections and indexes is kept.
+    fn get_collections_read_only(
+        mut self,
+        mut schema: Schema,
+        existing_schema: Option<Schema>,
+    ) -> Result<Vec<IsarCollection>> {
+        let existing_schema = if let Some(existing_schema) = existing_schema {
+            existing_schema
+        } else {
+            return schema_error("Read-only instances require an existing database.");
+        };
+        schema.update_with_existing_schema(Some(&existing_schema))?;
+        let existing_collections = existing_schema.build_collections();
+        let collections = schema.build_collections();
+        for collection in &collections {
+            let existing = existing_collections
+                .iter()
+                .find(|existing| existing.get_id() == collection.get_id());
+            if let Some(existing) = existing {
+                if CollectionMigrator::create(collection, existing).adds_indexes() {
+                    return schema_error_for(
+                        collection.get_name(),
+                        None,
+                        "Indexes cannot be added to a read-only instance.",
+                    );
+                }
+            }
+            self.update_oid_counter(collection)?;
+        }
+        Ok(collections)
+    }
+
     fn update_oid_counter(&mut self, collection: &IsarCollection) -> Result<()> {
         let col_id = collection.get_id();
         let next_key = IntKey::new(col_id + 1, MIN_ID);
@@ -0,0 +1,35 @@
+use crate::collection::IsarCollection;
+use crate::error::Result;
+use crate::object::isar_object::IsarObject;
+use crate::query::Query;
+use crate::txn::IsarTxn;
+
+/// Keeps a read transaction open so multiple queries see the same version of the database.
+///
+/// LMDB cannot reuse pages freed by later writes while a snapshot is open, so the database file
+/// may grow until the snapshot is closed or dropped.
+pub struct Snapshot<'a> {
+    txn: IsarTxn<'a>,
+}
+
+impl<'a> Snapshot<'a> {
+    pub(crate) fn new(txn: IsarTxn<'a>) -> Self {
+        Snapshot { txn }
+    }
+
+    pub fn get(&mut self, collection: &IsarCollection, oid: i64) -> Result<Option<IsarObject>> {
+        collection.get(&mut self.txn, oid)
+    }
+
+    pub fn find_all_vec(&mut self, query: &Query) -> Result<Vec<IsarObject>> {
+        query.find_all_vec(&mut self.txn)
+    }
+
+    pub fn count(&mut self, query: &Query) -> Result<u32> {
+        query.count(&mut self.txn)
+    }
+
+    pub fn close(self) {
+        self.txn.abort()
+    }
+}
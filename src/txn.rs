@@ -1,8 +1,13 @@
-use crate::error::{IsarError, Result};
+use crate::error::{illegal_arg, IsarError, Result};
 use crate::instance::IsarInstance;
 use crate::lmdb::cursor::Cursor;
 use crate::lmdb::txn::Txn;
 use crate::watch::change_set::ChangeSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 
 pub struct IsarTxn<'a> {
     txn: Option<Txn<'a>>,
@@ -10,6 +15,8 @@ pub struct IsarTxn<'a> {
     write: bool,
     change_set: Option<ChangeSet<'a>>,
     cursors: Option<Cursors<'a>>,
+    write_txn_active: Option<&'a AtomicBool>,
+    open_txns: &'a AtomicUsize,
 }
 
 #[derive(Clone)]
@@ -30,6 +37,12 @@ impl<'a> IsarTxn<'a> {
     ) -> Result<Self> {
         let cursors = isar.open_cursors(&txn)?;
         let cursors: Cursors<'static> = unsafe { std::mem::transmute(cursors) };
+        let write_txn_active = if write {
+            isar.write_txn_active.store(true, Ordering::SeqCst);
+            Some(&isar.write_txn_active)
+        } else {
+            None
+        };
 
         Ok(IsarTxn {
             txn: Some(txn),
@@ -37,6 +50,8 @@ impl<'a> IsarTxn<'a> {
             write,
             change_set,
             cursors: Some(cursors),
+            write_txn_active,
+            open_txns: &isar.open_txns,
         })
     }
 
@@ -44,10 +59,29 @@ impl<'a> IsarTxn<'a> {
     where
         F: FnOnce(&mut Cursors<'a>) -> Result<T>,
     {
-        if self.write && self.change_set.is_none() {
+        // silent write txns have no change set but may still be read from
+        job(self.cursors())
+    }
+
+    pub(crate) fn is_write(&self) -> bool {
+        self.write
+    }
+
+    pub(crate) fn change_set(&mut self) -> Option<&mut ChangeSet<'a>> {
+        self.change_set.as_mut()
+    }
+
+    pub(crate) fn cursors(&mut self) -> &mut Cursors<'a> {
+        self.cursors.as_mut().unwrap()
+    }
+
+    pub(crate) fn read_only_cursors(&mut self) -> Result<&mut Cursors<'a>> {
+        if self.write {
+            illegal_arg("Read transaction required.")
+        } else if !self.active {
             Err(IsarError::TransactionClosed {})
         } else {
-            job(self.cursors.as_mut().unwrap())
+            Ok(self.cursors.as_mut().unwrap())
         }
     }
 
@@ -94,5 +128,82 @@ impl<'a> Drop for IsarTxn<'a> {
             self.cursors.take(); // drop before txn
             self.txn.take().unwrap().abort();
         }
+        if let Some(write_txn_active) = self.write_txn_active {
+            write_txn_active.store(false, Ordering::SeqCst);
+        }
+        self.open_txns.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct TxnFutureState<T> {
+    result: Option<Result<T>>,
+    completed: bool,
+    waker: Option<Waker>,
+}
+
+/// Resolves to the result of a transaction started with `IsarInstance::begin_txn_async()`.
+pub struct TxnFuture<T> {
+    state: Arc<Mutex<TxnFutureState<T>>>,
+}
+
+pub(crate) struct TxnCompleter<T> {
+    state: Arc<Mutex<TxnFutureState<T>>>,
+}
+
+impl<T> TxnFuture<T> {
+    pub(crate) fn new() -> (TxnFuture<T>, TxnCompleter<T>) {
+        let state = Arc::new(Mutex::new(TxnFutureState {
+            result: None,
+            completed: false,
+            waker: None,
+        }));
+        let completer = TxnCompleter {
+            state: state.clone(),
+        };
+        (TxnFuture { state }, completer)
+    }
+}
+
+impl<T> Future for TxnFuture<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(result) = state.result.take() {
+            Poll::Ready(result)
+        } else if state.completed {
+            Poll::Ready(Err(IsarError::TransactionClosed {}))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> TxnCompleter<T> {
+    pub fn complete(self, result: Result<T>) {
+        self.complete_internal(Some(result));
+    }
+
+    fn complete_internal(&self, result: Option<Result<T>>) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            if state.completed {
+                return;
+            }
+            state.result = result;
+            state.completed = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for TxnCompleter<T> {
+    fn drop(&mut self) {
+        // the job panicked before completing the future
+        self.complete_internal(None);
     }
 }
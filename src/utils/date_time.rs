@@ -0,0 +1,218 @@
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Formats milliseconds since the epoch as an ISO-8601 UTC timestamp.
+pub(crate) fn format_iso8601(millis: i64) -> String {
+    let (year, month, day) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+    let time = millis.rem_euclid(MILLIS_PER_DAY);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3_600_000,
+        time / 60_000 % 60,
+        time / 1000 % 60,
+        time % 1000
+    )
+}
+
+/// Parses an ISO-8601 date or timestamp into milliseconds since the epoch. Timestamps without
+/// an offset are treated as UTC and fractional seconds are truncated to milliseconds.
+pub(crate) fn parse_iso8601(value: &str) -> Option<i64> {
+    let mut parser = Parser {
+        bytes: value.as_bytes(),
+        pos: 0,
+    };
+    let negative_year = parser.accept(b'-');
+    let year = parser.number(4, 6)?;
+    let year = if negative_year { -year } else { year };
+    parser.expect(b'-')?;
+    let month = parser.number(2, 2)?;
+    parser.expect(b'-')?;
+    let day = parser.number(2, 2)?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let mut millis = 0;
+    if parser.accept(b'T') || parser.accept(b' ') {
+        let hour = parser.number(2, 2)?;
+        parser.expect(b':')?;
+        let minute = parser.number(2, 2)?;
+        let second = if parser.accept(b':') {
+            parser.number(2, 2)?
+        } else {
+            0
+        };
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        millis = (hour * 3600 + minute * 60 + second) * 1000;
+        if parser.accept(b'.') || parser.accept(b',') {
+            let start = parser.pos;
+            let fraction = parser.number(1, 9)?;
+            let digits = (parser.pos - start) as u32;
+            millis += if digits >= 3 {
+                fraction / 10i64.pow(digits - 3)
+            } else {
+                fraction * 10i64.pow(3 - digits)
+            };
+        }
+        if !parser.accept(b'Z') {
+            let sign = if parser.accept(b'+') {
+                -1
+            } else if parser.accept(b'-') {
+                1
+            } else {
+                0
+            };
+            if sign != 0 {
+                let offset_hour = parser.number(2, 2)?;
+                parser.accept(b':');
+                let offset_minute = parser.number(2, 2)?;
+                millis += sign * (offset_hour * 60 + offset_minute) * 60_000;
+            }
+        }
+    }
+    if parser.pos != parser.bytes.len() {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * MILLIS_PER_DAY + millis)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn accept(&mut self, byte: u8) -> bool {
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        if self.accept(byte) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self, min_digits: usize, max_digits: usize) -> Option<i64> {
+        let start = self.pos;
+        let mut value = 0i64;
+        while self.pos - start < max_digits {
+            match self.bytes.get(self.pos) {
+                Some(byte) if byte.is_ascii_digit() => {
+                    value = value * 10 + (byte - b'0') as i64;
+                    self.pos += 1;
+                }
+                _ => break,
+            }
+        }
+        if self.pos - start >= min_digits {
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_iso8601() {
+        assert_eq!(format_iso8601(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(format_iso8601(-1), "1969-12-31T23:59:59.999Z");
+        assert_eq!(format_iso8601(951_782_400_123), "2000-02-29T00:00:00.123Z");
+        assert_eq!(
+            format_iso8601(1_617_235_200_500),
+            "2021-04-01T00:00:00.500Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_iso8601() {
+        assert_eq!(parse_iso8601("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_iso8601("1970-01-01"), Some(0));
+        assert_eq!(parse_iso8601("1969-12-31T23:59:59.999Z"), Some(-1));
+        assert_eq!(
+            parse_iso8601("1900-01-01T00:00:00Z"),
+            Some(-2_208_988_800_000)
+        );
+        assert_eq!(
+            parse_iso8601("2021-04-01T00:00:00.5Z"),
+            Some(1_617_235_200_500)
+        );
+        assert_eq!(
+            parse_iso8601("2021-04-01T00:00:00.123456Z"),
+            Some(1_617_235_200_123)
+        );
+        assert_eq!(
+            parse_iso8601("2021-04-01T02:00:00+02:00"),
+            Some(1_617_235_200_000)
+        );
+        assert_eq!(
+            parse_iso8601("2021-03-31T22:30:00-01:30"),
+            Some(1_617_235_200_000)
+        );
+        assert_eq!(parse_iso8601("2021-04-01T00:00"), Some(1_617_235_200_000));
+
+        assert_eq!(parse_iso8601(""), None);
+        assert_eq!(parse_iso8601("2021-13-01"), None);
+        assert_eq!(parse_iso8601("2021-02-31"), None);
+        assert_eq!(parse_iso8601("2021-02-29"), None);
+        assert_eq!(parse_iso8601("2021-04-31"), None);
+        assert_eq!(parse_iso8601("2000-02-29"), Some(951_782_400_000));
+        assert_eq!(parse_iso8601("1900-02-29"), None);
+        assert_eq!(parse_iso8601("2021-04-01T24:00:00Z"), None);
+        assert_eq!(parse_iso8601("2021-04-01T00:00:00Zx"), None);
+    }
+
+    #[test]
+    fn test_iso8601_roundtrip() {
+        for millis in [0, -1, -86_400_001, 1_617_235_200_123, 253_402_300_799_999].iter() {
+            assert_eq!(parse_iso8601(&format_iso8601(*millis)), Some(*millis));
+        }
+    }
+}
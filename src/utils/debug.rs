@@ -40,6 +40,17 @@ macro_rules! isar (
         isar!(_internal: path, key, $isar, $($col => $schema),+);
     };
 
+    (memory: $isar:ident, $($col:ident => $schema:expr),+) => {
+        let cols = vec![$($schema,)+];
+        let schema = crate::schema::Schema::new(cols).unwrap();
+        let name = format!("memory-{}", rand::random::<u64>());
+        let $isar = crate::instance::IsarInstance::open_in_memory(&name, 10000000, schema).unwrap();
+        $(
+            let col = $schema;
+            let $col = $isar.get_collection_by_name(&col.name).unwrap();
+        )+
+    };
+
     ($isar:ident, $($col:ident => $schema:expr),+) => {
         let temp = tempfile::tempdir().unwrap();
         let path = temp.path().to_str().unwrap();
@@ -135,7 +146,9 @@ pub fn dump_db(cursor: &mut Cursor, prefix: Option<&[u8]>) -> HashSet<(Vec<u8>,
     cursor
         .iter_between(
             ByteKey::new(prefix.unwrap_or(&[])),
+            true,
             ByteKey::new(&upper),
+            true,
             false,
             true,
             |_, k, v| {
@@ -154,7 +167,9 @@ pub fn dump_db_oid(cursor: &mut Cursor, prefix: u16) -> HashSet<(Vec<u8>, Vec<u8
     cursor
         .iter_between(
             IntKey::new(prefix, MIN_ID),
+            true,
             IntKey::new(prefix, MAX_ID),
+            true,
             false,
             true,
             |_, k, v| {
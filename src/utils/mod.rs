@@ -1,5 +1,6 @@
 #![allow(clippy::missing_safety_doc)]
 
+pub(crate) mod date_time;
 #[macro_use]
 pub mod debug;
 
@@ -14,6 +15,20 @@ macro_rules! option (
     };
 );
 
+/// Case folding used for all case insensitive indexes and filters. It lowercases every char
+/// without context and folds the characters whose lowercase form is not caseless.
+pub(crate) fn fold_case(value: &str) -> String {
+    let mut folded = String::with_capacity(value.len());
+    for char in value.chars() {
+        match char {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            _ => folded.extend(char.to_lowercase()),
+        }
+    }
+    folded
+}
+
 /*#[inline]
 pub fn signed_to_unsigned(value: i64) -> u64 {
     let unsigned = unsafe { transmute::<i64, u64>(value) };
@@ -1,12 +1,18 @@
 use crate::object::isar_object::IsarObject;
 use crate::watch::isar_watchers::IsarWatchers;
-use crate::watch::watcher::Watcher;
+use crate::watch::watcher::{ChangeKind, ChangeWatcher, QueryChange, QueryChangeWatcher, Watcher};
 use hashbrown::HashMap;
 use std::sync::{Arc, MutexGuard};
 
 pub(crate) struct ChangeSet<'a> {
     watchers: MutexGuard<'a, IsarWatchers>,
     changed_watchers: HashMap<usize, Arc<Watcher>>,
+    changes: Vec<Option<(u16, i64, ChangeKind, Option<Vec<u8>>)>>,
+    change_positions: HashMap<(u16, i64), usize>,
+    count_deltas: HashMap<u16, i64>,
+    count_bases: HashMap<u16, i64>,
+    query_changes: Vec<(Arc<QueryChangeWatcher>, i64, bool, bool)>,
+    query_change_positions: HashMap<(usize, i64), usize>,
 }
 
 impl<'a> ChangeSet<'a> {
@@ -14,6 +20,12 @@ impl<'a> ChangeSet<'a> {
         ChangeSet {
             watchers,
             changed_watchers: HashMap::new(),
+            changes: vec![],
+            change_positions: HashMap::new(),
+            count_deltas: HashMap::new(),
+            count_bases: HashMap::new(),
+            query_changes: vec![],
+            query_change_positions: HashMap::new(),
         }
     }
 
@@ -54,9 +66,109 @@ impl<'a> ChangeSet<'a> {
         }
     }
 
-    pub fn notify_watchers(self) {
+    pub fn register_object_change(
+        &mut self,
+        col_id: u16,
+        oid: i64,
+        kind: ChangeKind,
+        object: IsarObject,
+    ) {
+        self.register_change(col_id, Some(oid), Some(object));
+        let cw = self.watchers.get_col_watchers(col_id);
+        if cw.has_count_watchers() {
+            let delta = if kind == ChangeKind::Put { 1 } else { -1 };
+            *self.count_deltas.entry(col_id).or_insert(0) += delta;
+        }
+        for (q, w) in &cw.query_change_watchers {
+            // the first change of an object tells whether it matched before the transaction
+            let matches = q.matches_wc_filter(oid, object);
+            let key = (w.get_id(), oid);
+            if let Some(position) = self.query_change_positions.get(&key) {
+                self.query_changes[*position].3 = kind == ChangeKind::Put && matches;
+            } else {
+                self.query_change_positions
+                    .insert(key, self.query_changes.len());
+                let matched_before = kind == ChangeKind::Delete && matches;
+                let matches_now = kind == ChangeKind::Put && matches;
+                self.query_changes
+                    .push((w.clone(), oid, matched_before, matches_now));
+            }
+        }
+        if cw.change_watchers.is_empty() {
+            return;
+        }
+        let bytes = if kind == ChangeKind::Put {
+            // updates remove the old version first and should only be reported as a put
+            if let Some(position) = self.change_positions.get(&(col_id, oid)) {
+                let change = &mut self.changes[*position];
+                if matches!(change, Some((_, _, ChangeKind::Delete, _))) {
+                    change.take();
+                }
+            }
+            Some(object.as_bytes().to_vec())
+        } else {
+            None
+        };
+        self.change_positions
+            .insert((col_id, oid), self.changes.len());
+        self.changes.push(Some((col_id, oid, kind, bytes)));
+    }
+
+    pub fn has_count_watchers(&mut self, col_id: u16) -> bool {
+        self.watchers.get_col_watchers(col_id).has_count_watchers()
+    }
+
+    /// Sets the object count of a collection. Changes registered afterwards are added to it.
+    pub fn register_count(&mut self, col_id: u16, count: u64) {
+        let delta = self.count_deltas.get(&col_id).copied().unwrap_or(0);
+        self.count_bases.insert(col_id, count as i64 - delta);
+    }
+
+    pub fn register_all(&mut self, col_id: u16) {
+        let cw = self.watchers.get_col_watchers(col_id);
+        let watchers = cw
+            .watchers
+            .iter()
+            .chain(cw.object_watchers.values().flatten())
+            .chain(cw.query_watchers.iter().map(|(_, w)| w));
+        for w in watchers {
+            self.changed_watchers.insert(w.get_id(), w.clone());
+        }
+    }
+
+    pub fn notify_watchers(mut self) {
         for watcher in self.changed_watchers.values() {
             watcher.notify();
         }
+        for (col_id, oid, kind, object) in self.changes.iter().flatten() {
+            let change_watchers: Vec<Arc<ChangeWatcher>> = self
+                .watchers
+                .get_col_watchers(*col_id)
+                .change_watchers
+                .clone();
+            for watcher in change_watchers {
+                watcher.notify(*oid, *kind, object.as_deref());
+            }
+        }
+        for (watcher, oid, matched_before, matches_now) in &self.query_changes {
+            let change = match (matched_before, matches_now) {
+                (false, true) => QueryChange::Added(*oid),
+                (true, false) => QueryChange::Removed(*oid),
+                (true, true) => QueryChange::Updated(*oid),
+                (false, false) => continue,
+            };
+            watcher.notify(change);
+        }
+        for (col_id, base) in &self.count_bases {
+            let delta = self.count_deltas.remove(col_id).unwrap_or(0);
+            self.watchers
+                .get_col_watchers(*col_id)
+                .update_count((base + delta) as u64);
+        }
+        for (col_id, delta) in &self.count_deltas {
+            for watcher in &self.watchers.get_col_watchers(*col_id).count_watchers {
+                watcher.add(*delta);
+            }
+        }
     }
 }
@@ -1,5 +1,8 @@
 use crate::query::Query;
-use crate::watch::watcher::{Watcher, WatcherCallback};
+use crate::watch::watcher::{
+    ChangeWatcher, ChangeWatcherCallback, CountWatcher, CountWatcherCallback, QueryChangeWatcher,
+    QueryChangeWatcherCallback, Watcher, WatcherCallback,
+};
 use crossbeam_channel::Receiver;
 use hashbrown::HashMap;
 use itertools::Itertools;
@@ -40,6 +43,9 @@ pub struct IsarCollectionWatchers {
     pub(super) watchers: Vec<Arc<Watcher>>,
     pub(super) object_watchers: HashMap<i64, Vec<Arc<Watcher>>>,
     pub(super) query_watchers: Vec<(Query, Arc<Watcher>)>,
+    pub(super) query_change_watchers: Vec<(Query, Arc<QueryChangeWatcher>)>,
+    pub(super) change_watchers: Vec<Arc<ChangeWatcher>>,
+    pub(super) count_watchers: Vec<Arc<CountWatcher>>,
 }
 
 impl IsarCollectionWatchers {
@@ -48,6 +54,9 @@ impl IsarCollectionWatchers {
             watchers: Vec::new(),
             object_watchers: HashMap::new(),
             query_watchers: Vec::new(),
+            query_change_watchers: Vec::new(),
+            change_watchers: Vec::new(),
+            count_watchers: Vec::new(),
         }
     }
 
@@ -101,4 +110,66 @@ impl IsarCollectionWatchers {
             .unwrap();
         self.query_watchers.remove(position);
     }
+
+    pub fn add_query_change_watcher(
+        &mut self,
+        watcher_id: usize,
+        query: Query,
+        callback: QueryChangeWatcherCallback,
+    ) {
+        let watcher = Arc::new(QueryChangeWatcher::new(watcher_id, callback));
+        self.query_change_watchers.push((query, watcher));
+    }
+
+    pub fn remove_query_change_watcher(&mut self, watcher_id: usize) {
+        let position = self
+            .query_change_watchers
+            .iter()
+            .position(|(_, w)| w.get_id() == watcher_id)
+            .unwrap();
+        self.query_change_watchers.remove(position);
+    }
+
+    pub fn add_change_watcher(&mut self, watcher_id: usize, callback: ChangeWatcherCallback) {
+        let watcher = Arc::new(ChangeWatcher::new(watcher_id, callback));
+        self.change_watchers.push(watcher);
+    }
+
+    pub fn remove_change_watcher(&mut self, watcher_id: usize) {
+        let position = self
+            .change_watchers
+            .iter()
+            .position(|w| w.get_id() == watcher_id)
+            .unwrap();
+        self.change_watchers.remove(position);
+    }
+
+    pub fn add_count_watcher(
+        &mut self,
+        watcher_id: usize,
+        count: u64,
+        callback: CountWatcherCallback,
+    ) {
+        let watcher = Arc::new(CountWatcher::new(watcher_id, count, callback));
+        self.count_watchers.push(watcher);
+    }
+
+    pub fn remove_count_watcher(&mut self, watcher_id: usize) {
+        let position = self
+            .count_watchers
+            .iter()
+            .position(|w| w.get_id() == watcher_id)
+            .unwrap();
+        self.count_watchers.remove(position);
+    }
+
+    pub(crate) fn has_count_watchers(&self) -> bool {
+        !self.count_watchers.is_empty()
+    }
+
+    pub(crate) fn update_count(&self, count: u64) {
+        for watcher in &self.count_watchers {
+            watcher.update(count);
+        }
+    }
 }
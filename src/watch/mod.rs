@@ -2,6 +2,8 @@ pub(crate) mod change_set;
 pub(crate) mod isar_watchers;
 pub(crate) mod watcher;
 
+pub use watcher::{ChangeKind, QueryChange};
+
 pub struct WatchHandle {
     stop_callback: Option<Box<dyn FnOnce()>>,
 }
@@ -1,5 +1,29 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
 pub type WatcherCallback = Box<dyn Fn() + Send + Sync + 'static>;
 
+pub type ChangeWatcherCallback =
+    Box<dyn Fn(i64, ChangeKind, Option<&[u8]>) + Send + Sync + 'static>;
+
+pub type CountWatcherCallback = Box<dyn Fn(u64) + Send + Sync + 'static>;
+
+pub type QueryChangeWatcherCallback = Box<dyn Fn(QueryChange) + Send + Sync + 'static>;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ChangeKind {
+    Put,
+    Delete,
+}
+
+/// How a changed object affects the results of a watched query. The membership before the
+/// transaction is compared with the membership after it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum QueryChange {
+    Added(i64),
+    Removed(i64),
+    Updated(i64),
+}
+
 pub(super) struct Watcher {
     id: usize,
     callback: WatcherCallback,
@@ -18,3 +42,75 @@ impl Watcher {
         (*self.callback)()
     }
 }
+
+pub(super) struct ChangeWatcher {
+    id: usize,
+    callback: ChangeWatcherCallback,
+}
+
+impl ChangeWatcher {
+    pub fn new(id: usize, callback: ChangeWatcherCallback) -> Self {
+        ChangeWatcher { id, callback }
+    }
+
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn notify(&self, oid: i64, kind: ChangeKind, object: Option<&[u8]>) {
+        (*self.callback)(oid, kind, object)
+    }
+}
+
+pub(super) struct QueryChangeWatcher {
+    id: usize,
+    callback: QueryChangeWatcherCallback,
+}
+
+impl QueryChangeWatcher {
+    pub fn new(id: usize, callback: QueryChangeWatcherCallback) -> Self {
+        QueryChangeWatcher { id, callback }
+    }
+
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn notify(&self, change: QueryChange) {
+        (*self.callback)(change)
+    }
+}
+
+pub(super) struct CountWatcher {
+    id: usize,
+    count: AtomicI64,
+    callback: CountWatcherCallback,
+}
+
+impl CountWatcher {
+    pub fn new(id: usize, count: u64, callback: CountWatcherCallback) -> Self {
+        CountWatcher {
+            id,
+            count: AtomicI64::new(count as i64),
+            callback,
+        }
+    }
+
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn add(&self, delta: i64) {
+        if delta != 0 {
+            let count = self.count.fetch_add(delta, Ordering::SeqCst) + delta;
+            (*self.callback)(count as u64)
+        }
+    }
+
+    pub fn update(&self, count: u64) {
+        let old_count = self.count.swap(count as i64, Ordering::SeqCst);
+        if old_count != count as i64 {
+            (*self.callback)(count)
+        }
+    }
+}